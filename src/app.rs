@@ -4,8 +4,13 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use anyhow::{Context, Result};
+
 use crate::{
-    utils::rss_client_ng::RssClient,
+    render::article::DateDisplay,
+    schema,
+    subscription::backup::{Backup, RestoreMode},
+    utils::{rss_client_ng::RssClient, scheduler::Scheduler},
     view::{self, View},
 };
 
@@ -15,6 +20,8 @@ use crate::{
 pub struct RSSucks {
     pub rss_client: RssClient,
     pub visuals: Rc<RefCell<egui::Visuals>>,
+    pub scheduler: Scheduler,
+    pub date_display: Rc<RefCell<DateDisplay>>,
 
     #[serde(skip)]
     pub view: RefCell<Option<Rc<Box<dyn View>>>>,
@@ -33,6 +40,10 @@ pub struct RSSucks {
 #[serde(default)]
 pub struct App {
     app: Rc<RSSucks>,
+    /// The saved state can't be read, e.g. it's saved by a newer version,
+    /// so it's not overwritten.
+    #[serde(skip)]
+    frozen_state: bool,
 }
 
 impl App {
@@ -61,14 +72,173 @@ impl App {
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
         if let Some(storage) = cc.storage {
-            let res: App = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
-            // Sync all feed
-            let _ = res.app.rss_client.try_start_sync_all();
+            let (res, legacy) = match App::try_load(storage) {
+                Ok(loaded) => loaded,
+                Err(err) => {
+                    log::error!("Failed to load the app state: {err:#}");
+                    let res = App {
+                        frozen_state: true,
+                        ..Default::default()
+                    };
+                    res.app.add_window(view::InfoWindow::new(
+                        "无法读取应用状态".to_owned(),
+                        format!("{err:#}\n\n本次运行不会保存设置和文章库，以免覆盖已保存的状态。"),
+                    ));
+                    (res, None)
+                }
+            };
+            // The library may still be in the state which can't be read,
+            // and would be lost if an empty one is stored instead.
+            if !res.frozen_state {
+                res.open_library(legacy);
+            }
+            // Sync all feed, unless the scheduler will take care of it.
+            if !res.app.scheduler.is_enabled() {
+                let _ = res.app.rss_client.try_start_sync_all();
+            }
             return res;
         }
 
         Default::default()
     }
+
+    /// Attempts to load the app state, upgrading it if it's saved by an older version.
+    /// Also returns the library in it if it's saved before the schema was versioned.
+    fn try_load(storage: &dyn eframe::Storage) -> Result<(Self, Option<legacy::Library>)> {
+        let Some(state) = storage.get_string(eframe::APP_KEY) else {
+            return Ok((Self::default(), None));
+        };
+        if serde_json::from_str::<serde_json::Value>(&state).is_err() {
+            // Saved as RON before the schema was versioned.
+            let app: App = ron::from_str(&state)
+                .context("Failed to read the app state of a former version")?;
+            let app = serde_json::to_value(app)?;
+            let library = legacy::try_library(&state)?;
+            return Ok((schema::APP_STATE.try_decode_value(0, app)?, Some(library)));
+        }
+        Ok((schema::APP_STATE.try_decode(state.as_bytes())?.0, None))
+    }
+}
+
+/// Library in the app state saved by former versions, which kept all of it there.
+mod legacy {
+    use anyhow::{Context, Result};
+
+    use crate::schema;
+    use crate::subscription::article::{Article, ArticleUuid};
+    use crate::subscription::feed::Feed;
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Deserialize)]
+    struct App<T> {
+        app: RSSucks<T>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct RSSucks<T> {
+        rss_client: RssClient<T>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct RssClient<T> {
+        feed: T,
+    }
+
+    /// Articles of the library, which are no longer saved with the rest of it,
+    /// read as JSON values to be upgraded.
+    #[derive(serde::Deserialize)]
+    struct Articles {
+        #[serde(default)]
+        articles_map: BTreeMap<ArticleUuid, serde_json::Value>,
+    }
+
+    /// The library with its articles.
+    pub type Library = (Feed, Vec<(ArticleUuid, Article)>);
+
+    /// Attempts to read the library out of the app state saved by a former version as RON.
+    pub fn try_library(state: &str) -> Result<Library> {
+        let feed: App<Feed> =
+            ron::from_str(state).context("Failed to read the library of a former version")?;
+        let articles: App<Articles> = ron::from_str(state)
+            .context("Failed to read the articles of the library of a former version")?;
+        log::info!("Moving the library from the app state to its own storage");
+        Ok((
+            feed.app.rss_client.feed,
+            articles
+                .app
+                .rss_client
+                .feed
+                .articles_map
+                .into_iter()
+                .filter_map(|(id, article)| {
+                    schema::ARTICLE
+                        .try_decode_value(0, article)
+                        .map_err(|err| log::warn!("Dropped an article of the library: {err:#}"))
+                        .ok()
+                        .map(|article| (id, article))
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl App {
+    /// Opens the library on disk, moving the library saved in the app state into it at first.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_library(&self, legacy: Option<legacy::Library>) {
+        use crate::subscription::storage::file::{FileStore, SharedFileStore};
+
+        let Some(dir) = eframe::storage_dir("RSSucks") else {
+            log::error!("No directory to store the library, which won't be saved");
+            return;
+        };
+        let result = FileStore::try_open(dir.join("library")).and_then(|(store, loaded)| {
+            self.app.rss_client.try_open_storage(
+                Box::new(SharedFileStore::new(store)),
+                loaded,
+                || legacy,
+            )
+        });
+        if let Err(err) = result {
+            self.library_failed(err);
+        }
+    }
+
+    /// Opens the library in IndexedDB, moving the library saved in the app state into it at first.
+    /// It's opened in the background, after which all entries are synchronized.
+    #[cfg(target_arch = "wasm32")]
+    fn open_library(&self, legacy: Option<legacy::Library>) {
+        use crate::subscription::storage::indexed_db::IndexedDbStore;
+
+        let app = Rc::clone(&self.app);
+        IndexedDbStore::open("RSSucks", move |result| {
+            let result = result.and_then(|(store, loaded)| {
+                app.rss_client
+                    .try_open_storage(Box::new(store), loaded, move || legacy)
+            });
+            if let Err(err) = result {
+                log::error!("Failed to open the library: {err:#}");
+                app.add_window(view::InfoWindow::new(
+                    "无法打开文章库".to_owned(),
+                    format!("{err:#}\n\n本次运行的改动不会被保存。"),
+                ));
+            }
+            if !app.scheduler.is_enabled() {
+                let _ = app.rss_client.try_start_sync_all();
+            }
+        });
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl App {
+    fn library_failed(&self, err: anyhow::Error) {
+        log::error!("Failed to open the library: {err:#}");
+        self.app.add_window(view::InfoWindow::new(
+            "无法打开文章库".to_owned(),
+            format!("{err:#}\n\n本次运行的改动不会被保存。"),
+        ));
+    }
 }
 
 impl RSSucks {
@@ -80,14 +250,73 @@ impl RSSucks {
     }
 
     pub fn import_feed(&mut self, feed: crate::subscription::feed::Feed) {
-        self.rss_client = RssClient::new(feed);
+        self.rss_client.replace_feed(feed);
+    }
+
+    /// Attempts to write a backup archive of the library and the settings.
+    pub fn try_backup(&self) -> Result<Vec<u8>> {
+        let settings = schema::APP_STATE.try_encode_value(&AppState { app: self })?;
+        Backup::try_archive(&self.rss_client.get().borrow(), settings)
+    }
+
+    /// Attempts to restore a backup, returns the numbers of entries and articles added.
+    pub fn try_restore(&self, backup: Backup, mode: RestoreMode) -> Result<(usize, usize)> {
+        let added = match mode {
+            RestoreMode::Replace => {
+                let (settings, _): (AppState<RSSucks>, _) =
+                    schema::APP_STATE.try_decode_from(backup.settings)?;
+                self.restore_settings(&settings.app);
+                let added = (
+                    backup.library.get_all_entry_ids().len(),
+                    backup.articles.len(),
+                );
+                self.rss_client
+                    .replace_library(backup.library, backup.articles);
+                added
+            }
+            RestoreMode::Merge => self
+                .rss_client
+                .merge_library(backup.library, backup.articles),
+        };
+        self.rss_client.try_commit()?;
+        Ok(added)
+    }
+
+    fn restore_settings(&self, settings: &RSSucks) {
+        self.visuals.replace(settings.visuals.borrow().clone());
+        self.scheduler
+            .settings()
+            .replace(settings.scheduler.settings().borrow().clone());
+        self.scheduler.reschedule();
+        self.rss_client
+            .sync_limits()
+            .replace(*settings.rss_client.sync_limits().borrow());
+        self.date_display.replace(*settings.date_display.borrow());
     }
 }
 
+/// App state in the layout of [`App`], for backups.
+#[derive(serde::Deserialize, serde::Serialize)]
+struct AppState<T> {
+    app: T,
+}
+
 impl eframe::App for App {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+        if let Err(err) = self.app.rss_client.try_commit() {
+            log::error!("Failed to commit the library: {err:#}");
+        }
+        if self.frozen_state {
+            return;
+        }
+        match schema::APP_STATE.try_encode(self) {
+            Ok(state) => storage.set_string(
+                eframe::APP_KEY,
+                String::from_utf8_lossy(&state).into_owned(),
+            ),
+            Err(err) => log::error!("Failed to save the app state: {err:#}"),
+        }
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
@@ -95,6 +324,28 @@ impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui_extras::install_image_loaders(ctx);
 
+        if let Some(after) = self.app.scheduler.tick(&self.app.rss_client) {
+            ctx.request_repaint_after(after);
+        }
+        if self.app.rss_client.pump_sync_queue() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(200));
+        }
+        for (id, from) in self.app.rss_client.apply_feed_moves() {
+            if let Some(entry) = self.app.rss_client.get_entry(&id) {
+                let entry = entry.get();
+                let entry = entry.borrow();
+                self.app.add_window(view::InfoWindow::new(
+                    "订阅已迁移".to_owned(),
+                    format!(
+                        "「{}」已永久迁移，订阅地址已更新：\n{}\n→ {}",
+                        entry.title(),
+                        from,
+                        entry.xml_url
+                    ),
+                ));
+            }
+        }
+
         view::LeftSidePanel::new(&self.app).show(ctx);
 
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -104,6 +355,7 @@ impl eframe::App for App {
         });
 
         if let Some(next_view) = self.app.next_view.replace(None) {
+            self.app.rss_client.set_sync_focus(next_view.sync_focus());
             self.app.view.borrow_mut().replace(next_view);
         };
 
@@ -131,3 +383,39 @@ impl RSSucks {
         self
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::App;
+
+    #[derive(Default)]
+    struct Storage(HashMap<String, String>);
+
+    impl eframe::Storage for Storage {
+        fn get_string(&self, key: &str) -> Option<String> {
+            self.0.get(key).cloned()
+        }
+
+        fn set_string(&mut self, key: &str, value: String) {
+            self.0.insert(key.to_owned(), value);
+        }
+
+        fn flush(&mut self) {}
+    }
+
+    #[test]
+    fn unreadable_state() {
+        let mut storage = Storage::default();
+        assert!(App::try_load(&storage).unwrap().1.is_none());
+
+        // A library of a former version which can't be read any more.
+        eframe::Storage::set_string(
+            &mut storage,
+            eframe::APP_KEY,
+            "(app: (rss_client: (feed: (entries: 42))))".to_owned(),
+        );
+        assert!(App::try_load(&storage).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Formatting dates of articles, which are stored in UTC, for display.
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+const ABSOLUTE_FORMAT: &str = "%Y/%m/%d %H:%M";
+
+/// How dates are displayed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DateStyle {
+    /// Such as "2023/11/08 17:00".
+    #[default]
+    Absolute,
+    /// Such as "3 小时前", and absolute for dates over a week ago or in the future.
+    Relative,
+}
+
+/// Timezone which absolute dates are displayed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DisplayTimezone {
+    #[default]
+    Local,
+    Utc,
+    /// Offset east of UTC in minutes.
+    Fixed(i32),
+}
+
+/// Settings of displaying dates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DateDisplay {
+    pub style: DateStyle,
+    pub timezone: DisplayTimezone,
+}
+
+impl DisplayTimezone {
+    /// Describes the timezone for settings.
+    pub fn describe(&self) -> String {
+        match self {
+            DisplayTimezone::Local => "本地时区".to_owned(),
+            DisplayTimezone::Utc => "UTC".to_owned(),
+            DisplayTimezone::Fixed(minutes) => {
+                let sign = if *minutes < 0 { '-' } else { '+' };
+                let minutes = minutes.abs();
+                format!("UTC{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+            }
+        }
+    }
+
+    /// Returns the offset from UTC of the timezone at the date.
+    fn offset_at(&self, date: DateTime<Utc>) -> FixedOffset {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        match self {
+            DisplayTimezone::Local => *date.with_timezone(&Local).offset(),
+            DisplayTimezone::Utc => utc,
+            DisplayTimezone::Fixed(minutes) => {
+                FixedOffset::east_opt(minutes.saturating_mul(60)).unwrap_or(utc)
+            }
+        }
+    }
+
+    /// Returns the day of the date in the timezone.
+    pub fn day_of(&self, date: DateTime<Utc>) -> NaiveDate {
+        date.with_timezone(&self.offset_at(date)).date_naive()
+    }
+
+    /// Returns when the day of the date starts in the timezone.
+    pub fn start_of_day(&self, date: DateTime<Utc>) -> DateTime<Utc> {
+        let offset = self.offset_at(date);
+        offset
+            .from_local_datetime(&self.day_of(date).and_hms_opt(0, 0, 0).unwrap())
+            .single()
+            .map_or(date, |start| start.with_timezone(&Utc))
+    }
+
+    fn format(&self, date: DateTime<Utc>) -> String {
+        match self {
+            DisplayTimezone::Local => date.with_timezone(&Local).format(ABSOLUTE_FORMAT),
+            DisplayTimezone::Utc => date.format(ABSOLUTE_FORMAT),
+            DisplayTimezone::Fixed(minutes) => {
+                match FixedOffset::east_opt(minutes.saturating_mul(60)) {
+                    Some(offset) => date.with_timezone(&offset).format(ABSOLUTE_FORMAT),
+                    None => date.format(ABSOLUTE_FORMAT),
+                }
+            }
+        }
+        .to_string()
+    }
+}
+
+impl DateDisplay {
+    /// Formats the date by the settings at the time.
+    pub fn format(&self, date: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        let elapsed = now - date;
+        if self.style == DateStyle::Absolute
+            || elapsed < Duration::zero()
+            || elapsed >= Duration::weeks(1)
+        {
+            return self.timezone.format(date);
+        }
+        if elapsed < Duration::minutes(1) {
+            "刚刚".to_owned()
+        } else if elapsed < Duration::hours(1) {
+            format!("{} 分钟前", elapsed.num_minutes())
+        } else if elapsed < Duration::days(1) {
+            format!("{} 小时前", elapsed.num_hours())
+        } else {
+            format!("{} 天前", elapsed.num_days())
+        }
+    }
+
+    /// Describes the day of the date, such as "今天" and "2023/11/08", which articles are
+    /// grouped by.
+    pub fn day_label(&self, date: DateTime<Utc>, now: DateTime<Utc>) -> String {
+        let day = self.timezone.day_of(date);
+        let today = self.timezone.day_of(now);
+        if day == today {
+            "今天".to_owned()
+        } else if today.pred_opt() == Some(day) {
+            "昨天".to_owned()
+        } else {
+            day.format("%Y/%m/%d").to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DateDisplay, DateStyle, DisplayTimezone};
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn format() {
+        let date = Utc.with_ymd_and_hms(2023, 11, 8, 9, 0, 0).unwrap();
+        let absolute = DateDisplay {
+            style: DateStyle::Absolute,
+            timezone: DisplayTimezone::Fixed(8 * 60),
+        };
+        assert_eq!(absolute.format(date, date), "2023/11/08 17:00");
+        let utc = DateDisplay {
+            timezone: DisplayTimezone::Utc,
+            ..absolute
+        };
+        assert_eq!(utc.format(date, date), "2023/11/08 09:00");
+
+        let relative = DateDisplay {
+            style: DateStyle::Relative,
+            ..absolute
+        };
+        assert_eq!(relative.format(date, date + Duration::seconds(30)), "刚刚");
+        assert_eq!(relative.format(date, date + Duration::hours(3)), "3 小时前");
+        assert_eq!(relative.format(date, date + Duration::days(2)), "2 天前");
+        // Long ago or in the future.
+        assert_eq!(
+            relative.format(date, date + Duration::weeks(2)),
+            "2023/11/08 17:00"
+        );
+        assert_eq!(
+            relative.format(date, date - Duration::hours(1)),
+            "2023/11/08 17:00"
+        );
+        assert_eq!(
+            DisplayTimezone::Fixed(-(9 * 60 + 30)).describe(),
+            "UTC-09:30"
+        );
+    }
+
+    #[test]
+    fn days() {
+        // 2023/11/09 01:00 at UTC+8.
+        let now = Utc.with_ymd_and_hms(2023, 11, 8, 17, 0, 0).unwrap();
+        let display = DateDisplay {
+            style: DateStyle::Absolute,
+            timezone: DisplayTimezone::Fixed(8 * 60),
+        };
+        assert_eq!(
+            display.timezone.start_of_day(now),
+            Utc.with_ymd_and_hms(2023, 11, 8, 16, 0, 0).unwrap()
+        );
+        assert_eq!(display.day_label(now - Duration::minutes(30), now), "今天");
+        assert_eq!(display.day_label(now - Duration::hours(2), now), "昨天");
+        assert_eq!(
+            display.day_label(now - Duration::days(2), now),
+            "2023/11/07"
+        );
+        let utc = DateDisplay {
+            timezone: DisplayTimezone::Utc,
+            ..display
+        };
+        assert_eq!(utc.day_label(now - Duration::hours(2), now), "今天");
+    }
+}
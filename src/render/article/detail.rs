@@ -1,21 +1,65 @@
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
+use chrono::{DateTime, Utc};
 use egui::{Image, Margin, RichText, Rounding, Widget};
 
-use crate::{utils::rss_client_ng::ArticleId, view::View, RSSucks};
+use crate::{
+    subscription::diff::{self, Change},
+    utils::rss_client_ng::ArticleId,
+    view::View,
+    RSSucks,
+};
 
-use super::{absolute_url, Builder, Element, ElementType};
+use super::{absolute_url, flag_toggles, tag_editor, Builder, Element, ElementType};
 
 pub struct Detail {
     entry_title: Option<String>,
     title: String,
     link: Option<String>,
-    updated: Option<String>,
-    published: Option<String>,
+    updated: Option<DateTime<Utc>>,
+    published: Option<DateTime<Utc>>,
+    first_seen: Option<DateTime<Utc>>,
     elements: Option<Vec<Element>>,
     app: Rc<RSSucks>,
     parent_view: Option<Rc<Box<dyn View>>>,
     article_id: ArticleId,
+    revision_changes: Option<(Vec<Change>, Vec<Change>)>,
+    // Name of the tag being typed
+    new_tag: RefCell<String>,
+    // Is the article marked read since it's opened?
+    marked_read: Cell<bool>,
+}
+
+// Lays out a diff, with removed words struck through and added words underlined.
+fn diff_layout(ui: &egui::Ui, changes: &[Change]) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let visuals = &ui.style().visuals;
+    for change in changes {
+        let (text, format) = match change {
+            Change::Same(text) => (
+                text,
+                egui::TextFormat::simple(font_id.clone(), visuals.text_color()),
+            ),
+            Change::Removed(text) => (
+                text,
+                egui::TextFormat {
+                    strikethrough: egui::Stroke::new(1.0, visuals.error_fg_color),
+                    ..egui::TextFormat::simple(font_id.clone(), visuals.error_fg_color)
+                },
+            ),
+            Change::Added(text) => (
+                text,
+                egui::TextFormat {
+                    underline: egui::Stroke::new(1.0, visuals.warn_fg_color),
+                    ..egui::TextFormat::simple(font_id.clone(), visuals.warn_fg_color)
+                },
+            ),
+        };
+        job.append(text, 0.0, format);
+    }
+    job
 }
 
 impl<'a> From<Builder<'a>> for Detail {
@@ -24,21 +68,32 @@ impl<'a> From<Builder<'a>> for Detail {
             entry_title: value.entry_title.map(|s| s.to_owned()),
             title: value.title.to_owned(),
             link: value.link.map(|s| s.to_owned()),
-            updated: value.updated.map(|s| s.to_owned()),
-            published: value.published.map(|s| s.to_owned()),
+            updated: value.updated,
+            published: value.published,
+            first_seen: value.first_seen,
             elements: value.elements,
             app: value.app,
             parent_view: value.parent_view,
             article_id: value.article_id,
+            revision_changes: value.revision_changes,
+            new_tag: RefCell::new(String::new()),
+            marked_read: Cell::new(false),
         }
     }
 }
 
 impl Widget for &Detail {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
-        if let Some(article) = self.app.rss_client.get_article_by_id(&self.article_id) {
-            if let Ok(mut article) = article.get().lock() {
-                article.unread = false;
+        // Marked read once it's opened, so that it can be marked unread again.
+        if !self.marked_read.replace(true) {
+            if let Some(article) = self.app.rss_client.get_article_by_id(&self.article_id) {
+                let unseen = article
+                    .get()
+                    .lock()
+                    .is_ok_and(|article| article.unread || article.unseen_revision().is_some());
+                if unseen {
+                    self.app.rss_client.mark_article_read(&self.article_id);
+                }
             }
         }
         ui.allocate_ui(ui.available_size(), |ui| {
@@ -63,11 +118,14 @@ impl Widget for &Detail {
                         .outer_margin(Margin::same(16.0))
                         // .stroke(ui.style().visuals.widgets.noninteractive.bg_stroke)
                         .show(ui, |ui| {
-                            if ui.button("⬅ 返回").clicked() {
-                                if let Some(view) = &self.parent_view {
-                                    self.app.set_view(Rc::clone(view));
+                            ui.horizontal(|ui| {
+                                if ui.button("⬅ 返回").clicked() {
+                                    if let Some(view) = &self.parent_view {
+                                        self.app.set_view(Rc::clone(view));
+                                    }
                                 }
-                            }
+                                flag_toggles(ui, &self.app, &self.article_id);
+                            });
                             const HEADER_LARGE_TEXT_SIZE: f32 = 32.0;
                             const HEADER_SMALL_TEXT_SIZE: f32 = 12.0;
                             ui.spacing_mut().item_spacing = egui::vec2(0.0, 2.0);
@@ -91,28 +149,60 @@ impl Widget for &Detail {
                             // publish information
                             ui.horizontal_wrapped(|ui| {
                                 ui.add_space(4.0);
-                                // entry_title: Option<String>,
-                                // updated: Option<String>,
-                                // published: Option<String>,
+                                let date_display = *self.app.date_display.borrow();
+                                let now = Utc::now();
                                 if let Some(entry_title) = &self.entry_title {
                                     ui.label(
                                         RichText::new(entry_title).size(HEADER_SMALL_TEXT_SIZE),
                                     );
                                 }
-                                if let Some(published) = &self.published {
-                                    ui.label(
-                                        RichText::new("\tpublished at ")
-                                            .size(HEADER_SMALL_TEXT_SIZE),
-                                    );
-                                    ui.label(RichText::new(published).size(HEADER_SMALL_TEXT_SIZE));
-                                }
-                                if let Some(updated) = &self.updated {
-                                    ui.label(
-                                        RichText::new("\tupdated at ").size(HEADER_SMALL_TEXT_SIZE),
-                                    );
-                                    ui.label(RichText::new(updated).size(HEADER_SMALL_TEXT_SIZE));
+                                let dates = [
+                                    ("\tpublished at ", self.published),
+                                    ("\tupdated at ", self.updated),
+                                    ("\tfirst seen at ", self.first_seen),
+                                ];
+                                for (label, date) in dates {
+                                    if let Some(date) = date {
+                                        ui.label(RichText::new(label).size(HEADER_SMALL_TEXT_SIZE));
+                                        ui.label(
+                                            RichText::new(date_display.format(date, now))
+                                                .size(HEADER_SMALL_TEXT_SIZE),
+                                        );
+                                    }
                                 }
                             });
+
+                            // tags
+                            ui.horizontal_wrapped(|ui| {
+                                ui.spacing_mut().item_spacing.x = 4.0;
+                                ui.add_space(4.0);
+                                tag_editor(
+                                    ui,
+                                    &self.app,
+                                    &self.article_id,
+                                    &mut self.new_tag.borrow_mut(),
+                                );
+                            });
+
+                            // changes since the version read at last
+                            if let Some((title_changes, summary_changes)) = &self.revision_changes {
+                                ui.add_space(8.0);
+                                ui.label(
+                                    RichText::new("⟳ 这篇文章在你上次阅读后更新过")
+                                        .color(ui.visuals().warn_fg_color),
+                                );
+                                ui.collapsing("查看改动", |ui| {
+                                    ui.spacing_mut().item_spacing = egui::vec2(0.0, 8.0);
+                                    if diff::is_changed(title_changes) {
+                                        ui.label(RichText::new("标题").strong());
+                                        ui.label(diff_layout(ui, title_changes));
+                                    }
+                                    if diff::is_changed(summary_changes) {
+                                        ui.label(RichText::new("摘要").strong());
+                                        ui.label(diff_layout(ui, summary_changes));
+                                    }
+                                });
+                            }
                         });
                     // ui.separator();
 
@@ -240,10 +330,10 @@ impl Widget for &Detail {
                                                                     },
                                                                 )
                                                                 .max_height(
-                                                                    match element.image_tuple.2 {
-                                                                        Some(height) => height,
-                                                                        None => f32::INFINITY,
-                                                                    },
+                                                                    element
+                                                                        .image_tuple
+                                                                        .2
+                                                                        .unwrap_or(f32::INFINITY),
                                                                 )
                                                                 .rounding(
                                                                     Rounding::ZERO.at_least(10.0),
@@ -1,18 +1,23 @@
+mod date;
 mod detail;
 mod preview;
 
 use std::rc::Rc;
 
-use crate::subscription::article::Article;
+use crate::subscription::article::{Article, Flag};
+use crate::subscription::diff::{self, Change};
+use crate::subscription::tag::TagColor;
 use crate::utils::rss_client_ng::ArticleId;
 use crate::view::View;
 use crate::RSSucks;
+use chrono::{DateTime, Utc};
 use ego_tree::iter::Edge;
-use egui::RichText;
+use egui::{Color32, RichText};
 use lazy_static::lazy_static;
 use regex::Regex;
 use scraper;
 
+pub use self::date::{DateDisplay, DateStyle, DisplayTimezone};
 pub use self::detail::Detail;
 pub use self::preview::Preview;
 
@@ -109,8 +114,10 @@ pub struct Builder<'a> {
     entry_title: Option<String>,
     title: &'a str,
     link: Option<&'a str>,
-    updated: Option<&'a str>,
-    published: Option<&'a str>,
+    updated: Option<DateTime<Utc>>,
+    published: Option<DateTime<Utc>>,
+    // When the article is first seen, if it's undated
+    first_seen: Option<DateTime<Utc>>,
     elements: Option<Vec<Element>>,
     // For preview
     max_rows: usize,
@@ -120,6 +127,140 @@ pub struct Builder<'a> {
     article_id: ArticleId,
     parent_view: Option<Rc<Box<dyn View>>>,
     app: Rc<RSSucks>,
+    // Changes of title and text since the version read at last
+    revision_changes: Option<(Vec<Change>, Vec<Change>)>,
+}
+
+/// Returns the icon and the description of the flag.
+pub fn describe_flag(flag: Flag) -> (&'static str, &'static str) {
+    match flag {
+        Flag::Starred => ("⭐", "星标"),
+        Flag::ReadLater => ("🕒", "稍后读"),
+        Flag::Archived => ("📦", "存档（不会被清理）"),
+    }
+}
+
+// Adds toggles of whether the article is unread and of its flags,
+// returns whether any of them is hovered.
+fn flag_toggles(ui: &mut egui::Ui, app: &RSSucks, article_id: &ArticleId) -> bool {
+    let Some(article) = app.rss_client.get_article_by_id(article_id) else {
+        return false;
+    };
+    let unread = article.get().lock().unwrap().unread;
+    let flags = Flag::ALL.map(|flag| (flag, article.get().lock().unwrap().flag(flag)));
+    let response = ui.selectable_label(unread, "●").on_hover_text(if unread {
+        "标为已读"
+    } else {
+        "标为未读"
+    });
+    if response.clicked() {
+        if unread {
+            app.rss_client.mark_article_read(article_id);
+        } else {
+            app.rss_client.mark_article_unread(article_id);
+        }
+    }
+    let mut hovered = response.hovered();
+    for (flag, set) in flags {
+        let (icon, description) = describe_flag(flag);
+        let response = ui.selectable_label(set, icon).on_hover_text(description);
+        if response.clicked() {
+            app.rss_client.set_article_flag(article_id, flag, !set);
+        }
+        hovered |= response.hovered();
+    }
+    hovered
+}
+
+pub fn tag_color32(color: TagColor) -> Color32 {
+    let [r, g, b] = color.0;
+    Color32::from_rgb(r, g, b)
+}
+
+// Adds a chip of the tag, in its color.
+fn tag_chip(ui: &mut egui::Ui, app: &RSSucks, name: &str) -> egui::Response {
+    let color = tag_color32(app.rss_client.tag_color(name));
+    // Dark text on light colors, and light text on dark ones.
+    let [r, g, b, _] = color.to_array();
+    let luma = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    let text_color = if luma > 150.0 {
+        Color32::BLACK
+    } else {
+        Color32::WHITE
+    };
+    ui.label(
+        RichText::new(format!(" {name} "))
+            .background_color(color)
+            .color(text_color),
+    )
+}
+
+// Adds chips of the tags of the article.
+fn tag_chips(ui: &mut egui::Ui, app: &RSSucks, article_id: &ArticleId) {
+    let Some(article) = app.rss_client.get_article_by_id(article_id) else {
+        return;
+    };
+    let tags = article.get().lock().unwrap().tags.clone();
+    for name in tags {
+        tag_chip(ui, app, &name);
+    }
+}
+
+// Adds chips of the tags of the article which can be removed,
+// and an editor which tags the article with a new tag or an existing one.
+fn tag_editor(ui: &mut egui::Ui, app: &RSSucks, article_id: &ArticleId, new_tag: &mut String) {
+    let Some(article) = app.rss_client.get_article_by_id(article_id) else {
+        return;
+    };
+    let tags = article.get().lock().unwrap().tags.clone();
+    for name in &tags {
+        tag_chip(ui, app, name);
+        if ui.small_button("×").on_hover_text("移除标签").clicked() {
+            app.rss_client.remove_article_tag(article_id, name);
+        }
+    }
+    let response = ui.add(
+        egui::TextEdit::singleline(new_tag)
+            .hint_text("新标签")
+            .desired_width(96.0),
+    );
+    let entered = response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+    if (ui.small_button("＋").on_hover_text("添加标签").clicked() || entered)
+        && app
+            .rss_client
+            .try_add_article_tag(article_id, new_tag)
+            .is_ok()
+    {
+        new_tag.clear();
+    }
+    let existing: Vec<_> = app
+        .rss_client
+        .list_tag()
+        .into_iter()
+        .filter(|(name, _)| !tags.contains(name))
+        .collect();
+    if !existing.is_empty() {
+        ui.menu_button("🏷", |ui| {
+            for (name, _) in existing {
+                if ui.button(&name).clicked() {
+                    let _ = app.rss_client.try_add_article_tag(article_id, &name);
+                    ui.close_menu();
+                }
+            }
+        })
+        .response
+        .on_hover_text("已有的标签");
+    }
+}
+
+// Extracts the plain text of an HTML fragment.
+fn html_to_text(html: &str) -> String {
+    let fragment = scraper::Html::parse_fragment(html);
+    let text = fragment.root_element().text().collect::<Vec<_>>().join(" ");
+    CONTINUOUS_WHITESPACE_PATTERN
+        .replace_all(&text, " ")
+        .trim()
+        .to_owned()
 }
 
 impl<'a> Builder<'a> {
@@ -130,11 +271,12 @@ impl<'a> Builder<'a> {
         app: Rc<RSSucks>,
     ) -> Self {
         let feed = app.rss_client.get();
-        let updated = article.updated.as_deref();
-        let published = article.published.as_deref();
+        let updated = article.updated;
+        let published = article.published;
+        let first_seen = article.date().is_none().then_some(article.first_seen);
         let title = &article.title;
-        let link = article.links.first().map(|link| link.as_str());
-        let summary = article.summary.as_ref();
+        let link = article.alternate_link();
+        let text = article.text();
         let _catrgories = &article.categories;
         let entry_title = article.belong_to.and_then(|entry_uuid| {
             feed.borrow()
@@ -143,8 +285,17 @@ impl<'a> Builder<'a> {
                 .map(|entry_rc| entry_rc.borrow().title().to_owned())
         });
 
-        let (elements, fulltext) = if let Some(summary) = summary {
-            let fragment = scraper::Html::parse_fragment(summary);
+        let revision_changes = article.unseen_revision().map(|revision| {
+            let plain_text =
+                |text: Option<&String>| text.map(|text| html_to_text(text)).unwrap_or_default();
+            (
+                diff::diff_words(&revision.title, title),
+                diff::diff_words(&plain_text(revision.text()), &plain_text(text)),
+            )
+        });
+
+        let (elements, fulltext) = if let Some(text) = text {
+            let fragment = scraper::Html::parse_fragment(text);
             let mut dom_stack: Vec<String> = Vec::new();
             let mut elements = Vec::new();
             let mut fulltext = String::new();
@@ -251,6 +402,7 @@ impl<'a> Builder<'a> {
             link,
             updated,
             published,
+            first_seen,
             elements,
             max_rows: 3,
             break_anywhere: true,
@@ -259,6 +411,7 @@ impl<'a> Builder<'a> {
             article_id,
             app,
             parent_view,
+            revision_changes,
         }
     }
 
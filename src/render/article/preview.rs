@@ -5,7 +5,7 @@ use uuid::Uuid;
 
 use crate::{utils::rss_client_ng::ArticleId, RSSucks};
 
-use super::{absolute_url, Builder, Element, ElementType};
+use super::{absolute_url, flag_toggles, tag_chips, Builder, Element, ElementType};
 
 pub struct Preview {
     // rendering previews needs ownership
@@ -17,6 +17,8 @@ pub struct Preview {
     fulltext: Option<String>,
     max_images_num: usize,
     title: String,
+    /// Title of the subscription which the article comes from.
+    entry_title: Option<String>,
     link: Option<String>,
     pub article_id: ArticleId,
     app: Rc<RSSucks>,
@@ -33,6 +35,7 @@ impl<'a> From<Builder<'a>> for Preview {
             fulltext: value.fulltext.clone(),
             max_images_num: 3,
             title: value.title.to_owned(),
+            entry_title: value.entry_title,
             link: value.link.map(|l| l.to_owned()),
             article_id: value.article_id,
             app: value.app,
@@ -45,6 +48,8 @@ impl Widget for &Preview {
         ui.allocate_ui(ui.available_size(), |ui| {
             let mut child_ui =
                 ui.child_ui_with_id_source(ui.max_rect(), *ui.layout(), self.scroll_area_id);
+            // The card is not clicked when its toggles are.
+            let mut toggles_hovered = false;
             egui::Frame::none()
                 .inner_margin(Margin::same(32.0))
                 .outer_margin(Margin::symmetric(
@@ -61,6 +66,9 @@ impl Widget for &Preview {
                     // Set the spacing between header and content.
                     ui.spacing_mut().item_spacing = egui::vec2(10.0, 10.0);
                     ui.style_mut().override_text_style = Some(egui::TextStyle::Body);
+                    if let Some(entry_title) = &self.entry_title {
+                        ui.label(RichText::new(format!("📰 {entry_title}")).small().weak());
+                    }
                     // Render title:
                     ui.label(
                         RichText::new(&self.title).size(20.0).strong().color(
@@ -68,7 +76,7 @@ impl Widget for &Preview {
                                 .app
                                 .rss_client
                                 .get_article_by_id(&self.article_id)
-                                .map_or(false, |article| {
+                                .is_some_and(|article| {
                                     article.get().lock().is_ok_and(|article| article.unread)
                                 })
                             {
@@ -79,6 +87,11 @@ impl Widget for &Preview {
                         ),
                     );
 
+                    ui.horizontal(|ui| {
+                        toggles_hovered = flag_toggles(ui, &self.app, &self.article_id);
+                        tag_chips(ui, &self.app, &self.article_id);
+                    });
+
                     // Render content:
                     // First, render text.
                     let mut job = egui::text::LayoutJob::single_section(
@@ -159,7 +172,11 @@ impl Widget for &Preview {
                     .into(),
                 ),
                 child_ui.id(),
-                Sense::click(),
+                if toggles_hovered {
+                    Sense::hover()
+                } else {
+                    Sense::click()
+                },
             );
             ui.allocate_space(child_ui.min_size());
             response
@@ -0,0 +1,260 @@
+//! Schema versions of the persisted state, and migrations which upgrade older snapshots.
+//!
+//! Snapshots are saved as JSON in an envelope with their schema versions.
+//! Those without the envelope were saved before the schema was versioned, and are of version 0.
+//! A migration is added for each change of the layout, which bumps the version.
+use anyhow::{bail, Context, Result};
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// Upgrades a snapshot from a version to the next one.
+pub type Migration = fn(&mut Value) -> Result<()>;
+
+/// Schema of something persisted, whose version is the number of its migrations.
+pub struct Schema {
+    name: &'static str,
+    migrations: &'static [Migration],
+}
+
+/// Snapshots of version 0 have the same layout as version 1, only without the envelope.
+fn unversioned(_: &mut Value) -> Result<()> {
+    Ok(())
+}
+
+/// Snapshots of this version have the same layout as the former one,
+/// whose version is bumped along with the others stored with it.
+fn unchanged(_: &mut Value) -> Result<()> {
+    Ok(())
+}
+
+/// Version 2 keeps links of articles with their `rel`, type and title.
+fn links_to_objects(value: &mut Value) -> Result<()> {
+    let links = match value.get_mut("links") {
+        Some(Value::Array(links)) => links,
+        _ => bail!("Links are not a list"),
+    };
+    for link in links {
+        if let Value::String(href) = link {
+            *link = serde_json::json!({ "href": href });
+        }
+    }
+    Ok(())
+}
+
+/// Version 2 keeps the content of articles in their bodies along with the summary,
+/// which was the whole body.
+fn summary_to_body(value: &mut Value) -> Result<()> {
+    if let Value::String(summary) = value {
+        *value = serde_json::json!({ "summary": summary });
+    }
+    Ok(())
+}
+
+/// Parses a date formatted in the local timezone by former versions.
+fn local_date_to_utc(value: &Value) -> Value {
+    value
+        .as_str()
+        .and_then(|date| NaiveDateTime::parse_from_str(date, "%Y/%m/%d %H:%M").ok())
+        .and_then(|date| Local.from_local_datetime(&date).earliest())
+        .map(|date| serde_json::json!(date.with_timezone(&Utc)))
+        .unwrap_or_default()
+}
+
+/// Version 3 keeps dates of articles in UTC rather than formatted in the local timezone,
+/// and when they are first seen.
+fn typed_dates(value: &mut Value) -> Result<()> {
+    let Some(article) = value.as_object_mut() else {
+        bail!("An article is not an object");
+    };
+    // The date, which is dropped, is the precise one of `updated`,
+    // or of `published` if it's never updated.
+    let date = article.remove("date").unwrap_or_default();
+    let updated = article.get("updated").cloned().unwrap_or_default();
+    let published = article.get("published").cloned().unwrap_or_default();
+    let (updated, published) = match (updated.is_null(), date.is_null()) {
+        (false, false) => (date.clone(), local_date_to_utc(&published)),
+        (true, false) => (Value::Null, date.clone()),
+        (_, true) => (local_date_to_utc(&updated), local_date_to_utc(&published)),
+    };
+    let first_seen = match date {
+        Value::Null => serde_json::json!(Utc::now()),
+        date => date,
+    };
+    article.insert("updated".to_owned(), updated);
+    article.insert("published".to_owned(), published);
+    article.insert("first_seen".to_owned(), first_seen);
+    if let Some(Value::Array(revisions)) = article.get_mut("revisions") {
+        for revision in revisions.iter_mut().filter_map(Value::as_object_mut) {
+            let updated = revision.get("updated").map(local_date_to_utc);
+            revision.insert("updated".to_owned(), updated.unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+/// State of the app besides the library, such as settings.
+pub const APP_STATE: Schema = Schema {
+    name: "app state",
+    migrations: &[unversioned],
+};
+
+/// Library of entries and folders, that is, the serialized `Feed` without articles.
+pub const LIBRARY: Schema = Schema {
+    name: "library",
+    migrations: &[unversioned, unchanged, unchanged],
+};
+
+/// Articles, which are of the same version as the library they are stored with.
+pub const ARTICLE: Schema = Schema {
+    name: "article",
+    migrations: &[unversioned, links_to_objects, typed_dates],
+};
+
+/// Bodies of articles, which are of the same version as the articles.
+pub const BODY: Schema = Schema {
+    name: "article body",
+    migrations: &[unversioned, summary_to_body, unchanged],
+};
+
+/// Backup archive, which has the library and the app state in their own envelopes.
+pub const BACKUP: Schema = Schema {
+    name: "backup",
+    migrations: &[unversioned],
+};
+
+impl Schema {
+    /// Current version of the schema.
+    pub const fn version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    /// Attempts to upgrade a snapshot of the version to the current one.
+    /// Snapshots of newer versions, saved by newer builds, can't be read.
+    pub fn try_upgrade(&self, version: u32, value: &mut Value) -> Result<()> {
+        if version > self.version() {
+            bail!(
+                "The {} is saved by a newer version of RSSucks in schema version {version}, \
+                 while this one reads up to version {}. Please upgrade RSSucks to open it.",
+                self.name,
+                self.version()
+            );
+        }
+        for (from, migrate) in self.migrations.iter().enumerate().skip(version as usize) {
+            migrate(value).with_context(|| {
+                format!("Failed to upgrade the {} from version {from}", self.name)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Attempts to upgrade a snapshot of the version and deserialize it.
+    pub fn try_decode_value<T: DeserializeOwned>(
+        &self,
+        version: u32,
+        mut value: Value,
+    ) -> Result<T> {
+        self.try_upgrade(version, &mut value)?;
+        serde_json::from_value(value).with_context(|| format!("Invalid {}", self.name))
+    }
+
+    /// Attempts to decode a snapshot, upgrading it if it's older,
+    /// and returns it with the version it was saved in.
+    pub fn try_decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<(T, u32)> {
+        let value: Value =
+            serde_json::from_slice(bytes).with_context(|| format!("Invalid {}", self.name))?;
+        self.try_decode_from(value)
+    }
+
+    /// Attempts to decode a snapshot in a JSON value, like [`Schema::try_decode`].
+    pub fn try_decode_from<T: DeserializeOwned>(&self, value: Value) -> Result<(T, u32)> {
+        let (version, state) = match value {
+            Value::Object(mut envelope)
+                if envelope.len() == 2
+                    && envelope.contains_key("state")
+                    && envelope.get("version").is_some_and(Value::is_u64) =>
+            {
+                let version = envelope["version"].as_u64().unwrap_or_default();
+                (
+                    u32::try_from(version).unwrap_or(u32::MAX),
+                    envelope.remove("state").unwrap_or_default(),
+                )
+            }
+            value => (0, value),
+        };
+        Ok((self.try_decode_value(version, state)?, version))
+    }
+
+    /// Attempts to encode a snapshot in the current version.
+    pub fn try_encode<T: Serialize>(&self, state: &T) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&self.try_encode_value(state)?)?)
+    }
+
+    /// Attempts to encode a snapshot in the current version into a JSON value.
+    pub fn try_encode_value<T: Serialize>(&self, state: &T) -> Result<Value> {
+        Ok(serde_json::json!({
+            "version": self.version(),
+            "state": serde_json::to_value(state)?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{unversioned, Schema};
+    use anyhow::Result;
+    use serde::{Deserialize, Serialize};
+    use serde_json::Value;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Subscription {
+        title: String,
+        urls: Vec<String>,
+    }
+
+    /// Version 2 keeps the URLs of a subscription in a list.
+    fn url_to_urls(value: &mut Value) -> Result<()> {
+        let url = value["url"].take();
+        let object = value.as_object_mut().unwrap();
+        object.remove("url");
+        object.insert("urls".to_owned(), Value::Array(vec![url]));
+        Ok(())
+    }
+
+    const SCHEMA: Schema = Schema {
+        name: "subscription",
+        migrations: &[unversioned, url_to_urls],
+    };
+
+    #[test]
+    fn upgrade() {
+        let expected = Subscription {
+            title: "Example".to_owned(),
+            urls: vec!["https://example.com/feed".to_owned()],
+        };
+        // Saved before versioning.
+        let (state, version) = SCHEMA
+            .try_decode::<Subscription>(br#"{"title":"Example","url":"https://example.com/feed"}"#)
+            .unwrap();
+        assert_eq!((state, version), (expected, 0));
+
+        let (state, version) = SCHEMA
+            .try_decode::<Subscription>(
+                br#"{"version":1,"state":{"title":"Example","url":"https://example.com/feed"}}"#,
+            )
+            .unwrap();
+        assert_eq!(version, 1);
+        let encoded = SCHEMA.try_encode(&state).unwrap();
+        assert!(String::from_utf8_lossy(&encoded).contains(r#""version":2"#));
+        let (decoded, version) = SCHEMA.try_decode::<Subscription>(&encoded).unwrap();
+        assert_eq!((decoded, version), (state, 2));
+    }
+
+    #[test]
+    fn newer_version() {
+        let err = SCHEMA
+            .try_decode::<Subscription>(br#"{"version":3,"state":{"title":"Example","urls":[]}}"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("newer version"));
+    }
+}
@@ -1,97 +1,324 @@
 use super::feed::EntryUuid;
+use super::tag;
 use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
-/// Universally Unique Identifier for [`Article`].
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+/// Universally Unique Identifier for [`Article`],
+/// which is the feed it belongs to and its guid (or link, or title and date if it has no guid).
+/// Timestamps are not a part of it, so an edited article keeps its identity.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
 pub struct ArticleUuid {
-    updated: Option<DateTime<Utc>>,
-    published: Option<DateTime<Utc>>,
     feed_id: EntryUuid,
     id: String,
 }
 
-impl PartialOrd for ArticleUuid {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+impl ArticleUuid {
+    pub fn new(feed_id: &EntryUuid, id: impl ToString) -> Self {
+        Self {
+            feed_id: *feed_id,
+            id: id.to_string(),
+        }
+    }
+
+    /// Returns the guid of the article, or its link if it has no guid.
+    pub fn guid(&self) -> &str {
+        &self.id
     }
+
+    /// Returns the ID of the feed which the article belongs to.
+    pub fn feed_id(&self) -> EntryUuid {
+        self.feed_id
+    }
+
+    /// Creates the IDs of the articles parsed from the source of a feed,
+    /// in the order of the entries, see [`Self::from_entry`].
+    pub fn from_entries(
+        feed_id: &EntryUuid,
+        source: &[u8],
+        entries: &[feed_rs::model::Entry],
+    ) -> Vec<Self> {
+        // Entries are taken to have guids if they can't be matched up with the source.
+        let has_guids = entries_with_guids(source).filter(|has| has.len() == entries.len());
+        entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let has_guid = has_guids.as_ref().map_or(true, |has| has[index]);
+                Self::from_entry(feed_id, entry, has_guid)
+            })
+            .collect()
+    }
+
+    /// Creates the ID of an article in a feed by its guid, or if it has no guid,
+    /// by its link, or else by its title and date.
+    /// [`feed_rs`] makes guids up for entries without, which may change at each parse.
+    pub fn from_entry(feed_id: &EntryUuid, entry: &feed_rs::model::Entry, has_guid: bool) -> Self {
+        if has_guid && !entry.id.trim().is_empty() {
+            return Self::new(feed_id, &entry.id);
+        }
+        if let Some(link) = entry.links.first() {
+            return Self::new(feed_id, &link.href);
+        }
+        let title = entry.title.as_ref().map(|title| title.content.trim());
+        match (title, entry.published.or(entry.updated)) {
+            (None, None) => Self::new(feed_id, &entry.id),
+            (title, date) => Self::new(
+                feed_id,
+                format!(
+                    "{}@{}",
+                    title.unwrap_or_default(),
+                    date.map(|date| date.to_rfc3339()).unwrap_or_default()
+                ),
+            ),
+        }
+    }
+}
+
+lazy_static! {
+    static ref ENTRY_PATTERN: Regex =
+        Regex::new(r"(?s)<(?:item|entry)[\s>].*?</(?:item|entry)>").unwrap();
+    static ref GUID_PATTERN: Regex =
+        Regex::new(r"<(?:guid|id)(?:\s[^>]*)?>\s*(?:<!\[CDATA\[\s*)?[^\s<\]]").unwrap();
+}
+
+/// Tells which entries in the source of an XML feed have guids, in the order of the entries,
+/// or `None` for a JSON Feed, whose items can't be parsed without IDs.
+fn entries_with_guids(source: &[u8]) -> Option<Vec<bool>> {
+    let first = source
+        .iter()
+        .find(|byte| **byte == b'<' || **byte == b'{')?;
+    if *first == b'{' {
+        return None;
+    }
+    let source = String::from_utf8_lossy(source);
+    Some(
+        ENTRY_PATTERN
+            .find_iter(&source)
+            .map(|entry| GUID_PATTERN.is_match(entry.as_str()))
+            .collect(),
+    )
 }
 
-impl Ord for ArticleUuid {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match other.updated.cmp(&self.updated) {
-            std::cmp::Ordering::Equal => match other.published.cmp(&self.published) {
-                std::cmp::Ordering::Equal => self.id.cmp(&other.id),
-                std::cmp::Ordering::Greater => std::cmp::Ordering::Greater,
-                std::cmp::Ordering::Less => std::cmp::Ordering::Less,
-            },
-            std::cmp::Ordering::Greater => std::cmp::Ordering::Greater,
-            std::cmp::Ordering::Less => std::cmp::Ordering::Less,
+/// Link of an [`Article`], such as the original page (`rel=alternate`) or a related one.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Link {
+    pub href: String,
+    #[serde(default)]
+    pub rel: Option<String>,
+    #[serde(default)]
+    pub media_type: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub length: Option<u64>,
+}
+
+impl From<feed_rs::model::Link> for Link {
+    fn from(value: feed_rs::model::Link) -> Self {
+        Self {
+            href: value.href,
+            rel: value.rel,
+            media_type: value.media_type,
+            title: value.title,
+            length: value.length,
         }
     }
 }
 
-impl ArticleUuid {
-    pub fn new(
-        update: Option<DateTime<Utc>>,
-        publish: Option<DateTime<Utc>>,
-        feed_id: &EntryUuid,
-        id: impl ToString,
-    ) -> Self {
+/// Author or contributor of an [`Article`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Person {
+    pub name: String,
+    pub uri: Option<String>,
+    pub email: Option<String>,
+}
+
+impl From<feed_rs::model::Person> for Person {
+    fn from(value: feed_rs::model::Person) -> Self {
         Self {
-            updated: update,
-            published: publish,
-            feed_id: *feed_id,
-            id: id.to_string(),
+            name: value.name,
+            uri: value.uri,
+            email: value.email,
         }
     }
 }
 
+/// Media attached to an [`Article`], from RSS enclosures and MediaRSS.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Enclosure {
+    pub url: String,
+    pub media_type: Option<String>,
+    /// Size in bytes.
+    pub length: Option<u64>,
+    pub title: Option<String>,
+}
+
+impl Enclosure {
+    /// Enclosures of Atom are links of `rel=enclosure`.
+    fn from_link(link: &Link) -> Option<Self> {
+        (link.rel.as_deref() == Some("enclosure")).then(|| Self {
+            url: link.href.clone(),
+            media_type: link.media_type.clone(),
+            length: link.length,
+            title: link.title.clone(),
+        })
+    }
+
+    /// Enclosures of RSS and MediaRSS are media contents.
+    fn from_media(media: feed_rs::model::MediaObject) -> Vec<Self> {
+        let title = media.title.map(|title| title.content);
+        media
+            .content
+            .into_iter()
+            .filter_map(|content| {
+                Some(Self {
+                    url: content.url?.to_string(),
+                    media_type: content.content_type.map(|mime| mime.to_string()),
+                    length: content.size,
+                    title: title.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Body of an [`Article`], which may be left in the storage until it's read.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Body {
+    pub summary: Option<String>,
+    pub content: Option<String>,
+}
+
+/// A former version of an [`Article`], kept when the publisher edits it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Revision {
+    pub title: String,
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+    pub updated: Option<DateTime<Utc>>,
+    /// When this version was replaced by a newer one.
+    pub replaced_at: DateTime<Utc>,
+}
+
+/// Marks which the reader puts on an [`Article`], besides whether it's read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Flag {
+    Starred,
+    ReadLater,
+    /// Archived articles are never pruned.
+    Archived,
+}
+
+impl Flag {
+    pub const ALL: [Flag; 3] = [Flag::Starred, Flag::ReadLater, Flag::Archived];
+}
+
+/// Loads the bodies of articles which are stored but not loaded into memory yet.
+pub trait BodySource: Send + Sync + std::fmt::Debug {
+    /// Attempts to load the body of the article, `None` if it has none stored.
+    fn try_load_body(&self, id: &ArticleUuid) -> anyhow::Result<Option<Body>>;
+}
+
 /// Article, which can be convertec from [`feed_rs::model::Entry`]
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Article {
-    pub updated: Option<String>,
-    pub published: Option<String>,
+    pub updated: Option<DateTime<Utc>>,
+    pub published: Option<DateTime<Utc>>,
+    /// When the article was fetched at first.
+    pub first_seen: DateTime<Utc>,
     pub id: String,
     pub title: String,
-    pub links: Vec<String>,
+    pub links: Vec<Link>,
+    /// A teaser, or the whole text if the feed has no `content`.
     pub summary: Option<String>,
+    /// The whole text, which is preferred over `summary` by the reader.
+    #[serde(default)]
+    pub content: Option<String>,
     pub categories: Vec<String>,
+    #[serde(default)]
+    pub authors: Vec<Person>,
+    #[serde(default)]
+    pub enclosures: Vec<Enclosure>,
+    #[serde(default)]
+    pub rights: Option<String>,
+    /// The feed which the article is copied from.
+    #[serde(default)]
+    pub source: Option<String>,
     pub belong_to: Option<EntryUuid>,
     pub unread: bool,
-}
-
-fn utc_to_local_date_string(time_utc: Option<DateTime<Utc>>) -> Option<String> {
-    time_utc.map(|time_utc| {
-        time_utc
-            .with_timezone(&chrono::Local)
-            .format("%Y/%m/%d %H:%M")
-            .to_string()
-    })
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(default)]
+    pub read_later: bool,
+    #[serde(default)]
+    pub archived: bool,
+    /// Tags put by the reader, apart from `categories` supplied by the feed.
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// Former versions of the article, from the oldest.
+    #[serde(default)]
+    pub revisions: Vec<Revision>,
+    /// The version read at last, as an index of the versions from the oldest,
+    /// where `revisions.len()` is the current one.
+    #[serde(default)]
+    seen_revision: Option<usize>,
+    /// Is the body (`summary` and `content`) left in the storage and not loaded yet?
+    #[serde(skip)]
+    body_unloaded: bool,
 }
 
 impl From<feed_rs::model::Entry> for Article {
     fn from(value: feed_rs::model::Entry) -> Self {
+        let links: Vec<Link> = value.links.into_iter().map(Link::from).collect();
+        let enclosures = links
+            .iter()
+            .filter_map(Enclosure::from_link)
+            .chain(value.media.into_iter().flat_map(Enclosure::from_media))
+            .collect();
         Article {
             id: value.id,
             title: value
                 .title
                 .map_or("No Title".to_owned(), |text| text.content),
-            updated: utc_to_local_date_string(value.updated),
-            links: value.links.into_iter().map(|link| link.href).collect(),
+            updated: value.updated,
+            published: value.published,
+            first_seen: Utc::now(),
+            links,
             summary: value.summary.map(|summary| summary.content),
+            content: value.content.and_then(|content| content.body),
             categories: value
                 .categories
                 .into_iter()
-                .filter_map(|category| category.label)
+                .map(|category| category.label.unwrap_or(category.term))
                 .collect(),
-            published: utc_to_local_date_string(value.published),
+            authors: value.authors.into_iter().map(Person::from).collect(),
+            enclosures,
+            rights: value.rights.map(|rights| rights.content),
+            source: value.source,
             belong_to: None,
             unread: true,
+            starred: false,
+            read_later: false,
+            archived: false,
+            tags: BTreeSet::new(),
+            revisions: vec![],
+            seen_revision: None,
+            body_unloaded: false,
         }
     }
 }
 
+impl Revision {
+    /// Returns the text of the version, like [`Article::text`].
+    pub fn text(&self) -> Option<&String> {
+        self.content.as_ref().or(self.summary.as_ref())
+    }
+}
+
 impl Article {
     #[allow(unused)]
     pub fn set_belonging(mut self, id: &EntryUuid) -> Self {
@@ -99,11 +326,290 @@ impl Article {
         self
     }
 
-    #[allow(unused)]
+    /// Is the body loaded into memory?
+    pub fn is_body_loaded(&self) -> bool {
+        !self.body_unloaded
+    }
+
+    /// Takes the body out, leaving the article without it until it's loaded again.
+    pub fn take_body(&mut self) -> Body {
+        self.body_unloaded = true;
+        Body {
+            summary: self.summary.take(),
+            content: self.content.take(),
+        }
+    }
+
+    /// Sets the body loaded from the storage.
+    pub fn set_body(&mut self, body: Body) {
+        self.summary = body.summary;
+        self.content = body.content;
+        self.body_unloaded = false;
+    }
+
+    /// When the article was updated, or published if it's never updated.
+    pub fn date(&self) -> Option<DateTime<Utc>> {
+        self.updated.or(self.published)
+    }
+
+    /// The date of the article, or when it's first seen if it's undated,
+    /// which articles are ordered by.
+    pub fn sort_date(&self) -> DateTime<Utc> {
+        self.date().unwrap_or(self.first_seen)
+    }
+
+    /// Returns the text to read, which is the content if there is, otherwise the summary.
+    pub fn text(&self) -> Option<&String> {
+        self.content.as_ref().or(self.summary.as_ref())
+    }
+
+    /// Returns the link to the original page, which is the `rel=alternate` one,
+    /// or the first one without `rel` (which means `alternate` in Atom).
+    pub fn alternate_link(&self) -> Option<&str> {
+        self.links
+            .iter()
+            .find(|link| link.rel.as_deref() == Some("alternate"))
+            .or_else(|| self.links.iter().find(|link| link.rel.is_none()))
+            .map(|link| link.href.as_str())
+    }
+
+    /// Marks the article as read, including its current version.
     pub fn set_read(&mut self) {
         self.unread = false;
+        self.seen_revision = Some(self.revisions.len());
+    }
+
+    /// Is the flag set on the article?
+    pub fn flag(&self, flag: Flag) -> bool {
+        match flag {
+            Flag::Starred => self.starred,
+            Flag::ReadLater => self.read_later,
+            Flag::Archived => self.archived,
+        }
+    }
+
+    pub fn set_flag(&mut self, flag: Flag, value: bool) {
+        match flag {
+            Flag::Starred => self.starred = value,
+            Flag::ReadLater => self.read_later = value,
+            Flag::Archived => self.archived = value,
+        }
+    }
+
+    /// Tags the article, returns the normalized name of the tag
+    /// or `None` if the name is blank.
+    pub fn add_tag(&mut self, name: &str) -> Option<String> {
+        let name = tag::normalize(name)?;
+        self.tags.insert(name.clone());
+        Some(name)
+    }
+
+    /// Removes the tag, returns whether the article was tagged with it.
+    pub fn remove_tag(&mut self, name: &str) -> bool {
+        self.tags.remove(name)
+    }
+
+    /// Takes the state put by the reader on another copy of the article, such as one in a backup:
+    /// it's read if either copy is, and has the flags and the tags of both.
+    pub fn merge_state(&mut self, other: &Article) {
+        if self.unread && !other.unread {
+            self.set_read();
+        }
+        for flag in Flag::ALL {
+            if other.flag(flag) {
+                self.set_flag(flag, true);
+            }
+        }
+        self.tags.extend(other.tags.iter().cloned());
+    }
+
+    /// Marks the article as unread again, which keeps the version read at last.
+    pub fn set_unread(&mut self) {
+        self.unread = true;
+    }
+
+    /// Updates the article to a newer version fetched from the feed.
+    /// If the title or the body is changed, the current version is kept as a revision.
+    /// Returns whether it's revised.
+    pub fn update(&mut self, newer: Article) -> bool {
+        let revised = self.title != newer.title
+            || self.summary != newer.summary
+            || self.content != newer.content;
+        if revised {
+            self.revisions.push(Revision {
+                title: std::mem::take(&mut self.title),
+                summary: self.summary.take(),
+                content: self.content.take(),
+                updated: self.updated.take(),
+                replaced_at: Utc::now(),
+            });
+        }
+        self.title = newer.title;
+        self.summary = newer.summary;
+        self.content = newer.content;
+        self.updated = newer.updated;
+        self.published = newer.published;
+        self.links = newer.links;
+        self.categories = newer.categories;
+        self.authors = newer.authors;
+        self.enclosures = newer.enclosures;
+        self.rights = newer.rights;
+        self.source = newer.source;
+        revised
+    }
+
+    /// Returns the version read at last if the article has been revised since then.
+    pub fn unseen_revision(&self) -> Option<&Revision> {
+        self.seen_revision.and_then(|seen| self.revisions.get(seen))
     }
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use super::{Article, ArticleUuid};
+    use crate::subscription::feed::EntryUuid;
+    use uuid::Uuid;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+<channel>
+  <title>Revisions</title>
+  <item>
+    <guid>post-1</guid>
+    <title>First title</title>
+    <description>First summary</description>
+    <pubDate>Mon, 06 Nov 2023 08:00:00 GMT</pubDate>
+  </item>
+  <item>
+    <title>No guid</title>
+    <link>https://example.com/no-guid</link>
+  </item>
+</channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Full entries</title>
+  <id>urn:example:feed</id>
+  <updated>2023-11-08T09:00:00Z</updated>
+  <entry>
+    <title>Full entry</title>
+    <id>urn:example:entry</id>
+    <updated>2023-11-08T09:00:00Z</updated>
+    <link rel="related" href="https://example.com/related"/>
+    <link rel="alternate" type="text/html" href="https://example.com/original"/>
+    <link rel="enclosure" type="audio/mpeg" length="1024" href="https://example.com/episode.mp3"/>
+    <author><name>Ferris</name><email>ferris@example.com</email></author>
+    <rights>CC BY 4.0</rights>
+    <summary>A short teaser.</summary>
+    <content type="html">&lt;p&gt;The full body.&lt;/p&gt;</content>
+  </entry>
+</feed>"#;
+
+    fn parse(source: &str) -> Vec<feed_rs::model::Entry> {
+        feed_rs::parser::parse(source.as_bytes()).unwrap().entries
+    }
+
+    fn ids(source: &str) -> Vec<String> {
+        let feed_id = EntryUuid::from(Uuid::new_v4());
+        ArticleUuid::from_entries(&feed_id, source.as_bytes(), &parse(source))
+            .into_iter()
+            .map(|id| id.guid().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn stable_id() {
+        let feed_id = EntryUuid::from(Uuid::new_v4());
+        let edited = RSS
+            .replace("First title", "Edited title")
+            .replace("No guid", "Edited no guid")
+            .replace("08:00:00", "09:00:00");
+        assert_eq!(ids(RSS), vec!["post-1", "https://example.com/no-guid"]);
+        assert_eq!(ids(RSS), ids(&edited));
+        let entries = parse(RSS);
+        assert_ne!(
+            ArticleUuid::from_entry(&feed_id, &entries[0], true),
+            ArticleUuid::from_entry(&EntryUuid::from(Uuid::new_v4()), &entries[0], true)
+        );
+    }
+
+    #[test]
+    fn hex_guid() {
+        let source = RSS
+            .replace("post-1", "d41d8cd98f00b204e9800998ecf8427e")
+            .replace(
+                "<title>First title</title>",
+                "<title>First title</title>\n    <link>https://example.com/first</link>",
+            );
+        assert_eq!(
+            ids(&source),
+            vec![
+                "d41d8cd98f00b204e9800998ecf8427e",
+                "https://example.com/no-guid"
+            ]
+        );
+    }
+
+    #[test]
+    fn no_guid_nor_link() {
+        let source = RSS.replace(
+            "<link>https://example.com/no-guid</link>",
+            "<pubDate>Tue, 07 Nov 2023 08:00:00 GMT</pubDate>",
+        );
+        // Stable across parses, while feed-rs makes a random guid up at each.
+        assert_eq!(ids(&source), ids(&source));
+        assert_eq!(ids(&source)[1], "No guid@2023-11-07T08:00:00+00:00");
+    }
+
+    #[test]
+    fn full_entry() {
+        let mut article = Article::from(parse(ATOM).remove(0));
+        assert_eq!(article.text().unwrap(), "<p>The full body.</p>");
+        assert_eq!(article.summary.as_deref(), Some("A short teaser."));
+        assert_eq!(
+            article.alternate_link(),
+            Some("https://example.com/original")
+        );
+        assert_eq!(article.links.len(), 3);
+        let enclosure = &article.enclosures[0];
+        assert_eq!(enclosure.url, "https://example.com/episode.mp3");
+        assert_eq!(enclosure.media_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(enclosure.length, Some(1024));
+        assert_eq!(article.authors[0].name, "Ferris");
+        assert_eq!(
+            article.authors[0].email.as_deref(),
+            Some("ferris@example.com")
+        );
+        assert_eq!(article.rights.as_deref(), Some("CC BY 4.0"));
+
+        let body = article.take_body();
+        assert!(article.text().is_none());
+        article.set_body(body);
+        assert_eq!(article.text().unwrap(), "<p>The full body.</p>");
+
+        // Without content, the summary is the text.
+        let rss = Article::from(parse(RSS).remove(0));
+        assert_eq!(rss.text().unwrap(), "First summary");
+    }
+
+    #[test]
+    fn revisions() {
+        let mut article = Article::from(parse(RSS).remove(0));
+        // Updates without changes are not revisions.
+        assert!(!article.update(Article::from(parse(RSS).remove(0))));
+        article.set_read();
+        assert!(article.unseen_revision().is_none());
+
+        let edited = RSS.replace("First summary", "Edited summary");
+        assert!(article.update(Article::from(parse(&edited).remove(0))));
+        assert!(!article.unread);
+        assert_eq!(article.revisions.len(), 1);
+        assert_eq!(article.summary.as_deref(), Some("Edited summary"));
+        let seen = article.unseen_revision().unwrap();
+        assert_eq!(seen.summary.as_deref(), Some("First summary"));
+
+        article.set_read();
+        assert!(article.unseen_revision().is_none());
+    }
+}
@@ -0,0 +1,186 @@
+//! Backups of the whole library with the app settings in one archive,
+//! unlike OPML which only carries the subscriptions and the folders.
+use super::article::{Article, ArticleUuid, Body};
+use super::feed::Feed;
+use crate::schema;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Article in an archive with its body,
+/// whose article and body are read as JSON values to be upgraded.
+#[derive(Deserialize, Serialize)]
+struct ArchivedArticle<A = Article, B = Body> {
+    id: ArticleUuid,
+    article: A,
+    body: Option<B>,
+}
+
+/// Backup archive, encoded by [`schema::BACKUP`].
+#[derive(Deserialize, Serialize)]
+struct Archive<A = Article, B = Body> {
+    created: DateTime<Utc>,
+    /// Entries and folders, encoded by [`schema::LIBRARY`].
+    library: Value,
+    /// Articles, of the schema version of the library.
+    articles: Vec<ArchivedArticle<A, B>>,
+    /// App settings, encoded by [`schema::APP_STATE`].
+    settings: Value,
+}
+
+/// How a backup is restored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestoreMode {
+    /// Replaces the library and the settings.
+    Replace,
+    /// Merges the library into the current one (see [`Feed::merge`]), keeping the settings.
+    Merge,
+}
+
+/// Backup read from an archive.
+#[derive(Debug)]
+pub struct Backup {
+    pub created: DateTime<Utc>,
+    pub library: Feed,
+    /// Articles with their bodies.
+    pub articles: Vec<(ArticleUuid, Article)>,
+    /// App settings, still encoded by [`schema::APP_STATE`].
+    pub settings: Value,
+}
+
+impl Backup {
+    /// Attempts to write an archive of the library with all of its articles,
+    /// and the app settings encoded by [`schema::APP_STATE`].
+    pub fn try_archive(feed: &Feed, settings: Value) -> Result<Vec<u8>> {
+        let mut articles = vec![];
+        for id in feed.get_all_article_ids() {
+            let mut article = feed.try_get_article_by_id(&id)?.lock().unwrap().clone();
+            article.take_body();
+            articles.push(ArchivedArticle {
+                body: Some(feed.try_get_article_body(&id)?),
+                id,
+                article,
+            });
+        }
+        schema::BACKUP.try_encode(&Archive {
+            created: Utc::now(),
+            library: schema::LIBRARY.try_encode_value(feed)?,
+            articles,
+            settings,
+        })
+    }
+
+    /// Attempts to read an archive, upgrading it if it's written by an older version.
+    pub fn try_read(bytes: &[u8]) -> Result<Self> {
+        let (archive, _): (Archive<Value, Value>, _) = schema::BACKUP.try_decode(bytes)?;
+        let (library, version) = schema::LIBRARY.try_decode_from(archive.library)?;
+        let mut articles = vec![];
+        for ArchivedArticle { id, article, body } in archive.articles {
+            let mut article: Article = schema::ARTICLE.try_decode_value(version, article)?;
+            article.set_body(match body {
+                Some(body) => schema::BODY.try_decode_value(version, body)?,
+                None => Body::default(),
+            });
+            articles.push((id, article));
+        }
+        Ok(Backup {
+            created: archive.created,
+            library,
+            articles,
+            settings: archive.settings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Backup;
+    use crate::subscription::article::{ArticleUuid, Flag};
+    use crate::subscription::feed::{Entry, Feed, Folder};
+    use crate::subscription::fetch::{fixture_fetcher, ATOM_URL, RSS_URL};
+    use crate::subscription::tag::TagColor;
+    use url::Url;
+
+    #[test]
+    fn archive_and_merge() {
+        let mut feed = Feed::default();
+        feed.set_fetcher(fixture_fetcher());
+        let folder_id = feed.add_empty_folder(Folder::new("News"));
+        let rss_id = feed
+            .try_add_entry_to_folder(
+                Entry::new_with_alias("RSS", Url::parse(RSS_URL).unwrap()),
+                &folder_id,
+            )
+            .unwrap();
+        let atom_id = feed.add_orphan_entry(Entry::new(Url::parse(ATOM_URL).unwrap()));
+        feed.try_sync_all().unwrap();
+        let read_id = feed.try_get_all_article_ids_by_entry_id(&rss_id).unwrap()[0].clone();
+        feed.try_mark_article_read(&read_id).unwrap();
+        feed.try_set_article_flag(&read_id, Flag::Starred, true)
+            .unwrap();
+        feed.try_add_article_tag(&read_id, "weekly").unwrap();
+        let tagged_id = feed.try_get_all_article_ids_by_entry_id(&atom_id).unwrap()[0].clone();
+        feed.try_add_article_tag(&tagged_id, " weekly ").unwrap();
+        feed.set_tag_color("weekly", TagColor([0, 0, 0]));
+
+        let archive = Backup::try_archive(&feed, serde_json::json!({})).unwrap();
+        let backup = Backup::try_read(&archive).unwrap();
+        assert_eq!(backup.articles.len(), feed.get_all_article_ids().len());
+        assert!(backup
+            .articles
+            .iter()
+            .all(|(_, article)| article.summary.is_some()));
+
+        // Merged into a library which has one of the feeds already.
+        let mut current = Feed::default();
+        current.set_fetcher(fixture_fetcher());
+        let current_rss_id = current.add_orphan_entry(Entry::new(Url::parse(RSS_URL).unwrap()));
+        current.try_sync_entry_by_id(&current_rss_id).unwrap();
+        let merged_id = ArticleUuid::new(&current_rss_id, read_id.guid());
+        current.try_add_article_tag(&merged_id, "later").unwrap();
+        let (entries, articles) = current.merge(backup.library, backup.articles);
+        let atom_articles = feed
+            .try_get_all_article_ids_by_entry_id(&atom_id)
+            .unwrap()
+            .len();
+        assert_eq!((entries, articles), (1, atom_articles));
+        assert_eq!(current.get_all_entry_ids().len(), 2);
+        assert_eq!(current.get_folder_ids_by_name("News").len(), 1);
+        // Articles of the matched feed take the state in the backup.
+        let merged = current.try_get_article_by_id(&merged_id).unwrap();
+        let merged = merged.lock().unwrap();
+        assert!(!merged.unread && merged.starred);
+        assert_eq!(
+            merged.tags.iter().collect::<Vec<_>>(),
+            vec!["later", "weekly"]
+        );
+        drop(merged);
+        let unread = current
+            .try_get_all_article_ids_by_entry_id(&current_rss_id)
+            .unwrap()
+            .into_iter()
+            .filter(|id| {
+                current
+                    .try_get_article_by_id(id)
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .unread
+            })
+            .count();
+        assert_eq!(
+            unread,
+            feed.try_get_all_article_ids_by_entry_id(&rss_id)
+                .unwrap()
+                .len()
+                - 1
+        );
+        // Tags are kept along with their colors.
+        assert_eq!(
+            current.get_all_tags(),
+            vec![("later".to_owned(), 1), ("weekly".to_owned(), 2)]
+        );
+        assert_eq!(current.tag_color("weekly"), TagColor([0, 0, 0]));
+    }
+}
@@ -0,0 +1,251 @@
+//! Detecting the same article published in several feeds,
+//! like a site's main feed and its category feeds, or aggregators.
+use super::article::{Article, ArticleUuid};
+use std::collections::HashMap;
+use url::Url;
+
+/// Query parameters which only track where visitors come from.
+const TRACKING_PARAMS: [&str; 5] = ["fbclid", "gclid", "mc_cid", "mc_eid", "ref"];
+
+/// Something that identifies an article across feeds.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+enum Fingerprint {
+    Link(String),
+    Guid(String),
+}
+
+/// Normalizes a link so that the same page linked in different ways compares equal.
+/// The scheme, `www.`, the fragment, tracking parameters and trailing slashes are ignored.
+pub fn normalize_link(link: &str) -> Option<String> {
+    let url = Url::parse(link.trim()).ok()?;
+    let host = url.host_str()?.to_ascii_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let host = match url.port() {
+        Some(port) => format!("{host}:{port}"),
+        None => host.to_owned(),
+    };
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| !name.starts_with("utm_") && !TRACKING_PARAMS.contains(&name.as_ref()))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    params.sort();
+    let mut normalized = format!("{host}{}", url.path().trim_end_matches('/'));
+    if !params.is_empty() {
+        let query: Vec<String> = params
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        normalized = format!("{normalized}?{}", query.join("&"));
+    }
+    Some(normalized)
+}
+
+fn fingerprints(id: &ArticleUuid, article: &Article) -> Vec<Fingerprint> {
+    let mut fingerprints: Vec<Fingerprint> = article
+        .alternate_link()
+        .and_then(normalize_link)
+        .map(Fingerprint::Link)
+        .into_iter()
+        .collect();
+    // Only guids which look globally unique, like URLs and URNs,
+    // since plain numbers are often reused by different sites.
+    if id.guid().contains(':') {
+        fingerprints.push(Fingerprint::Guid(id.guid().to_owned()));
+    }
+    fingerprints
+}
+
+/// Groups of articles which are copies of each other.
+#[derive(Debug, Default)]
+pub struct DuplicateIndex {
+    groups: HashMap<ArticleUuid, usize>,
+}
+
+impl DuplicateIndex {
+    /// Builds the index of the articles, which are copies of each other
+    /// if they share the normalized link or the guid.
+    /// Titles are not enough, since unrelated posts may share them.
+    pub fn build<'a>(articles: impl IntoIterator<Item = (&'a ArticleUuid, &'a Article)>) -> Self {
+        let mut ids: Vec<ArticleUuid> = vec![];
+        // Union-find of the indices of the articles.
+        let mut parents: Vec<usize> = vec![];
+        fn find(parents: &mut [usize], mut index: usize) -> usize {
+            while parents[index] != index {
+                parents[index] = parents[parents[index]];
+                index = parents[index];
+            }
+            index
+        }
+        let mut owners: HashMap<Fingerprint, usize> = HashMap::new();
+        for (id, article) in articles {
+            let index = ids.len();
+            ids.push(id.clone());
+            parents.push(index);
+            for fingerprint in fingerprints(id, article) {
+                match owners.get(&fingerprint) {
+                    Some(owner) => {
+                        let (root, owner_root) =
+                            (find(&mut parents, index), find(&mut parents, *owner));
+                        parents[root] = owner_root;
+                    }
+                    None => {
+                        owners.insert(fingerprint, index);
+                    }
+                }
+            }
+        }
+        let groups = ids
+            .into_iter()
+            .enumerate()
+            .map(|(index, id)| (id, find(&mut parents, index)))
+            .collect();
+        DuplicateIndex { groups }
+    }
+
+    /// Returns the copies of the article, including itself.
+    pub fn copies_of(&self, id: &ArticleUuid) -> Vec<ArticleUuid> {
+        match self.groups.get(id) {
+            Some(group) => self
+                .groups
+                .iter()
+                .filter(|(_, other)| *other == group)
+                .map(|(id, _)| id.clone())
+                .collect(),
+            None => vec![id.clone()],
+        }
+    }
+
+    /// Returns the copies of all the articles, including themselves.
+    pub fn copies_of_all(&self, ids: &[ArticleUuid]) -> Vec<ArticleUuid> {
+        let groups: std::collections::HashSet<usize> = ids
+            .iter()
+            .filter_map(|id| self.groups.get(id))
+            .copied()
+            .collect();
+        self.groups
+            .iter()
+            .filter(|(_, group)| groups.contains(group))
+            .map(|(id, _)| id.clone())
+            .chain(
+                ids.iter()
+                    .filter(|id| !self.groups.contains_key(id))
+                    .cloned(),
+            )
+            .collect()
+    }
+
+    /// Collapses the copies in the articles, keeping only the first one of them.
+    pub fn collapse(&self, ids: Vec<ArticleUuid>) -> Vec<ArticleUuid> {
+        let mut seen = std::collections::HashSet::new();
+        ids.into_iter()
+            .filter(|id| match self.groups.get(id) {
+                Some(group) => seen.insert(*group),
+                None => true,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{normalize_link, DuplicateIndex};
+    use crate::subscription::article::{Article, ArticleUuid};
+    use crate::subscription::feed::EntryUuid;
+    use uuid::Uuid;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            normalize_link("https://www.Example.com/posts/1/?utm_source=rss&b=2&a=1#comments"),
+            Some("example.com/posts/1?a=1&b=2".to_owned())
+        );
+        assert_eq!(
+            normalize_link("http://example.com/posts/1"),
+            normalize_link("https://example.com/posts/1/")
+        );
+        assert_ne!(
+            normalize_link("https://example.com/posts/1"),
+            normalize_link("https://example.com/posts/2")
+        );
+        assert_eq!(normalize_link("not a link"), None);
+    }
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+<channel>
+  <title>Duplicates</title>
+  <item>
+    <guid>1</guid>
+    <title>Linked</title>
+    <link>https://www.example.com/linked?utm_medium=feed</link>
+  </item>
+  <item>
+    <guid>tag:example.com,2023:guid</guid>
+    <title>Same guid</title>
+  </item>
+  <item>
+    <guid>3</guid>
+    <title>Same title and date</title>
+    <pubDate>Mon, 06 Nov 2023 08:00:00 GMT</pubDate>
+  </item>
+  <item>
+    <guid>4</guid>
+    <title>Unique</title>
+    <pubDate>Mon, 06 Nov 2023 08:00:00 GMT</pubDate>
+  </item>
+</channel>
+</rss>"#;
+
+    fn articles(feed_id: &EntryUuid, source: &str) -> Vec<(ArticleUuid, Article)> {
+        let entries = feed_rs::parser::parse(source.as_bytes()).unwrap().entries;
+        ArticleUuid::from_entries(feed_id, source.as_bytes(), &entries)
+            .into_iter()
+            .zip(entries)
+            .map(|(id, entry)| (id, Article::from(entry).set_belonging(feed_id)))
+            .collect()
+    }
+
+    #[test]
+    fn find_duplicates() {
+        let main = articles(&EntryUuid::from(Uuid::new_v4()), RSS);
+        let category = articles(
+            &EntryUuid::from(Uuid::new_v4()),
+            &RSS.replace(
+                "https://www.example.com/linked?utm_medium=feed",
+                "http://example.com/linked/",
+            )
+            .replace("<guid>1</guid>", "<guid>a</guid>")
+            .replace("<guid>3</guid>", "<guid>c</guid>")
+            .replace("<title>Unique</title>", "<title>Other</title>")
+            .replace("<guid>4</guid>", "<guid>d</guid>"),
+        );
+        let index = DuplicateIndex::build(
+            main.iter()
+                .chain(category.iter())
+                .map(|(id, article)| (id, article)),
+        );
+        for i in 0..2 {
+            let mut copies = index.copies_of(&main[i].0);
+            copies.sort();
+            let mut expected = vec![main[i].0.clone(), category[i].0.clone()];
+            expected.sort();
+            assert_eq!(copies, expected);
+        }
+        // The same title and date are not enough.
+        assert_eq!(index.copies_of(&main[2].0), vec![main[2].0.clone()]);
+        assert_eq!(index.copies_of(&main[3].0), vec![main[3].0.clone()]);
+
+        let ids: Vec<ArticleUuid> = main
+            .iter()
+            .chain(category.iter())
+            .map(|(id, _)| id.clone())
+            .collect();
+        let collapsed = index.collapse(ids);
+        assert_eq!(collapsed.len(), 6);
+        assert_eq!(
+            collapsed[..4],
+            main.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>()[..]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Word diffs between revisions of articles.
+
+/// A piece of a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+/// Most cells of the table for finding the longest common subsequence,
+/// beyond which the differing parts are regarded as entirely replaced.
+const MAX_TABLE_SIZE: usize = 1 << 22;
+
+/// Splits the text into words, spaces and punctuations.
+/// Each CJK character is a word by itself since they are not separated by spaces.
+fn tokenize(text: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    let mut last_kind = None;
+    for (index, ch) in text.char_indices() {
+        let kind = if ch.is_whitespace() {
+            Some(0)
+        } else if ch.is_alphanumeric() && ch.is_ascii() || ch == '_' {
+            Some(1)
+        } else {
+            // Punctuations and CJK characters.
+            None
+        };
+        if index > start && (kind.is_none() || kind != last_kind) {
+            tokens.push(&text[start..index]);
+            start = index;
+        }
+        last_kind = kind;
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Appends the tokens to the changes, merging into the last change of the same kind.
+fn push(changes: &mut Vec<Change>, change: fn(String) -> Change, token: &str) {
+    match (changes.last_mut(), change(String::new())) {
+        (Some(Change::Same(last)), Change::Same(_))
+        | (Some(Change::Removed(last)), Change::Removed(_))
+        | (Some(Change::Added(last)), Change::Added(_)) => last.push_str(token),
+        _ => changes.push(change(token.to_owned())),
+    }
+}
+
+/// Returns the word diff from the old text to the new one.
+pub fn diff_words(old: &str, new: &str) -> Vec<Change> {
+    let old = tokenize(old);
+    let new = tokenize(new);
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut changes = vec![];
+    old[..prefix]
+        .iter()
+        .for_each(|token| push(&mut changes, Change::Same, token));
+    if old_middle.len() * new_middle.len() > MAX_TABLE_SIZE {
+        old_middle
+            .iter()
+            .for_each(|token| push(&mut changes, Change::Removed, token));
+        new_middle
+            .iter()
+            .for_each(|token| push(&mut changes, Change::Added, token));
+    } else {
+        // lengths[i][j] is the length of the longest common subsequence
+        // of old_middle[i..] and new_middle[j..].
+        let width = new_middle.len() + 1;
+        let mut lengths = vec![0u32; (old_middle.len() + 1) * width];
+        for i in (0..old_middle.len()).rev() {
+            for j in (0..new_middle.len()).rev() {
+                lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_middle.len() || j < new_middle.len() {
+            if i < old_middle.len() && j < new_middle.len() && old_middle[i] == new_middle[j] {
+                push(&mut changes, Change::Same, old_middle[i]);
+                i += 1;
+                j += 1;
+            } else if j == new_middle.len()
+                || i < old_middle.len()
+                    && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1]
+            {
+                push(&mut changes, Change::Removed, old_middle[i]);
+                i += 1;
+            } else {
+                push(&mut changes, Change::Added, new_middle[j]);
+                j += 1;
+            }
+        }
+    }
+    old[old.len() - suffix..]
+        .iter()
+        .for_each(|token| push(&mut changes, Change::Same, token));
+    changes
+}
+
+/// Is there any change in the diff?
+pub fn is_changed(changes: &[Change]) -> bool {
+    changes
+        .iter()
+        .any(|change| !matches!(change, Change::Same(_)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_words, is_changed, tokenize, Change};
+
+    #[test]
+    fn tokenize_text() {
+        assert_eq!(
+            tokenize("Hello, world  foo_bar"),
+            vec!["Hello", ",", " ", "world", "  ", "foo_bar"]
+        );
+        assert_eq!(tokenize("更新了 v2"), vec!["更", "新", "了", " ", "v2"]);
+    }
+
+    #[test]
+    fn diff() {
+        assert_eq!(
+            diff_words("The quick brown fox", "The slow brown fox jumps"),
+            vec![
+                Change::Same("The ".to_owned()),
+                Change::Removed("quick".to_owned()),
+                Change::Added("slow".to_owned()),
+                Change::Same(" brown fox".to_owned()),
+                Change::Added(" jumps".to_owned()),
+            ]
+        );
+        assert!(!is_changed(&diff_words("Same text", "Same text")));
+        assert_eq!(diff_words("", "New"), vec![Change::Added("New".to_owned())]);
+    }
+}
@@ -0,0 +1,289 @@
+//! Discovering feeds of a website from its homepage.
+use super::fetch::SharedFetcher;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// Paths probed when a page doesn't link to its feeds.
+pub const COMMON_FEED_PATHS: [&str; 4] = ["/feed", "/rss.xml", "/atom.xml", "/index.xml"];
+
+const FEED_TYPES: [&str; 3] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+];
+
+/// A feed found on a website.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredFeed {
+    pub url: Url,
+    pub title: Option<String>,
+}
+
+/// State of a discovery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscoveryState {
+    Searching,
+    /// The URL is a feed itself.
+    IsFeed(DiscoveredFeed),
+    /// Feeds found on the website.
+    Found(Vec<DiscoveredFeed>),
+    /// No feed is found, with the reason.
+    NotFound(String),
+}
+
+/// Returns the feeds linked by `<link rel="alternate">` in an HTML page,
+/// with relative URLs resolved against the URL of the page.
+pub fn find_feed_links(html: &str, base: &Url) -> Vec<DiscoveredFeed> {
+    let document = scraper::Html::parse_document(html);
+    let selector = scraper::Selector::parse("link[rel][type][href]").unwrap();
+    let mut feeds: Vec<DiscoveredFeed> = vec![];
+    for link in document.select(&selector) {
+        let link = link.value();
+        let is_alternate = link
+            .attr("rel")
+            .is_some_and(|rel| rel.split_whitespace().any(|rel| rel == "alternate"));
+        let is_feed = link.attr("type").is_some_and(|typ| {
+            FEED_TYPES
+                .iter()
+                .any(|feed_type| typ.trim().eq_ignore_ascii_case(feed_type))
+        });
+        if !is_alternate || !is_feed {
+            continue;
+        }
+        let Some(url) = link
+            .attr("href")
+            .and_then(|href| base.join(href.trim()).ok())
+        else {
+            continue;
+        };
+        if feeds.iter().any(|feed| feed.url == url) {
+            continue;
+        }
+        feeds.push(DiscoveredFeed {
+            url,
+            title: link
+                .attr("title")
+                .map(|title| title.trim().to_owned())
+                .filter(|title| !title.is_empty()),
+        });
+    }
+    feeds
+}
+
+fn parse_feed_title(response: &ehttp::Response) -> Option<Option<String>> {
+    feed_rs::parser::parse_with_uri(
+        std::io::Cursor::new(&response.bytes),
+        Some(response.url.as_str()),
+    )
+    .ok()
+    .map(|feed| feed.title.map(|title| title.content))
+}
+
+/// Discovery of the feeds of a website, which runs in the background.
+#[derive(Clone)]
+pub struct Discovery {
+    state: Arc<Mutex<DiscoveryState>>,
+    /// Response of the URL if it's a feed, which the first synchronization can use.
+    feed_response: Arc<Mutex<Option<ehttp::Response>>>,
+}
+
+impl Discovery {
+    /// Starts discovering feeds from the URL, which can be a feed or a web page.
+    /// If it's a web page linking to no feed, common feed paths of the site are probed.
+    pub fn start(fetcher: SharedFetcher, url: Url) -> Self {
+        let state = Arc::new(Mutex::new(DiscoveryState::Searching));
+        let feed_response = Arc::new(Mutex::new(None));
+        let discovery = Discovery {
+            state: state.clone(),
+            feed_response: feed_response.clone(),
+        };
+        let prober = fetcher.clone();
+        fetcher.fetch(
+            ehttp::Request::get(url.as_str()),
+            Box::new(move |result| {
+                let response = match result {
+                    Ok(response) if response.ok => response,
+                    Ok(response) => {
+                        *state.lock().unwrap() = DiscoveryState::NotFound(format!(
+                            "{} {}",
+                            response.status, response.status_text
+                        ));
+                        return;
+                    }
+                    Err(err) => {
+                        *state.lock().unwrap() = DiscoveryState::NotFound(err);
+                        return;
+                    }
+                };
+                let base = Url::parse(&response.url).unwrap_or(url);
+                if let Some(title) = parse_feed_title(&response) {
+                    *feed_response.lock().unwrap() = Some(response);
+                    *state.lock().unwrap() =
+                        DiscoveryState::IsFeed(DiscoveredFeed { url: base, title });
+                    return;
+                }
+                let feeds = find_feed_links(&String::from_utf8_lossy(&response.bytes), &base);
+                if !feeds.is_empty() {
+                    *state.lock().unwrap() = DiscoveryState::Found(feeds);
+                    return;
+                }
+                Self::probe(prober, base, state);
+            }),
+        );
+        discovery
+    }
+
+    fn probe(fetcher: SharedFetcher, base: Url, state: Arc<Mutex<DiscoveryState>>) {
+        let candidates: Vec<Url> = COMMON_FEED_PATHS
+            .iter()
+            .filter_map(|path| base.join(path).ok())
+            .collect();
+        // Feeds found by the probes, in the order of the candidates.
+        let probed = Arc::new(Mutex::new(vec![None; candidates.len()]));
+        let remaining = Arc::new(Mutex::new(candidates.len()));
+        for (index, candidate) in candidates.into_iter().enumerate() {
+            let probed = probed.clone();
+            let remaining = remaining.clone();
+            let state = state.clone();
+            fetcher.fetch(
+                ehttp::Request::get(candidate.as_str()),
+                Box::new(move |result| {
+                    if let Some(title) = result
+                        .ok()
+                        .filter(|response| response.ok)
+                        .and_then(|response| parse_feed_title(&response))
+                    {
+                        probed.lock().unwrap()[index] = Some(DiscoveredFeed {
+                            url: candidate,
+                            title,
+                        });
+                    }
+                    let mut remaining = remaining.lock().unwrap();
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        let feeds: Vec<DiscoveredFeed> =
+                            probed.lock().unwrap().drain(..).flatten().collect();
+                        *state.lock().unwrap() = if feeds.is_empty() {
+                            DiscoveryState::NotFound("No feed is found on the website.".to_owned())
+                        } else {
+                            DiscoveryState::Found(feeds)
+                        };
+                    }
+                }),
+            );
+        }
+    }
+
+    pub fn state(&self) -> DiscoveryState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Takes the response of the URL if it's a feed, see [`DiscoveryState::IsFeed`].
+    pub fn take_feed_response(&self) -> Option<ehttp::Response> {
+        self.feed_response.lock().unwrap().take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_feed_links, DiscoveredFeed, Discovery, DiscoveryState};
+    use crate::subscription::fetch::{Fixture, FixtureFetcher, SharedFetcher};
+    use url::Url;
+
+    const HOMEPAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <title>Example</title>
+  <link rel="stylesheet" type="text/css" href="/style.css">
+  <link rel="alternate" type="application/rss+xml" title="Posts" href="/posts.xml">
+  <link rel="alternate" type="application/atom+xml" href="https://cdn.example.com/atom">
+  <link rel="alternate" type="application/feed+json" title="JSON" href="feed.json">
+  <link rel="alternate" type="application/rss+xml" title="Duplicated" href="/posts.xml">
+  <link rel="alternate" hreflang="en" href="/en/">
+</head>
+<body>Hello</body>
+</html>"#;
+
+    #[test]
+    fn find_links() {
+        let base = Url::parse("https://example.com/blog/").unwrap();
+        let feeds = find_feed_links(HOMEPAGE, &base);
+        assert_eq!(
+            feeds,
+            vec![
+                DiscoveredFeed {
+                    url: Url::parse("https://example.com/posts.xml").unwrap(),
+                    title: Some("Posts".to_owned()),
+                },
+                DiscoveredFeed {
+                    url: Url::parse("https://cdn.example.com/atom").unwrap(),
+                    title: None,
+                },
+                DiscoveredFeed {
+                    url: Url::parse("https://example.com/blog/feed.json").unwrap(),
+                    title: Some("JSON".to_owned()),
+                },
+            ]
+        );
+    }
+
+    fn start(fetcher: &FixtureFetcher, url: &str) -> Discovery {
+        Discovery::start(
+            SharedFetcher::new(fetcher.clone()),
+            Url::parse(url).unwrap(),
+        )
+    }
+
+    fn discover(fetcher: &FixtureFetcher, url: &str) -> DiscoveryState {
+        start(fetcher, url).state()
+    }
+
+    #[test]
+    fn discover_feeds() {
+        let fetcher = FixtureFetcher::new();
+        let rss = Fixture::try_from_file("./fixtures/rss.xml").unwrap();
+        let atom = Fixture::try_from_file("./fixtures/atom.xml").unwrap();
+        fetcher
+            .serve("https://linked.example.com/", Fixture::ok(HOMEPAGE))
+            .serve("https://probed.example.com/", Fixture::ok("<html></html>"))
+            .serve("https://probed.example.com/feed", rss.clone())
+            .serve("https://probed.example.com/atom.xml", atom)
+            .serve("https://empty.example.com/", Fixture::ok("<html></html>"))
+            .serve("https://direct.example.com/rss.xml", rss);
+
+        assert!(
+            matches!(discover(&fetcher, "https://linked.example.com/"), DiscoveryState::Found(feeds) if feeds.len() == 3)
+        );
+        assert_eq!(
+            discover(&fetcher, "https://probed.example.com/"),
+            DiscoveryState::Found(vec![
+                DiscoveredFeed {
+                    url: Url::parse("https://probed.example.com/feed").unwrap(),
+                    title: Some("RSS Fixture".to_owned()),
+                },
+                DiscoveredFeed {
+                    url: Url::parse("https://probed.example.com/atom.xml").unwrap(),
+                    title: Some("Atom Fixture".to_owned()),
+                },
+            ])
+        );
+        assert!(matches!(
+            discover(&fetcher, "https://empty.example.com/"),
+            DiscoveryState::NotFound(_)
+        ));
+        assert_eq!(
+            discover(&fetcher, "https://direct.example.com/rss.xml"),
+            DiscoveryState::IsFeed(DiscoveredFeed {
+                url: Url::parse("https://direct.example.com/rss.xml").unwrap(),
+                title: Some("RSS Fixture".to_owned()),
+            })
+        );
+        // Only feeds keep their responses.
+        assert!(start(&fetcher, "https://direct.example.com/rss.xml")
+            .take_feed_response()
+            .is_some());
+        assert!(start(&fetcher, "https://linked.example.com/")
+            .take_feed_response()
+            .is_none());
+    }
+}
@@ -1,11 +1,21 @@
 //! Data structures and operating interfaces for Rss feeds.
-use super::article::{Article, ArticleUuid};
+use super::article::{Article, ArticleUuid, Body, BodySource, Flag};
+use super::dedup::DuplicateIndex;
+use super::fetch::{fetch_following_redirects, Fetcher, Followed, OnFollowed, SharedFetcher};
 use super::opml;
+use super::order::{self, SortKey, SortMode};
+use super::retention::{Retention, RetentionPolicy};
+use super::rule::{Condition, Matcher, Rule};
+use super::schedule::RefreshHints;
+use super::search::{Query, SearchFilter, SearchIndex};
+use super::tag::TagColor;
 use anyhow::{anyhow, Context, Error, Ok, Result};
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::cmp::{Eq, PartialEq};
-use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::{cell::RefCell, rc::Rc};
 use url::Url;
@@ -45,6 +55,23 @@ impl From<Uuid> for FolderUuid {
     }
 }
 
+/// Universally Unique Identifier for [`SmartFolder`].
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub struct SmartFolderUuid(Uuid);
+
+impl Deref for SmartFolderUuid {
+    type Target = Uuid;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Uuid> for SmartFolderUuid {
+    fn from(value: Uuid) -> Self {
+        SmartFolderUuid(value)
+    }
+}
+
 /// OPML head information,
 /// which can be converted from [`opml::Head`].
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -58,6 +85,133 @@ impl From<opml::Head> for Head {
     }
 }
 
+/// HTTP cache validators of the latest successful response of a feed,
+/// which are sent back on the next synchronization to make a conditional request.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Validators {
+    /// Value of the `ETag` response header.
+    pub etag: Option<String>,
+    /// Value of the `Last-Modified` response header.
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    /// Returns the validators carried by a response.
+    pub fn from_response(response: &ehttp::Response) -> Self {
+        Validators {
+            etag: response.headers.get("etag").map(String::to_owned),
+            last_modified: response.headers.get("last-modified").map(String::to_owned),
+        }
+    }
+
+    /// Adds `If-None-Match` and `If-Modified-Since` headers to a request.
+    pub fn apply_to(&self, request: &mut ehttp::Request) {
+        if let Some(etag) = &self.etag {
+            request
+                .headers
+                .insert("If-None-Match".to_owned(), etag.to_owned());
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request
+                .headers
+                .insert("If-Modified-Since".to_owned(), last_modified.to_owned());
+        }
+    }
+}
+
+/// Kind of error which makes a synchronization fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SyncErrorKind {
+    /// The request could not be sent or the response could not be received.
+    Network,
+    /// The server answered with an unsuccessful status code.
+    Http,
+    /// The response could not be parsed as a feed.
+    Parse,
+}
+
+impl std::fmt::Display for SyncErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncErrorKind::Network => write!(f, "network error"),
+            SyncErrorKind::Http => write!(f, "HTTP error"),
+            SyncErrorKind::Parse => write!(f, "parse error"),
+        }
+    }
+}
+
+/// Error of the latest failed synchronization.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SyncError {
+    pub kind: SyncErrorKind,
+    pub message: String,
+}
+
+/// Record of the synchronizations of an [`Entry`].
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SyncRecord {
+    /// When the latest synchronization started.
+    pub last_attempt: Option<DateTime<Utc>>,
+    /// When the latest successful synchronization finished.
+    pub last_success: Option<DateTime<Utc>>,
+    /// HTTP status code of the latest response.
+    pub http_status: Option<u16>,
+    /// Error of the latest synchronization, `None` if it succeeded.
+    pub error: Option<SyncError>,
+    /// Number of new articles fetched by the latest successful synchronization.
+    pub new_articles: usize,
+    /// Number of articles pruned after the latest successful synchronization.
+    #[serde(default)]
+    pub pruned_articles: usize,
+}
+
+impl SyncRecord {
+    fn begin(&mut self) {
+        self.last_attempt = Some(Utc::now());
+    }
+
+    fn succeed(&mut self, http_status: u16, new_articles: usize) {
+        self.last_success = Some(Utc::now());
+        self.http_status = Some(http_status);
+        self.error = None;
+        self.new_articles = new_articles;
+        self.pruned_articles = 0;
+    }
+
+    fn fail(&mut self, http_status: Option<u16>, kind: SyncErrorKind, message: impl ToString) {
+        self.http_status = http_status;
+        self.error = Some(SyncError {
+            kind,
+            message: message.to_string(),
+        });
+    }
+}
+
+/// Describes the response if it's an HTML page rather than a feed,
+/// like an error page or a login wall.
+fn describe_html_page(response: &ehttp::Response) -> Option<String> {
+    let is_html = response
+        .content_type()
+        .is_some_and(|content_type| content_type.contains("text/html"))
+        || String::from_utf8_lossy(&response.bytes[..response.bytes.len().min(64)])
+            .trim_start()
+            .to_ascii_lowercase()
+            .starts_with("<!doctype html");
+    if !is_html {
+        return None;
+    }
+    let document = scraper::Html::parse_document(&String::from_utf8_lossy(&response.bytes));
+    let title = document
+        .select(&scraper::Selector::parse("title").unwrap())
+        .next()
+        .map(|title| title.text().collect::<String>().trim().to_owned())
+        .filter(|title| !title.is_empty());
+    Some(match title {
+        Some(title) => format!("got an HTML page \"{title}\" instead of a feed"),
+        None => "got an HTML page instead of a feed".to_owned(),
+    })
+}
+
 /// Feed entry, the basic unit for getting subsciptions from feed,
 /// which can be converted from [`opml::Entry`] (see [`Entry::try_from`]).
 #[allow(unused)]
@@ -80,6 +234,35 @@ pub struct Entry {
     uuid: EntryUuid,
     /// Is synchronizing now?
     is_synchronizing: Arc<Mutex<bool>>,
+    /// Cache validators for conditional requests.
+    #[serde(default)]
+    validators: Arc<Mutex<Validators>>,
+    /// Record of the latest synchronizations.
+    #[serde(default)]
+    sync_record: Arc<Mutex<SyncRecord>>,
+    /// Refresh interval in minutes, overrides the default one if set.
+    #[serde(default)]
+    refresh_interval: Option<u32>,
+    /// Refresh hints published by the feed.
+    #[serde(default)]
+    refresh_hints: Arc<Mutex<RefreshHints>>,
+    /// Has the feed been removed for good (`410 Gone`)?
+    /// Dead entries are not refreshed automatically.
+    #[serde(default)]
+    dead: Arc<Mutex<bool>>,
+    /// Where the feed has permanently moved to, not applied yet (see [`Feed::apply_moves`]).
+    #[serde(skip)]
+    moved_to: Arc<Mutex<Option<Url>>>,
+    /// Retention rules of articles of this entry.
+    #[serde(default)]
+    retention: Retention,
+    /// Guids of articles pruned but still in the feed, which are not added again.
+    #[serde(default)]
+    pruned: Arc<Mutex<BTreeSet<String>>>,
+    /// Position among the entries in the same folder, or among orphan entries,
+    /// which they are ordered by unless sorted otherwise.
+    #[serde(default)]
+    position: u32,
 }
 
 impl Entry {
@@ -95,6 +278,15 @@ impl Entry {
             belong_to: None,
             uuid: Uuid::new_v4().into(),
             is_synchronizing: Arc::new(Mutex::new(false)),
+            validators: Arc::new(Mutex::new(Validators::default())),
+            sync_record: Arc::new(Mutex::new(SyncRecord::default())),
+            refresh_interval: None,
+            refresh_hints: Arc::new(Mutex::new(RefreshHints::default())),
+            dead: Arc::new(Mutex::new(false)),
+            moved_to: Arc::new(Mutex::new(None)),
+            retention: Retention::default(),
+            pruned: Arc::new(Mutex::new(BTreeSet::new())),
+            position: 0,
         }
     }
 
@@ -110,6 +302,15 @@ impl Entry {
             belong_to: None,
             uuid: Uuid::new_v4().into(),
             is_synchronizing: Arc::new(Mutex::new(false)),
+            validators: Arc::new(Mutex::new(Validators::default())),
+            sync_record: Arc::new(Mutex::new(SyncRecord::default())),
+            refresh_interval: None,
+            refresh_hints: Arc::new(Mutex::new(RefreshHints::default())),
+            dead: Arc::new(Mutex::new(false)),
+            moved_to: Arc::new(Mutex::new(None)),
+            retention: Retention::default(),
+            pruned: Arc::new(Mutex::new(BTreeSet::new())),
+            position: 0,
         }
     }
 
@@ -128,6 +329,11 @@ impl Entry {
         self
     }
 
+    /// Returns the UUID of the folder to which this entry belongs, if any.
+    pub fn belonging(&self) -> Option<FolderUuid> {
+        self.belong_to
+    }
+
     /// Returns the title of the entry.
     #[allow(unused)]
     pub fn title(&self) -> String {
@@ -139,10 +345,53 @@ impl Entry {
     }
 
     /// Set the title of the entry.
-    #[allow(unused)]
     pub fn rename(&mut self, alias: impl ToString) {
         self.alias = Some(alias.to_string());
     }
+
+    /// Returns the refresh interval in minutes overriding the default one.
+    pub fn refresh_interval(&self) -> Option<u32> {
+        self.refresh_interval
+    }
+
+    /// Sets the refresh interval in minutes, `None` to use the default one.
+    pub fn set_refresh_interval(&mut self, interval: Option<u32>) {
+        self.refresh_interval = interval;
+    }
+
+    /// Returns the refresh hints published by the feed.
+    pub fn refresh_hints(&self) -> RefreshHints {
+        self.refresh_hints.lock().unwrap().clone()
+    }
+
+    /// Returns the retention rules of the entry.
+    pub fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    /// Sets the retention rules of the entry.
+    pub fn set_retention(&mut self, retention: Retention) {
+        self.retention = retention;
+    }
+
+    /// Has the feed been removed for good?
+    pub fn is_dead(&self) -> bool {
+        *self.dead.lock().unwrap()
+    }
+
+    /// Returns when the entry should be refreshed next,
+    /// with the default refresh interval in minutes.
+    pub fn next_refresh(&self, default_interval: u32) -> DateTime<Utc> {
+        let last_attempt = self.sync_record.lock().unwrap().last_attempt;
+        match last_attempt {
+            Some(last_attempt) => self.refresh_hints.lock().unwrap().next_refresh(
+                last_attempt,
+                self.refresh_interval.unwrap_or(default_interval),
+            ),
+            // Never synchronized, it's due now.
+            None => Utc::now(),
+        }
+    }
 }
 
 impl TryFrom<opml::Entry> for Entry {
@@ -159,11 +408,20 @@ impl TryFrom<opml::Entry> for Entry {
             html_url: value.html_url,
             belong_to: None,
             is_synchronizing: Arc::new(Mutex::new(false)),
+            validators: Arc::new(Mutex::new(Validators::default())),
+            sync_record: Arc::new(Mutex::new(SyncRecord::default())),
+            refresh_interval: None,
+            refresh_hints: Arc::new(Mutex::new(RefreshHints::default())),
+            dead: Arc::new(Mutex::new(false)),
+            moved_to: Arc::new(Mutex::new(None)),
+            retention: Retention::default(),
+            pruned: Arc::new(Mutex::new(BTreeSet::new())),
+            position: 0,
         })
     }
 }
 
-/// A folder for containing a series of subscriptions on similar topics,
+/// A folder for containing a series of subscriptions on similar topics, and other folders,
 /// which can **not** be converted from [`opml::Folder`] directly.
 #[allow(unused)]
 #[derive(Debug, Deserialize, Serialize)]
@@ -172,8 +430,21 @@ pub struct Folder {
     title: String,
     /// The IDs of entries which belong to this folder.
     entries: BTreeSet<EntryUuid>,
+    /// The ID of the folder which this folder is inside, `None` if it's a top-level one.
+    #[serde(default)]
+    parent: Option<FolderUuid>,
+    /// The IDs of folders inside this folder.
+    #[serde(default)]
+    folders: BTreeSet<FolderUuid>,
     /// UUID of this feed folder.
     uuid: FolderUuid,
+    /// Retention rules of articles of entries in this folder.
+    #[serde(default)]
+    retention: Retention,
+    /// Position among the folders in the same folder, or among top-level folders,
+    /// which they are ordered by unless sorted otherwise.
+    #[serde(default)]
+    position: u32,
 }
 
 impl Folder {
@@ -183,7 +454,11 @@ impl Folder {
         Folder {
             title: name.to_string(),
             entries: BTreeSet::new(),
+            parent: None,
+            folders: BTreeSet::new(),
             uuid: Uuid::new_v4().into(),
+            retention: Retention::default(),
+            position: 0,
         }
     }
 
@@ -194,7 +469,6 @@ impl Folder {
     }
 
     /// Set the title of the folder.
-    #[allow(unused)]
     pub fn rename(&mut self, name: impl ToString) {
         self.title = name.to_string();
     }
@@ -204,6 +478,105 @@ impl Folder {
     pub fn get_entry_ids(&self) -> impl Iterator<Item = &EntryUuid> {
         self.entries.iter()
     }
+
+    /// Returns the ID of the folder which this folder is inside.
+    pub fn parent(&self) -> Option<FolderUuid> {
+        self.parent
+    }
+
+    /// Returns the IDs of the folders inside this folder, not including those inside them.
+    pub fn get_folder_ids(&self) -> impl Iterator<Item = &FolderUuid> {
+        self.folders.iter()
+    }
+
+    /// Returns the retention rules of entries in the folder.
+    pub fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    /// Sets the retention rules of entries in the folder.
+    pub fn set_retention(&mut self, retention: Retention) {
+        self.retention = retention;
+    }
+}
+
+/// A virtual folder containing the articles which match a saved rule,
+/// rather than those of the entries belonging to it.
+/// Its articles are found when they are listed, so they are always up to date.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SmartFolder {
+    /// The title of the smart folder.
+    title: String,
+    /// The rule which articles in the smart folder match.
+    pub rule: Rule,
+    /// UUID of this smart folder.
+    uuid: SmartFolderUuid,
+}
+
+impl SmartFolder {
+    /// Creates a `SmartFolder` with the rule.
+    pub fn new(name: impl ToString, rule: Rule) -> Self {
+        SmartFolder {
+            title: name.to_string(),
+            rule,
+            uuid: Uuid::new_v4().into(),
+        }
+    }
+
+    /// Returns the title of the smart folder.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Set the title of the smart folder.
+    pub fn rename(&mut self, name: impl ToString) {
+        self.title = name.to_string();
+    }
+}
+
+/// Adds a folder of OPML, along with the folders and entries inside, and returns its UUID.
+/// Folders and entries are positioned in the order of OPML.
+fn add_opml_folder(
+    folder: opml::Folder,
+    parent: Option<FolderUuid>,
+    position: u32,
+    entries_map: &mut BTreeMap<EntryUuid, Rc<RefCell<Entry>>>,
+    folders_map: &mut BTreeMap<FolderUuid, Rc<RefCell<Folder>>>,
+) -> Result<FolderUuid> {
+    let uuid = Uuid::new_v4().into();
+    let mut entries = BTreeSet::new();
+    let mut folders = BTreeSet::new();
+    for (position, outline) in (0..).zip(folder.outlines) {
+        match outline {
+            opml::Outline::Entry(e) => {
+                let mut entry = Entry::try_from(e)
+                    .with_context(|| format!("At folder {}", folder.text))?
+                    .set_belonging(&uuid);
+                entry.position = position;
+                let uuid = entry.uuid;
+                let entry = Rc::new(RefCell::new(entry));
+                entries_map.insert(uuid, entry);
+                entries.insert(uuid);
+            }
+            opml::Outline::Folder(f) => {
+                let folder_uuid =
+                    add_opml_folder(f, Some(uuid), position, entries_map, folders_map)
+                        .with_context(|| format!("At folder {}", folder.text))?;
+                folders.insert(folder_uuid);
+            }
+        }
+    }
+    let folder = Rc::new(RefCell::new(Folder {
+        title: folder.text,
+        entries,
+        parent,
+        folders,
+        uuid,
+        retention: Retention::default(),
+        position,
+    }));
+    folders_map.insert(uuid, folder);
+    Ok(uuid)
 }
 
 impl TryFrom<opml::Opml> for Feed {
@@ -214,33 +587,18 @@ impl TryFrom<opml::Opml> for Feed {
         let mut orphans = HashSet::new();
         let mut entries_map = BTreeMap::new();
         let mut folders_map = BTreeMap::new();
-        for outline in value.body.outlines {
+        for (position, outline) in (0..).zip(value.body.outlines) {
             match outline {
                 opml::Outline::Entry(e) => {
-                    let entry = Entry::try_from(e)?;
+                    let mut entry = Entry::try_from(e)?;
+                    entry.position = position;
                     let uuid = entry.uuid;
                     let entry = Rc::new(RefCell::new(entry));
                     entries_map.insert(uuid, entry);
                     orphans.insert(uuid);
                 }
                 opml::Outline::Folder(f) => {
-                    let uuid = Uuid::new_v4().into();
-                    let mut entries = BTreeSet::new();
-                    for e in f.entries {
-                        let entry = Entry::try_from(e)
-                            .with_context(|| format!("At folder {}", f.text))?
-                            .set_belonging(&uuid);
-                        let uuid = entry.uuid;
-                        let entry = Rc::new(RefCell::new(entry));
-                        entries_map.insert(uuid, entry);
-                        entries.insert(uuid);
-                    }
-                    let folder = Rc::new(RefCell::new(Folder {
-                        title: f.text,
-                        entries,
-                        uuid,
-                    }));
-                    folders_map.insert(uuid, folder);
+                    add_opml_folder(f, None, position, &mut entries_map, &mut folders_map)?;
                 }
             }
         }
@@ -251,12 +609,109 @@ impl TryFrom<opml::Opml> for Feed {
             folders_map,
             entries_map,
             articles_map: Arc::new(Mutex::new(BTreeMap::new())),
+            retention: RetentionPolicy::default(),
+            fetcher: SharedFetcher::default(),
+            body_source: None,
+            search_index: Default::default(),
+            tag_colors: BTreeMap::new(),
+            sort_mode: SortMode::default(),
+            smart_folders: vec![],
+            revision: Revision::default(),
+            content_revision: Revision::default(),
+            changed_articles: Default::default(),
+            compiled_rules: Default::default(),
+            duplicate_index: Default::default(),
+            tag_counts: Default::default(),
+            unread_counts: Default::default(),
         })
     }
 }
 
 type ArticlesMap = Arc<Mutex<BTreeMap<ArticleUuid, Arc<Mutex<Article>>>>>;
 
+/// Tags with the numbers of articles tagged, by their names.
+type TagCounts = Vec<(String, usize)>;
+
+/// Revision of the articles of a library, which changes whenever articles are added, removed,
+/// moved or changed by the reader, so that what's listed of them can be cached until then.
+/// Revisions are unique across libraries, so a library replacing another doesn't match caches.
+#[derive(Debug, Clone)]
+struct Revision(Arc<AtomicU64>);
+
+impl Revision {
+    fn next() -> u64 {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn bump(&self) {
+        self.0.store(Self::next(), Ordering::Relaxed);
+    }
+}
+
+impl Default for Revision {
+    fn default() -> Self {
+        Self(Arc::new(AtomicU64::new(Self::next())))
+    }
+}
+
+/// Articles added, changed or removed since the library was committed at last.
+#[derive(Debug)]
+pub struct ChangedArticles {
+    /// Whether all articles are to be committed, as those of a library never committed,
+    /// so that the committed ones missing from them are removed.
+    pub all: bool,
+    pub ids: BTreeSet<ArticleUuid>,
+}
+
+impl Default for ChangedArticles {
+    fn default() -> Self {
+        Self {
+            all: true,
+            ids: BTreeSet::new(),
+        }
+    }
+}
+
+impl ChangedArticles {
+    fn extend(&mut self, other: ChangedArticles) {
+        self.all |= other.all;
+        self.ids.extend(other.ids);
+    }
+}
+
+/// Prunes the articles of an entry by the retention rules, and remembers their guids
+/// so that they are not added again. Returns the articles pruned.
+fn prune_articles(
+    policy: &RetentionPolicy,
+    retention: &Retention,
+    article_id_set: &mut BTreeSet<ArticleUuid>,
+    article_map: &mut BTreeMap<ArticleUuid, Arc<Mutex<Article>>>,
+    pruned: &mut BTreeSet<String>,
+) -> Vec<ArticleUuid> {
+    let pruned_ids = {
+        let articles: Vec<_> = article_id_set
+            .iter()
+            .filter_map(|id| Some((id, article_map.get(id)?.lock().unwrap())))
+            .collect();
+        policy.select_pruned(
+            retention,
+            articles.iter().map(|(id, article)| (*id, &**article)),
+            Utc::now(),
+        )
+    };
+    for id in &pruned_ids {
+        article_id_set.remove(id);
+        article_map.remove(id);
+        pruned.insert(id.guid().to_owned());
+    }
+    pruned_ids
+}
+
 /// Main data structure for RSS feeds,
 /// which contains orphan entries directly and folders with entries inside.
 /// Feed can be converted from [`opml::Opml`].
@@ -274,10 +729,315 @@ pub struct Feed {
     /// Map for all folders.
     folders_map: BTreeMap<FolderUuid, Rc<RefCell<Folder>>>,
     /// Map for all articles.
+    /// Articles are kept in the storage instead (see [`super::storage`]).
+    #[serde(skip)]
     articles_map: ArticlesMap,
+    /// Global retention policy of articles.
+    #[serde(default)]
+    retention: RetentionPolicy,
+    /// Fetcher for synchronizing entries.
+    #[serde(skip)]
+    fetcher: SharedFetcher,
+    /// Source of the bodies of articles not loaded yet.
+    #[serde(skip)]
+    body_source: Option<Arc<dyn BodySource>>,
+    /// Full-text index of articles.
+    #[serde(skip)]
+    search_index: Arc<Mutex<SearchIndex>>,
+    /// Colors which the reader gives to tags of articles.
+    #[serde(default)]
+    tag_colors: BTreeMap<String, TagColor>,
+    /// How folders and entries are ordered in the library, kept until changed.
+    #[serde(default)]
+    sort_mode: SortMode,
+    /// Smart folders, in the order they are created.
+    #[serde(default)]
+    smart_folders: Vec<SmartFolder>,
+    /// Revision of the articles.
+    #[serde(skip)]
+    revision: Revision,
+    /// Revision of which articles there are and what they say,
+    /// which is kept when they are read, flagged or tagged.
+    #[serde(skip)]
+    content_revision: Revision,
+    /// Articles to be committed.
+    #[serde(skip)]
+    changed_articles: Arc<Mutex<ChangedArticles>>,
+    /// Rules of smart folders compiled at last.
+    #[serde(skip)]
+    compiled_rules: RefCell<HashMap<SmartFolderUuid, CompiledRule>>,
+    /// Index of the copies of articles built at last, with the content revision it's built at.
+    #[serde(skip)]
+    duplicate_index: RefCell<Option<(u64, Rc<DuplicateIndex>)>>,
+    /// Tags with the numbers of articles tagged counted at last, with the revision counted at.
+    #[serde(skip)]
+    tag_counts: RefCell<Option<(u64, TagCounts)>>,
+    /// Numbers of unread articles of the entries counted at last, with the revision counted at.
+    #[serde(skip)]
+    unread_counts: RefCell<Option<(u64, HashMap<EntryUuid, usize>)>>,
+}
+
+/// A rule of a smart folder compiled at a revision of the articles,
+/// with the number of articles matching it once counted.
+#[derive(Debug, Clone)]
+struct CompiledRule {
+    revision: u64,
+    compiled_at: DateTime<Utc>,
+    /// The matcher, or the message of the error compiling it.
+    matcher: std::result::Result<Rc<Matcher>, String>,
+    count: Option<usize>,
 }
 
 impl Feed {
+    /// Sets the fetcher for synchronizing entries.
+    #[allow(unused)]
+    pub fn set_fetcher(&mut self, fetcher: impl Fetcher + 'static) {
+        self.fetcher = SharedFetcher::new(fetcher);
+    }
+
+    /// Sets the source of the bodies of articles not loaded yet.
+    pub fn set_body_source(&mut self, body_source: Arc<dyn BodySource>) {
+        self.body_source = Some(body_source);
+    }
+
+    /// Returns the revision of the articles, which changes whenever articles are added,
+    /// removed, moved or changed by the reader, and by which what's listed can be cached.
+    pub fn revision(&self) -> u64 {
+        self.revision.get()
+    }
+
+    /// Returns the map of all articles.
+    pub fn get_articles_map(&self) -> ArticlesMap {
+        self.articles_map.clone()
+    }
+
+    /// Marks the articles to be committed, which are added, changed or removed.
+    pub fn mark_articles_changed(&self, ids: impl IntoIterator<Item = ArticleUuid>) {
+        self.changed_articles.lock().unwrap().ids.extend(ids);
+    }
+
+    /// Takes the articles changed since the last commit, to commit them.
+    pub fn take_changed_articles(&self) -> ChangedArticles {
+        std::mem::replace(
+            &mut self.changed_articles.lock().unwrap(),
+            ChangedArticles {
+                all: false,
+                ids: BTreeSet::new(),
+            },
+        )
+    }
+
+    /// Puts back the articles taken to commit, if the commit fails.
+    pub fn restore_changed_articles(&self, changed: ChangedArticles) {
+        self.changed_articles.lock().unwrap().extend(changed);
+    }
+
+    /// Adds the articles loaded from the storage, and makes the entries agree with them:
+    /// articles missing from the storage are dropped from the entries,
+    /// and articles missing from their entries are added back.
+    pub fn attach_articles(&mut self, articles: impl IntoIterator<Item = (ArticleUuid, Article)>) {
+        let mut article_map = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map");
+        let mut changed = self.changed_articles.lock().unwrap();
+        article_map.extend(articles.into_iter().map(|(id, article)| {
+            changed.ids.insert(id.clone());
+            (id, Arc::new(Mutex::new(article)))
+        }));
+        article_map.retain(|id, _| {
+            let kept = self.entries_map.contains_key(&id.feed_id());
+            if !kept {
+                changed.ids.insert(id.clone());
+            }
+            kept
+        });
+        drop(changed);
+        // Indexed at the next search, with their bodies.
+        self.search_index.lock().unwrap().set_built(false);
+        self.revision.bump();
+        self.content_revision.bump();
+        for entry in self.entries_map.values() {
+            let entry = entry.borrow();
+            let mut article_id_set = entry
+                .articles
+                .lock()
+                .expect("Failed to get the lock on article id set.");
+            article_id_set.retain(|id| article_map.contains_key(id));
+            article_id_set.extend(
+                article_map
+                    .keys()
+                    .filter(|id| id.feed_id() == entry.uuid)
+                    .cloned(),
+            );
+        }
+    }
+
+    /// Attempts to load the body of the article if it's not loaded yet.
+    /// The article is left unloaded if it fails,
+    /// so that the stored body is not overwritten by an empty one.
+    pub fn try_load_article_body(&self, id: &ArticleUuid) -> Result<()> {
+        let article = self.try_get_article_by_id(id)?;
+        if article.lock().unwrap().is_body_loaded() {
+            return Ok(());
+        }
+        let body = self.try_load_stored_body(id)?;
+        article.lock().unwrap().set_body(body);
+        Ok(())
+    }
+
+    /// Attempts to load the body of the article from the body source.
+    fn try_load_stored_body(&self, id: &ArticleUuid) -> Result<Body> {
+        let body_source = self
+            .body_source
+            .as_ref()
+            .context("Article body is not loaded and there's nowhere to load it from")?;
+        Ok(body_source.try_load_body(id)?.unwrap_or_default())
+    }
+
+    /// Returns the body of the article, loading it without keeping it if it's not loaded.
+    pub fn try_get_article_body(&self, id: &ArticleUuid) -> Result<Body> {
+        let article = self.try_get_article_by_id(id)?;
+        let article = article.lock().unwrap();
+        if article.is_body_loaded() {
+            return Ok(Body {
+                summary: article.summary.clone(),
+                content: article.content.clone(),
+            });
+        }
+        drop(article);
+        self.try_load_stored_body(id)
+    }
+
+    /// Merges another library, e.g. restored from a backup, into this one,
+    /// returns the numbers of entries and articles added.
+    ///
+    /// Entries are matched by their feed URLs. Unmatched entries are added with their settings,
+    /// into the folders of the same paths of names, which are created if missing.
+    /// Articles missing from this library are added, while the state of the others put by
+    /// the reader is merged (see [`Article::merge_state`]).
+    pub fn merge(
+        &mut self,
+        other: Feed,
+        articles: impl IntoIterator<Item = (ArticleUuid, Article)>,
+    ) -> (usize, usize) {
+        let mut folder_ids = HashMap::new();
+        // Parents come before their children.
+        let other_folder_ids: Vec<FolderUuid> = other
+            .get_top_level_folder_ids()
+            .iter()
+            .flat_map(|id| other.try_get_folder_ids_in_tree(id).unwrap_or_default())
+            .collect();
+        for other_id in other_folder_ids {
+            let folder = other.folders_map[&other_id].borrow();
+            let name = folder.title().to_owned();
+            let parent = folder
+                .parent
+                .and_then(|parent| folder_ids.get(&parent).copied());
+            let siblings = match parent {
+                Some(parent) => self.try_get_subfolder_ids(&parent).unwrap_or_default(),
+                None => self.get_top_level_folder_ids(),
+            };
+            let matched = siblings
+                .into_iter()
+                .find(|id| self.folders_map[id].borrow().title() == name);
+            let folder_id = match (matched, parent) {
+                (Some(folder_id), _) => folder_id,
+                (None, Some(parent)) => self
+                    .try_add_folder_to_folder(Folder::new(name), &parent)
+                    .expect("Parents are added before their children"),
+                (None, None) => self.add_empty_folder(Folder::new(name)),
+            };
+            folder_ids.insert(other_id, folder_id);
+        }
+
+        let mut entry_ids = HashMap::new();
+        let mut added_entries = 0;
+        // Added in their order, which they keep among the entries added to the same folders.
+        let mut other_entries: Vec<_> = other.entries_map.iter().collect();
+        other_entries.sort_by_key(|(_, entry)| entry.borrow().position);
+        for (other_id, entry) in other_entries {
+            let entry = entry.borrow();
+            if let Some((id, _)) = self
+                .entries_map
+                .iter()
+                .find(|(_, existing)| existing.borrow().xml_url == entry.xml_url)
+            {
+                entry_ids.insert(*other_id, *id);
+                continue;
+            }
+            let folder_id = entry
+                .belong_to
+                .and_then(|folder_id| folder_ids.get(&folder_id));
+            let mut entry = Entry {
+                articles: Default::default(),
+                is_synchronizing: Default::default(),
+                belong_to: None,
+                ..entry.clone()
+            };
+            if self.entries_map.contains_key(other_id) {
+                entry.uuid = EntryUuid::from(Uuid::new_v4());
+            }
+            let id = match folder_id {
+                Some(folder_id) => self
+                    .try_add_entry_to_folder(entry, folder_id)
+                    .expect("Folders are just added"),
+                None => self.add_orphan_entry(entry),
+            };
+            entry_ids.insert(*other_id, id);
+            added_entries += 1;
+        }
+
+        let articles: Vec<(ArticleUuid, Article)> = {
+            let article_map = self
+                .articles_map
+                .lock()
+                .expect("Failed to get the lock on article map");
+            articles
+                .into_iter()
+                .filter_map(|(id, article)| {
+                    let feed_id = entry_ids.get(&id.feed_id())?;
+                    let id = ArticleUuid::new(feed_id, id.guid());
+                    if let Some(existing) = article_map.get(&id) {
+                        existing.lock().unwrap().merge_state(&article);
+                        self.mark_articles_changed([id]);
+                        return None;
+                    }
+                    Some((id, article.set_belonging(feed_id)))
+                })
+                .collect()
+        };
+        let added_articles = articles.len();
+        self.attach_articles(articles);
+        for (name, color) in other.tag_colors {
+            self.tag_colors.entry(name).or_insert(color);
+        }
+        // Smart folders are matched by their titles, and refer to the matched folders and entries.
+        for mut smart_folder in other.smart_folders {
+            if self
+                .smart_folders
+                .iter()
+                .any(|existing| existing.title == smart_folder.title)
+            {
+                continue;
+            }
+            for condition in &mut smart_folder.rule.conditions {
+                match condition {
+                    Condition::InFolder(id) => *id = folder_ids.get(id).copied().unwrap_or(*id),
+                    Condition::InEntry(id) => *id = entry_ids.get(id).copied().unwrap_or(*id),
+                    _ => {}
+                }
+            }
+            self.add_smart_folder(smart_folder);
+        }
+        (added_entries, added_articles)
+    }
+
+    /// Returns the fetcher for synchronizing entries.
+    pub fn fetcher(&self) -> SharedFetcher {
+        self.fetcher.clone()
+    }
+
     /// Returns all folders.
     #[allow(unused)]
     pub fn get_all_folders(&self) -> impl Iterator<Item = &Rc<RefCell<Folder>>> {
@@ -290,6 +1050,72 @@ impl Feed {
         self.folders_map.keys().map(FolderUuid::clone).collect()
     }
 
+    /// Returns the IDs of the folders which are not inside any folders,
+    /// in the order given by the reader.
+    pub fn get_top_level_folder_ids(&self) -> Vec<FolderUuid> {
+        let folder_ids = self
+            .folders_map
+            .iter()
+            .filter(|(_, folder)| folder.borrow().parent.is_none())
+            .map(|(id, _)| *id)
+            .collect();
+        self.sort_folder_ids(folder_ids, SortMode::Manual)
+    }
+
+    /// Attempts to return the IDs of the folders inside the folder,
+    /// not including those inside them, in the order given by the reader.
+    pub fn try_get_subfolder_ids(&self, folder_id: &FolderUuid) -> Result<Vec<FolderUuid>> {
+        let folder_ids = self
+            .try_get_folder_by_id(folder_id)?
+            .borrow()
+            .folders
+            .iter()
+            .copied()
+            .collect();
+        Ok(self.sort_folder_ids(folder_ids, SortMode::Manual))
+    }
+
+    /// Attempts to return the IDs of the folder and all folders inside it at any depth,
+    /// where parents come before their children.
+    pub fn try_get_folder_ids_in_tree(&self, folder_id: &FolderUuid) -> Result<Vec<FolderUuid>> {
+        let mut folder_ids = vec![];
+        let mut stack = vec![*folder_id];
+        while let Some(folder_id) = stack.pop() {
+            stack.extend(self.try_get_subfolder_ids(&folder_id)?.into_iter().rev());
+            folder_ids.push(folder_id);
+        }
+        Ok(folder_ids)
+    }
+
+    /// Attempts to return the IDs of the entries in the folder and in all folders inside it.
+    pub fn try_get_entry_ids_in_folder_tree(
+        &self,
+        folder_id: &FolderUuid,
+    ) -> Result<Vec<EntryUuid>> {
+        let mut entry_ids = vec![];
+        for folder_id in self.try_get_folder_ids_in_tree(folder_id)? {
+            entry_ids.extend(self.try_get_entry_ids_by_folder_id(&folder_id)?);
+        }
+        Ok(entry_ids)
+    }
+
+    /// Attempts to return the IDs of the folders from the top-level one to the folder itself.
+    pub fn try_get_folder_path(&self, folder_id: &FolderUuid) -> Result<Vec<FolderUuid>> {
+        let mut path = vec![*folder_id];
+        while let Some(parent) = self
+            .try_get_folder_by_id(path.last().unwrap())?
+            .borrow()
+            .parent
+        {
+            if path.contains(&parent) {
+                return Err(anyhow!("Folders are inside each other"));
+            }
+            path.push(parent);
+        }
+        path.reverse();
+        Ok(path)
+    }
+
     /// Returns all entries.
     #[allow(unused)]
     pub fn get_all_entries(&self) -> impl Iterator<Item = &Rc<RefCell<Entry>>> {
@@ -310,7 +1136,7 @@ impl Feed {
         self.entries_map.keys().map(EntryUuid::clone).collect()
     }
 
-    /// Returns the IDs of all entries in the folder.
+    /// Returns the IDs of all entries in the folder, in the order given by the reader.
     #[allow(unused)]
     pub fn try_get_entry_ids_by_folder_id(&self, folder_id: &FolderUuid) -> Result<Vec<EntryUuid>> {
         let folder = self.try_get_folder_by_id(folder_id)?;
@@ -321,13 +1147,188 @@ impl Feed {
             .iter()
             .map(EntryUuid::clone)
             .collect();
-        Ok(entry_ids)
+        Ok(self.sort_entry_ids(entry_ids, SortMode::Manual))
     }
 
-    /// Returns the IDs of all orphan entries.
+    /// Returns the IDs of all orphan entries, in the order given by the reader.
     #[allow(unused)]
     pub fn get_all_orphan_entry_ids(&self) -> Vec<EntryUuid> {
-        self.orphans.iter().map(EntryUuid::clone).collect()
+        let entry_ids = self.orphans.iter().map(EntryUuid::clone).collect();
+        self.sort_entry_ids(entry_ids, SortMode::Manual)
+    }
+
+    /// Attempts to return the IDs of the entries in the folder,
+    /// or of orphan entries if `folder_id` is `None`.
+    fn try_get_sibling_entry_ids(&self, folder_id: Option<&FolderUuid>) -> Result<Vec<EntryUuid>> {
+        match folder_id {
+            Some(folder_id) => self.try_get_entry_ids_by_folder_id(folder_id),
+            None => Ok(self.get_all_orphan_entry_ids()),
+        }
+    }
+
+    /// Attempts to return the IDs of the folders inside the folder,
+    /// or of top-level folders if `parent` is `None`.
+    fn try_get_sibling_folder_ids(&self, parent: Option<&FolderUuid>) -> Result<Vec<FolderUuid>> {
+        match parent {
+            Some(parent) => self.try_get_subfolder_ids(parent),
+            None => Ok(self.get_top_level_folder_ids()),
+        }
+    }
+
+    /// Returns the sort mode of folders and entries, which is kept until changed.
+    pub fn sort_mode(&self) -> SortMode {
+        self.sort_mode
+    }
+
+    /// Sets the sort mode of folders and entries, which is kept until changed.
+    pub fn set_sort_mode(&mut self, mode: SortMode) {
+        self.sort_mode = mode;
+    }
+
+    /// Returns what a folder or an entry containing the entries is sorted by in the mode.
+    fn sort_key(
+        &self,
+        position: u32,
+        name: String,
+        entry_ids: &[EntryUuid],
+        mode: SortMode,
+    ) -> SortKey {
+        let mut key = SortKey {
+            position,
+            name,
+            ..Default::default()
+        };
+        match mode {
+            SortMode::Unread => key.unread = self.count_unread_of_entries(entry_ids),
+            SortMode::Updated => {
+                let article_ids = self.get_article_ids_of_entries(entry_ids);
+                let article_map = self
+                    .articles_map
+                    .lock()
+                    .expect("Failed to get the lock on article map");
+                key.updated = article_ids
+                    .iter()
+                    .filter_map(|id| article_map.get(id))
+                    .map(|article| article.lock().unwrap().sort_date())
+                    .max();
+            }
+            SortMode::Manual | SortMode::Name => {}
+        }
+        key
+    }
+
+    /// Sorts the IDs of entries in the same folder, or of orphan entries, in the mode.
+    pub fn sort_entry_ids(&self, entry_ids: Vec<EntryUuid>, mode: SortMode) -> Vec<EntryUuid> {
+        let mut keyed: Vec<_> = entry_ids
+            .into_iter()
+            .filter_map(|id| {
+                let entry = self.entries_map.get(&id)?.borrow();
+                Some((
+                    id,
+                    self.sort_key(entry.position, entry.title(), &[id], mode),
+                ))
+            })
+            .collect();
+        order::sort(&mut keyed, mode);
+        keyed.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Sorts the IDs of folders in the same folder, or of top-level folders, in the mode.
+    /// Folders are sorted by the articles of all entries inside them at any depth.
+    pub fn sort_folder_ids(&self, folder_ids: Vec<FolderUuid>, mode: SortMode) -> Vec<FolderUuid> {
+        let mut keyed: Vec<_> = folder_ids
+            .into_iter()
+            .filter_map(|id| {
+                let entry_ids = if mode.uses_articles() {
+                    self.try_get_entry_ids_in_folder_tree(&id).ok()?
+                } else {
+                    vec![]
+                };
+                let folder = self.folders_map.get(&id)?.borrow();
+                let key = self.sort_key(folder.position, folder.title.clone(), &entry_ids, mode);
+                Some((id, key))
+            })
+            .collect();
+        order::sort(&mut keyed, mode);
+        keyed.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Returns the position after the last entry in the folder,
+    /// or the last orphan entry if `folder_id` is `None`.
+    fn next_entry_position(&self, folder_id: Option<&FolderUuid>) -> u32 {
+        self.try_get_sibling_entry_ids(folder_id)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.entries_map.get(id))
+            .map(|entry| entry.borrow().position + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the position after the last folder inside the folder,
+    /// or the last top-level folder if `parent` is `None`.
+    fn next_folder_position(&self, parent: Option<&FolderUuid>) -> u32 {
+        self.try_get_sibling_folder_ids(parent)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|id| self.folders_map.get(id))
+            .map(|folder| folder.borrow().position + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Positions the entries in the order of their IDs.
+    fn reposition_entries(&self, entry_ids: &[EntryUuid]) {
+        for (position, id) in (0..).zip(entry_ids) {
+            if let Some(entry) = self.entries_map.get(id) {
+                entry.borrow_mut().position = position;
+            }
+        }
+    }
+
+    /// Positions the folders in the order of their IDs.
+    fn reposition_folders(&self, folder_ids: &[FolderUuid]) {
+        for (position, id) in (0..).zip(folder_ids) {
+            if let Some(folder) = self.folders_map.get(id) {
+                folder.borrow_mut().position = position;
+            }
+        }
+    }
+
+    /// Attempts to move the entry by `offset` places among the entries in the same folder,
+    /// or among orphan entries, in the order given by the reader.
+    pub fn try_shift_entry(&mut self, id: &EntryUuid, offset: isize) -> Result<()> {
+        let folder_id = self.try_get_entry_by_id(id)?.borrow().belong_to;
+        let mut entry_ids = self.try_get_sibling_entry_ids(folder_id.as_ref())?;
+        order::shift(&mut entry_ids, id, offset);
+        self.reposition_entries(&entry_ids);
+        Ok(())
+    }
+
+    /// Attempts to move the folder by `offset` places among the folders in the same folder,
+    /// or among top-level folders, in the order given by the reader.
+    pub fn try_shift_folder(&mut self, id: &FolderUuid, offset: isize) -> Result<()> {
+        let parent = self.try_get_folder_by_id(id)?.borrow().parent;
+        let mut folder_ids = self.try_get_sibling_folder_ids(parent.as_ref())?;
+        order::shift(&mut folder_ids, id, offset);
+        self.reposition_folders(&folder_ids);
+        Ok(())
+    }
+
+    /// Sorts all folders and entries once in the mode,
+    /// which becomes the order given by the reader.
+    pub fn sort_once(&mut self, mode: SortMode) {
+        let parents = std::iter::once(None).chain(self.folders_map.keys().copied().map(Some));
+        for parent in parents.collect::<Vec<_>>() {
+            let entry_ids = self
+                .try_get_sibling_entry_ids(parent.as_ref())
+                .unwrap_or_default();
+            self.reposition_entries(&self.sort_entry_ids(entry_ids, mode));
+            let folder_ids = self
+                .try_get_sibling_folder_ids(parent.as_ref())
+                .unwrap_or_default();
+            self.reposition_folders(&self.sort_folder_ids(folder_ids, mode));
+        }
     }
 
     /// Attempts to return an folder by giving its ID.
@@ -364,30 +1365,188 @@ impl Feed {
             self.orphans.remove(id);
         }
         self.entries_map.remove(id);
+        // Articles are locked before the index, as when synchronizing.
+        let mut article_map = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map");
+        let mut search_index = self.search_index.lock().unwrap();
+        let mut changed = self.changed_articles.lock().unwrap();
+        article_map.retain(|article_id, _| {
+            let kept = article_id.feed_id() != *id;
+            if !kept {
+                search_index.remove(article_id);
+                changed.ids.insert(article_id.clone());
+            }
+            kept
+        });
+        self.revision.bump();
+        self.content_revision.bump();
         Ok(entry)
     }
 
-    /// Attempts to remove a folder by giving its ID.
-    #[allow(unused)]
+    /// Indexes at most `limit` of the articles not indexed yet, loading their bodies
+    /// without keeping them, and returns how many articles are indexed and in all.
+    /// Indexing a few at a time keeps the UI responsive while building the index.
+    pub fn try_index_articles(&self, limit: usize) -> Result<(usize, usize)> {
+        let articles: Vec<_> = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map")
+            .iter()
+            .map(|(id, article)| (id.clone(), Arc::clone(article)))
+            .collect();
+        let total = articles.len();
+        if self.search_index.lock().unwrap().is_built() {
+            return Ok((total, total));
+        }
+        let pending: Vec<_> = {
+            let search_index = self.search_index.lock().unwrap();
+            articles
+                .into_iter()
+                .filter(|(id, _)| !search_index.contains(id))
+                .collect()
+        };
+        let mut indexed = SearchIndex::default();
+        for (id, article) in pending.iter().take(limit) {
+            let mut article = article.lock().unwrap().clone();
+            if !article.is_body_loaded() {
+                article.set_body(self.try_get_article_body(id)?);
+            }
+            indexed.insert(id, &article);
+        }
+        let remaining = pending.len().saturating_sub(limit);
+        let mut search_index = self.search_index.lock().unwrap();
+        search_index.absorb(indexed);
+        search_index.set_built(remaining == 0);
+        Ok((total - remaining, total))
+    }
+
+    /// Attempts to search articles by the query and the filters, from the most relevant,
+    /// where the copies of the same article from different entries are collapsed.
+    /// Fails if articles are not all indexed yet, see [`Self::try_index_articles`].
+    pub fn try_search(&self, query: &Query, filter: &SearchFilter) -> Result<Vec<ArticleUuid>> {
+        if query.is_empty() {
+            return Ok(vec![]);
+        }
+        if !self.search_index.lock().unwrap().is_built() {
+            return Err(anyhow!("The search index is not ready"));
+        }
+        let entries: Option<HashSet<EntryUuid>> = match (&filter.entry, &filter.folder) {
+            (Some(entry), _) => Some(HashSet::from([*entry])),
+            (None, Some(folder)) => Some(
+                self.try_get_entry_ids_in_folder_tree(folder)?
+                    .into_iter()
+                    .collect(),
+            ),
+            (None, None) => None,
+        };
+        let results = self.search_index.lock().unwrap().search(query);
+        let article_map = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map");
+        // Articles may have been removed since they were indexed.
+        let results = results
+            .into_iter()
+            .filter(|(id, _)| {
+                article_map.get(id).is_some_and(|article| {
+                    filter.accepts(entries.as_ref(), &article.lock().unwrap())
+                })
+            })
+            .map(|(id, _)| id)
+            .collect();
+        drop(article_map);
+        Ok(self.collapse_duplicate_article_ids(results))
+    }
+
+    /// Attempts to remove a folder by giving its ID.
+    #[allow(unused)]
     pub fn try_remove_folder_by_id(&mut self, id: &FolderUuid) -> Result<()> {
-        let folder = self.try_get_folder_by_id(id)?;
-        // Remove all the entries of the folder from the general map of entries.
-        folder
+        let folder_ids = self.try_get_folder_ids_in_tree(id)?;
+        // Remove all the entries of the folder and of the folders inside,
+        // along with their articles.
+        for entry_id in self.try_get_entry_ids_in_folder_tree(id)? {
+            self.try_remove_entry_by_id(&entry_id)?;
+        }
+        if let Some(parent) = self.try_get_folder_by_id(id)?.borrow().parent {
+            self.try_get_folder_by_id(&parent)?
+                .try_borrow_mut()
+                .with_context(|| format!("Failed to borrow folder (UUID `{}`).", *parent))?
+                .folders
+                .remove(id);
+        }
+        for folder_id in folder_ids {
+            self.folders_map.remove(&folder_id);
+        }
+        self.revision.bump();
+        Ok(())
+    }
+
+    /// Attempts to add an empty folder inside another folder.
+    pub fn try_add_folder_to_folder(
+        &mut self,
+        mut folder: Folder,
+        to_folder_id: &FolderUuid,
+    ) -> Result<FolderUuid> {
+        let uuid = folder.uuid;
+        folder.position = self.next_folder_position(Some(to_folder_id));
+        self.try_get_folder_by_id(to_folder_id)?
             .try_borrow_mut()
-            .with_context(|| format!("Failed to borrow folder (UUID `{}`).", **id))?
-            .entries
-            .iter()
-            .for_each(|e| {
-                self.entries_map.remove(e);
-            });
-        self.folders_map.remove(id);
+            .with_context(|| format!("Failed to borrow folder (UUID `{}`).", **to_folder_id))?
+            .folders
+            .insert(uuid);
+        folder.parent = Some(*to_folder_id);
+        self.folders_map.insert(uuid, Rc::new(RefCell::new(folder)));
+        Ok(uuid)
+    }
+
+    /// Attempts to move a folder into another folder, or to the top level if `to_folder_id`
+    /// is `None`. A folder can't be moved into itself or any folder inside it.
+    pub fn try_move_folder_to_folder(
+        &mut self,
+        folder_id: &FolderUuid,
+        to_folder_id: Option<&FolderUuid>,
+    ) -> Result<()> {
+        let folder = self.try_get_folder_by_id(folder_id)?;
+        if let Some(to_folder_id) = to_folder_id {
+            if self.try_get_folder_path(to_folder_id)?.contains(folder_id) {
+                return Err(anyhow!("Can't move a folder into itself"));
+            }
+        }
+        let old_parent = folder.borrow().parent;
+        if old_parent.as_ref() == to_folder_id {
+            return Ok(());
+        }
+        let position = self.next_folder_position(to_folder_id);
+        if let Some(old_parent) = old_parent {
+            self.try_get_folder_by_id(&old_parent)?
+                .try_borrow_mut()
+                .with_context(|| format!("Failed to borrow folder (UUID `{}`).", *old_parent))?
+                .folders
+                .remove(folder_id);
+        }
+        if let Some(to_folder_id) = to_folder_id {
+            self.try_get_folder_by_id(to_folder_id)?
+                .try_borrow_mut()
+                .with_context(|| format!("Failed to borrow folder (UUID `{}`).", **to_folder_id))?
+                .folders
+                .insert(*folder_id);
+        }
+        let mut folder = folder
+            .try_borrow_mut()
+            .with_context(|| format!("Failed to borrow folder (UUID `{}`).", **folder_id))?;
+        folder.parent = to_folder_id.copied();
+        folder.position = position;
+        self.revision.bump();
         Ok(())
     }
 
     /// Addes an empty folder into a feed.
     #[allow(unused)]
-    pub fn add_empty_folder(&mut self, folder: Folder) -> FolderUuid {
+    pub fn add_empty_folder(&mut self, mut folder: Folder) -> FolderUuid {
         let uuid = folder.uuid;
+        folder.position = self.next_folder_position(None);
         let folder = Rc::new(RefCell::new(folder));
         self.folders_map.insert(uuid, folder);
         uuid
@@ -395,8 +1554,9 @@ impl Feed {
 
     /// Addes an orphan entry which doesn't belong to any folder.
     #[allow(unused)]
-    pub fn add_orphan_entry(&mut self, entry: Entry) -> EntryUuid {
+    pub fn add_orphan_entry(&mut self, mut entry: Entry) -> EntryUuid {
         let uuid = entry.uuid;
+        entry.position = self.next_entry_position(None);
         let entry = Rc::new(RefCell::new(entry));
         self.entries_map.insert(uuid, entry);
         self.orphans.insert(uuid);
@@ -407,10 +1567,11 @@ impl Feed {
     #[allow(unused)]
     pub fn try_add_entry_to_folder(
         &mut self,
-        entry: Entry,
+        mut entry: Entry,
         to_folder_uuid: &FolderUuid,
     ) -> Result<EntryUuid> {
         let uuid = entry.uuid;
+        entry.position = self.next_entry_position(Some(to_folder_uuid));
         let entry = Rc::new(RefCell::new(entry.set_belonging(to_folder_uuid)));
         self.entries_map.insert(uuid, entry);
         self.try_add_entry_id_to_folder_set(&uuid, to_folder_uuid);
@@ -447,14 +1608,16 @@ impl Feed {
 
     /// Attempts to move an entry to another folder or make an entry orphan.
     /// > Note that when `to_folder_id` is `None`, it will attempt to make the
-    /// entry belong to **no** folder.
-    #[allow(unused)]
+    /// > entry belong to **no** folder.
     pub fn try_move_entry_to_folder(
         &mut self,
         entry_id: &EntryUuid,
         to_folder_id: Option<&FolderUuid>,
     ) -> Result<Rc<RefCell<Entry>>> {
         let unborrowed = self.try_get_entry_by_id(entry_id)?;
+        // Moved to the end of the folder, unless it's already there.
+        let position = (unborrowed.borrow().belong_to.as_ref() != to_folder_id)
+            .then(|| self.next_entry_position(to_folder_id));
         let binding = unborrowed.clone();
         let mut entry = binding
             .try_borrow_mut()
@@ -481,7 +1644,11 @@ impl Feed {
             }
             _ => (),
         }
-        entry.belong_to = to_folder_id.map(FolderUuid::clone);
+        entry.belong_to = to_folder_id.copied();
+        if let Some(position) = position {
+            entry.position = position;
+        }
+        self.revision.bump();
         Ok(unborrowed)
     }
 
@@ -524,10 +1691,48 @@ impl Feed {
             .map(|entry| *entry.borrow().is_synchronizing.lock().unwrap())
     }
 
+    /// Returns the synchronization record of an entry.
+    /// If there is not such entry, returns `None`.
+    #[allow(unused)]
+    pub fn get_entry_sync_record(&self, id: &EntryUuid) -> Option<SyncRecord> {
+        self.try_get_entry_by_id(id)
+            .ok()
+            .map(|entry| entry.borrow().sync_record.lock().unwrap().clone())
+    }
+
     /// Attempts to sync articles of a entry by giveing its ID.
     /// If makes it to sync, return `Ok(true)`,
     /// else if it's synchronizing now, it returns `Ok(false)`.
     pub fn try_sync_entry_by_id(&mut self, id: &EntryUuid) -> Result<bool> {
+        let Some((request, on_done)) = self.try_begin_sync(id)? else {
+            return Ok(false);
+        };
+        fetch_following_redirects(self.fetcher.clone(), request, on_done);
+        Ok(true)
+    }
+
+    /// Attempts to sync articles of a entry from a response fetched already,
+    /// such as the one of discovering the feed, instead of fetching it again.
+    /// Returns `Ok(false)` if it's synchronizing now.
+    pub fn try_sync_entry_with_response(
+        &mut self,
+        id: &EntryUuid,
+        response: ehttp::Response,
+    ) -> Result<bool> {
+        let Some((_, on_done)) = self.try_begin_sync(id)? else {
+            return Ok(false);
+        };
+        on_done(ehttp::Result::Ok(Followed {
+            response,
+            moved_to: None,
+        }));
+        Ok(true)
+    }
+
+    /// Marks an entry synchronizing, and returns the request to fetch it
+    /// with the callback which applies the response,
+    /// or `None` if it's synchronizing already.
+    fn try_begin_sync(&self, id: &EntryUuid) -> Result<Option<(ehttp::Request, OnFollowed)>> {
         let binding = self.try_get_entry_by_id(id)?;
         let entry = binding.try_borrow()?;
         let title = entry.title.clone();
@@ -535,7 +1740,7 @@ impl Feed {
         {
             let mut is_synchronizing = sync_lock.lock().unwrap();
             if *is_synchronizing {
-                return Ok(false);
+                return Ok(None);
             }
             *is_synchronizing = true;
         }
@@ -543,41 +1748,195 @@ impl Feed {
         let article_map = self.articles_map.to_owned();
         let url = entry.xml_url.to_string();
         let entry_uuid = entry.uuid;
-        ehttp::fetch(ehttp::Request::get(url.as_str()), move |result| {
-            if let ehttp::Result::Ok(response) = result {
-                if let feed_rs::parser::ParseFeedResult::Ok(feed) = feed_rs::parser::parse_with_uri(
-                    std::io::Cursor::new(response.bytes),
-                    Some(url.as_str()),
+        let validators = entry.validators.clone();
+        let sync_record = entry.sync_record.clone();
+        let refresh_hints = entry.refresh_hints.clone();
+        let dead = entry.dead.clone();
+        let moved_to = entry.moved_to.clone();
+        let pruned = entry.pruned.clone();
+        let body_source = self.body_source.clone();
+        let search_index = self.search_index.clone();
+        let revision = self.revision.clone();
+        let content_revision = self.content_revision.clone();
+        let changed_articles = self.changed_articles.clone();
+        let policy = self.retention;
+        let retention = self.get_entry_retention(&entry);
+        sync_record.lock().unwrap().begin();
+        let mut request = ehttp::Request::get(url.as_str());
+        validators.lock().unwrap().apply_to(&mut request);
+        let on_done: OnFollowed = Box::new(move |result| {
+            let mut sync_record = sync_record.lock().unwrap();
+            // Moves are applied only once the new location serves the feed,
+            // so that a redirect to a broken one doesn't lose the entry.
+            let mut location = None;
+            let result = result.map(|followed| {
+                location = followed
+                    .moved_to
+                    .and_then(|location| Url::parse(&location).ok());
+                followed.response
+            });
+            let apply_move = |location: Option<Url>| {
+                if let Some(location) = location {
+                    *moved_to.lock().unwrap() = Some(location);
+                }
+            };
+            match result {
+                ehttp::Result::Ok(response) if response.status == 304 => {
+                    // Not modified since the last synchronization, nothing to parse.
+                    apply_move(location);
+                    *dead.lock().unwrap() = false;
+                    sync_record.succeed(response.status, 0);
+                }
+                ehttp::Result::Ok(response) if response.status == 410 => {
+                    *dead.lock().unwrap() = true;
+                    sync_record.fail(
+                        Some(response.status),
+                        SyncErrorKind::Http,
+                        "410 Gone: the feed has been removed for good",
+                    );
+                }
+                ehttp::Result::Ok(response) if !response.ok => {
+                    let mut message = format!("{} {}", response.status, response.status_text);
+                    if let Some(page) = describe_html_page(&response) {
+                        message = format!("{}: {page}", message.trim_end());
+                    }
+                    sync_record.fail(Some(response.status), SyncErrorKind::Http, message);
+                }
+                ehttp::Result::Ok(response) => match feed_rs::parser::parse_with_uri(
+                    std::io::Cursor::new(&response.bytes),
+                    Some(response.url.as_str()),
                 ) {
-                    *sync_lock.lock().unwrap() = false;
-                    *title.lock().unwrap() = feed
-                        .title
-                        .map(|text| text.content)
-                        .unwrap_or("No title".to_owned());
-                    feed.entries.iter().for_each(|item| {
-                        let article_id =
-                            ArticleUuid::new(item.updated, item.published, &entry_uuid, &item.id);
+                    feed_rs::parser::ParseFeedResult::Ok(feed) => {
+                        apply_move(location);
+                        *dead.lock().unwrap() = false;
+                        *validators.lock().unwrap() = Validators::from_response(&response);
+                        *refresh_hints.lock().unwrap() =
+                            RefreshHints::from_feed(&feed, &response.bytes);
+                        *title.lock().unwrap() = feed
+                            .title
+                            .map(|text| text.content)
+                            .unwrap_or("No title".to_owned());
+                        let items: Vec<_> =
+                            ArticleUuid::from_entries(&entry_uuid, &response.bytes, &feed.entries)
+                                .into_iter()
+                                .zip(feed.entries)
+                                .collect();
+                        let mut pruned = pruned.lock().unwrap();
+                        // Pruned articles no longer in the feed won't come back.
+                        pruned.retain(|guid| items.iter().any(|(id, _)| id.guid() == guid));
+                        let mut new_articles = 0;
                         let mut article_id_set = article_id_set
                             .lock()
                             .expect("Failed to get the lock on article id set.");
-                        if !article_id_set.contains(&article_id) {
-                            article_id_set.insert(article_id.clone());
-                            article_map
-                                .lock()
-                                .expect("Failed to get the lock on article map")
-                                .insert(
-                                    article_id,
-                                    Arc::new(Mutex::new(
-                                        Article::from(item.to_owned()).set_belonging(&entry_uuid),
-                                    )),
-                                );
+                        let mut article_map = article_map
+                            .lock()
+                            .expect("Failed to get the lock on article map");
+                        let mut search_index = search_index.lock().unwrap();
+                        let mut changed = changed_articles.lock().unwrap();
+                        items.into_iter().for_each(|(article_id, item)| {
+                            let article = Article::from(item).set_belonging(&entry_uuid);
+                            match article_map.get(&article_id) {
+                                // Seen before, it may have been edited by the publisher.
+                                Some(existing) if article_id_set.contains(&article_id) => {
+                                    let mut existing = existing.lock().unwrap();
+                                    // The body is needed to tell whether it's revised.
+                                    if let (false, Some(body_source)) =
+                                        (existing.is_body_loaded(), &body_source)
+                                    {
+                                        match body_source.try_load_body(&article_id) {
+                                            std::result::Result::Ok(body) => {
+                                                existing.set_body(body.unwrap_or_default())
+                                            }
+                                            // Updated by a later synchronization instead.
+                                            Err(err) => {
+                                                log::error!("{err:#}");
+                                                return;
+                                            }
+                                        }
+                                    }
+                                    existing.update(article);
+                                    search_index.insert(&article_id, &existing);
+                                    changed.ids.insert(article_id);
+                                }
+                                _ if pruned.contains(article_id.guid()) => {}
+                                _ => {
+                                    article_id_set.insert(article_id.clone());
+                                    search_index.insert(&article_id, &article);
+                                    changed.ids.insert(article_id.clone());
+                                    article_map.insert(article_id, Arc::new(Mutex::new(article)));
+                                    new_articles += 1;
+                                }
+                            }
+                        });
+                        sync_record.succeed(response.status, new_articles);
+                        let pruned_ids = prune_articles(
+                            &policy,
+                            &retention,
+                            &mut article_id_set,
+                            &mut article_map,
+                            &mut pruned,
+                        );
+                        for id in &pruned_ids {
+                            search_index.remove(id);
                         }
-                    });
+                        changed.ids.extend(pruned_ids.iter().cloned());
+                        sync_record.pruned_articles = pruned_ids.len();
+                        revision.bump();
+                        content_revision.bump();
+                    }
+                    feed_rs::parser::ParseFeedResult::Err(err) => {
+                        let message =
+                            describe_html_page(&response).unwrap_or_else(|| err.to_string());
+                        sync_record.fail(Some(response.status), SyncErrorKind::Parse, message);
+                    }
+                },
+                ehttp::Result::Err(err) => {
+                    sync_record.fail(None, SyncErrorKind::Network, err);
                 }
             }
             *sync_lock.lock().unwrap() = false;
         });
-        Ok(true)
+        Ok(Some((request, on_done)))
+    }
+
+    /// Returns the retention rules in effect for an entry, those of its own,
+    /// then its folder's and those of the folders which the folder is inside,
+    /// then the global ones.
+    fn get_entry_retention(&self, entry: &Entry) -> Retention {
+        let folder_path = entry
+            .belong_to
+            .and_then(|folder_id| self.try_get_folder_path(&folder_id).ok())
+            .unwrap_or_default();
+        folder_path
+            .iter()
+            .rev()
+            .filter_map(|folder_id| self.try_get_folder_by_id(folder_id).ok())
+            .fold(entry.retention, |retention, folder| {
+                retention.or(folder.borrow().retention)
+            })
+            .or(self.retention.retention)
+    }
+
+    /// Returns the global retention policy.
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        self.retention
+    }
+
+    /// Sets the global retention policy.
+    pub fn set_retention_policy(&mut self, policy: RetentionPolicy) {
+        self.retention = policy;
+    }
+
+    /// Applies the permanent moves found by synchronizations to the URLs of the entries,
+    /// returns the IDs of the moved entries with their former URLs.
+    pub fn apply_moves(&mut self) -> Vec<(EntryUuid, Url)> {
+        self.get_all_entries()
+            .filter_map(|entry| {
+                let mut entry = entry.borrow_mut();
+                let moved_to = entry.moved_to.lock().unwrap().take()?;
+                Some((entry.uuid, std::mem::replace(&mut entry.xml_url, moved_to)))
+            })
+            .collect()
     }
 
     /// Attempts to sync articles of all entries.
@@ -593,12 +1952,29 @@ impl Feed {
     /// Returns the IDs of all articles.
     #[allow(unused)]
     pub fn get_all_article_ids(&self) -> Vec<ArticleUuid> {
-        self.articles_map
+        let mut article_ids: Vec<ArticleUuid> = self
+            .articles_map
             .lock()
             .expect("Failed to get the lock on article map")
             .keys()
             .map(ArticleUuid::clone)
-            .collect()
+            .collect();
+        self.sort_article_ids(&mut article_ids);
+        article_ids
+    }
+
+    /// Sorts the IDs of articles from the latest by their dates.
+    fn sort_article_ids(&self, article_ids: &mut [ArticleUuid]) {
+        let article_map = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map");
+        article_ids.sort_by_cached_key(|id| {
+            let date = article_map
+                .get(id)
+                .map(|article| article.lock().unwrap().sort_date());
+            (std::cmp::Reverse(date), id.clone())
+        });
     }
 
     /// Attempts to return the article by giveing its ID.
@@ -613,6 +1989,422 @@ impl Feed {
             .ok_or(anyhow!("Invalid article id"))?)
     }
 
+    /// Returns the index of articles which are copies of each other across entries,
+    /// which is built again only once articles are added, updated or removed.
+    pub fn build_duplicate_index(&self) -> Rc<DuplicateIndex> {
+        let content_revision = self.content_revision.get();
+        if let Some((revision, index)) = self.duplicate_index.borrow().as_ref() {
+            if *revision == content_revision {
+                return Rc::clone(index);
+            }
+        }
+        let article_map = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map");
+        let articles: Vec<_> = article_map
+            .iter()
+            .map(|(id, article)| (id, article.lock().unwrap()))
+            .collect();
+        let index = Rc::new(DuplicateIndex::build(
+            articles.iter().map(|(id, article)| (*id, &**article)),
+        ));
+        drop(articles);
+        drop(article_map);
+        *self.duplicate_index.borrow_mut() = Some((content_revision, Rc::clone(&index)));
+        index
+    }
+
+    /// Collapses the copies of the same article from different entries,
+    /// keeping only the first one of them.
+    pub fn collapse_duplicate_article_ids(
+        &self,
+        article_ids: Vec<ArticleUuid>,
+    ) -> Vec<ArticleUuid> {
+        self.build_duplicate_index().collapse(article_ids)
+    }
+
+    /// Attempts to mark the article read, along with its copies in other entries.
+    pub fn try_mark_article_read(&self, id: &ArticleUuid) -> Result<()> {
+        self.try_get_article_by_id(id)?;
+        for copy in self.build_duplicate_index().copies_of(id) {
+            self.try_get_article_by_id(&copy)?
+                .lock()
+                .unwrap()
+                .set_read();
+            self.mark_articles_changed([copy]);
+        }
+        self.revision.bump();
+        Ok(())
+    }
+
+    /// Attempts to mark the article unread again, along with its copies in other entries.
+    pub fn try_mark_article_unread(&self, id: &ArticleUuid) -> Result<()> {
+        self.try_get_article_by_id(id)?;
+        for copy in self.build_duplicate_index().copies_of(id) {
+            self.try_get_article_by_id(&copy)?
+                .lock()
+                .unwrap()
+                .set_unread();
+            self.mark_articles_changed([copy]);
+        }
+        self.revision.bump();
+        Ok(())
+    }
+
+    /// Returns the IDs of the articles of the entries, which are unsorted.
+    fn get_article_ids_of_entries(&self, entry_ids: &[EntryUuid]) -> Vec<ArticleUuid> {
+        entry_ids
+            .iter()
+            .filter_map(|id| self.entries_map.get(id))
+            .flat_map(|entry| {
+                entry
+                    .borrow()
+                    .articles
+                    .lock()
+                    .expect("Failed to get the lock on article id set.")
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Counts the unread articles of the entries,
+    /// which are counted again only once the articles change.
+    fn count_unread_of_entries(&self, entry_ids: &[EntryUuid]) -> usize {
+        let sum = |counts: &HashMap<EntryUuid, usize>| {
+            entry_ids.iter().filter_map(|id| counts.get(id)).sum()
+        };
+        let revision = self.revision();
+        if let Some((counted_at, counts)) = self.unread_counts.borrow().as_ref() {
+            if *counted_at == revision {
+                return sum(counts);
+            }
+        }
+        let article_map = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map");
+        let counts: HashMap<_, _> = self
+            .entries_map
+            .iter()
+            .map(|(id, entry)| {
+                let count = entry
+                    .borrow()
+                    .articles
+                    .lock()
+                    .expect("Failed to get the lock on article id set.")
+                    .iter()
+                    .filter(|id| {
+                        article_map
+                            .get(id)
+                            .is_some_and(|article| article.lock().unwrap().unread)
+                    })
+                    .count();
+                (*id, count)
+            })
+            .collect();
+        let unread = sum(&counts);
+        *self.unread_counts.borrow_mut() = Some((revision, counts));
+        unread
+    }
+
+    /// Attempts to count the unread articles of the entry.
+    pub fn try_count_unread_by_entry_id(&self, id: &EntryUuid) -> Result<usize> {
+        self.try_get_entry_by_id(id)?;
+        Ok(self.count_unread_of_entries(&[*id]))
+    }
+
+    /// Attempts to count the unread articles of the entries in the folder,
+    /// and in the folders inside it.
+    pub fn try_count_unread_by_folder_id(&self, id: &FolderUuid) -> Result<usize> {
+        Ok(self.count_unread_of_entries(&self.try_get_entry_ids_in_folder_tree(id)?))
+    }
+
+    /// Counts the unread articles of all entries.
+    pub fn count_unread(&self) -> usize {
+        self.count_unread_of_entries(&self.get_all_entry_ids())
+    }
+
+    /// Marks the unread articles of the entries read, along with their copies in other entries.
+    /// Only those dated before `before` are marked if it's given, where undated articles are
+    /// dated when they are first seen. Returns the number of articles marked.
+    fn mark_entries_read(&self, entry_ids: &[EntryUuid], before: Option<DateTime<Utc>>) -> usize {
+        let article_ids = self.get_article_ids_of_entries(entry_ids);
+        let marked: Vec<ArticleUuid> = {
+            let article_map = self
+                .articles_map
+                .lock()
+                .expect("Failed to get the lock on article map");
+            article_ids
+                .into_iter()
+                .filter(|id| {
+                    article_map.get(id).is_some_and(|article| {
+                        let article = article.lock().unwrap();
+                        article.unread && before.map_or(true, |before| article.sort_date() < before)
+                    })
+                })
+                .collect()
+        };
+        let copies = self.build_duplicate_index().copies_of_all(&marked);
+        let article_map = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map");
+        for id in &copies {
+            if let Some(article) = article_map.get(id) {
+                article.lock().unwrap().set_read();
+            }
+        }
+        self.mark_articles_changed(copies);
+        if !marked.is_empty() {
+            self.revision.bump();
+        }
+        marked.len()
+    }
+
+    /// Attempts to mark the articles of the entry read, like [`Feed::mark_all_read`].
+    pub fn try_mark_entry_read(
+        &self,
+        id: &EntryUuid,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<usize> {
+        self.try_get_entry_by_id(id)?;
+        Ok(self.mark_entries_read(&[*id], before))
+    }
+
+    /// Attempts to mark the articles of the entries in the folder,
+    /// and in the folders inside it, read, like [`Feed::mark_all_read`].
+    pub fn try_mark_folder_read(
+        &self,
+        id: &FolderUuid,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<usize> {
+        Ok(self.mark_entries_read(&self.try_get_entry_ids_in_folder_tree(id)?, before))
+    }
+
+    /// Marks all unread articles read, or only those dated before `before` if it's given.
+    /// Returns the number of articles marked.
+    pub fn mark_all_read(&self, before: Option<DateTime<Utc>>) -> usize {
+        self.mark_entries_read(&self.get_all_entry_ids(), before)
+    }
+
+    /// Attempts to set or clear the flag on the article.
+    pub fn try_set_article_flag(&self, id: &ArticleUuid, flag: Flag, value: bool) -> Result<()> {
+        self.try_get_article_by_id(id)?
+            .lock()
+            .unwrap()
+            .set_flag(flag, value);
+        self.mark_articles_changed([id.clone()]);
+        self.revision.bump();
+        Ok(())
+    }
+
+    /// Returns the IDs of the articles across all entries with the flag set, from the latest.
+    pub fn get_article_ids_by_flag(&self, flag: Flag) -> Vec<ArticleUuid> {
+        self.get_article_ids_where(|article| article.flag(flag))
+    }
+
+    /// Returns the IDs of the articles of all entries passing the test, from the latest.
+    fn get_article_ids_where(&self, test: impl Fn(&Article) -> bool) -> Vec<ArticleUuid> {
+        let mut article_ids: Vec<ArticleUuid> = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map")
+            .iter()
+            .filter(|(_, article)| test(&article.lock().unwrap()))
+            .map(|(id, _)| id.clone())
+            .collect();
+        self.sort_article_ids(&mut article_ids);
+        article_ids
+    }
+
+    /// Returns the IDs of the unread articles of all entries, from the latest.
+    pub fn get_unread_article_ids(&self) -> Vec<ArticleUuid> {
+        self.get_article_ids_where(|article| article.unread)
+    }
+
+    /// Returns the IDs of the articles of all entries dated since then (or first seen if
+    /// undated), from the latest.
+    pub fn get_article_ids_since(&self, since: DateTime<Utc>) -> Vec<ArticleUuid> {
+        self.get_article_ids_where(|article| article.sort_date() >= since)
+    }
+
+    /// Attempts to tag the article, returns the normalized name of the tag.
+    pub fn try_add_article_tag(&self, id: &ArticleUuid, name: &str) -> Result<String> {
+        let name = self
+            .try_get_article_by_id(id)?
+            .lock()
+            .unwrap()
+            .add_tag(name)
+            .ok_or(anyhow!("Tag name is blank"))?;
+        self.mark_articles_changed([id.clone()]);
+        self.revision.bump();
+        Ok(name)
+    }
+
+    /// Attempts to remove the tag from the article.
+    pub fn try_remove_article_tag(&self, id: &ArticleUuid, name: &str) -> Result<()> {
+        self.try_get_article_by_id(id)?
+            .lock()
+            .unwrap()
+            .remove_tag(name);
+        self.mark_articles_changed([id.clone()]);
+        self.revision.bump();
+        Ok(())
+    }
+
+    /// Returns all tags on articles with the numbers of articles tagged, by their names.
+    /// They are counted again only once the articles change.
+    pub fn get_all_tags(&self) -> Vec<(String, usize)> {
+        let revision = self.revision();
+        if let Some((counted_at, tags)) = self.tag_counts.borrow().as_ref() {
+            if *counted_at == revision {
+                return tags.clone();
+            }
+        }
+        let mut tags = BTreeMap::new();
+        for article in self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map")
+            .values()
+        {
+            for name in &article.lock().unwrap().tags {
+                *tags.entry(name.clone()).or_insert(0) += 1;
+            }
+        }
+        let tags: Vec<_> = tags.into_iter().collect();
+        *self.tag_counts.borrow_mut() = Some((revision, tags.clone()));
+        tags
+    }
+
+    /// Returns the IDs of the articles across all entries with the tag, from the latest.
+    pub fn get_article_ids_by_tag(&self, name: &str) -> Vec<ArticleUuid> {
+        self.get_article_ids_where(|article| article.tags.contains(name))
+    }
+
+    /// Returns the color of the tag, which is picked by its name if the reader gives none.
+    pub fn tag_color(&self, name: &str) -> TagColor {
+        self.tag_colors
+            .get(name)
+            .copied()
+            .unwrap_or_else(|| TagColor::default_for(name))
+    }
+
+    pub fn set_tag_color(&mut self, name: &str, color: TagColor) {
+        self.tag_colors.insert(name.to_owned(), color);
+        self.revision.bump();
+    }
+
+    /// Adds a smart folder after the others, returns its UUID.
+    pub fn add_smart_folder(&mut self, folder: SmartFolder) -> SmartFolderUuid {
+        let uuid = folder.uuid;
+        self.smart_folders.push(folder);
+        self.revision.bump();
+        uuid
+    }
+
+    /// Returns the IDs of all smart folders, in the order they are created.
+    pub fn get_smart_folder_ids(&self) -> Vec<SmartFolderUuid> {
+        self.smart_folders
+            .iter()
+            .map(|folder| folder.uuid)
+            .collect()
+    }
+
+    /// Attempts to return a smart folder by giving its ID.
+    pub fn try_get_smart_folder_by_id(&self, id: &SmartFolderUuid) -> Result<&SmartFolder> {
+        self.smart_folders
+            .iter()
+            .find(|folder| folder.uuid == *id)
+            .with_context(|| format!("Failed to get smart folder by UUID `{}`", **id))
+    }
+
+    /// Attempts to return a smart folder to change by giving its ID.
+    pub fn try_get_smart_folder_mut(&mut self, id: &SmartFolderUuid) -> Result<&mut SmartFolder> {
+        self.revision.bump();
+        self.smart_folders
+            .iter_mut()
+            .find(|folder| folder.uuid == *id)
+            .with_context(|| format!("Failed to get smart folder by UUID `{}`", **id))
+    }
+
+    /// Removes a smart folder, which leaves the articles in it as they are.
+    pub fn remove_smart_folder(&mut self, id: &SmartFolderUuid) {
+        self.smart_folders.retain(|folder| folder.uuid != *id);
+        self.revision.bump();
+    }
+
+    /// Attempts to return the compiled rule of the smart folder, which is compiled again
+    /// once the articles change, or a minute later as rules may depend on the time.
+    fn try_compile_smart_folder_rule(&self, id: &SmartFolderUuid) -> Result<CompiledRule> {
+        let now = Utc::now();
+        let revision = self.revision();
+        if let Some(compiled) = self.compiled_rules.borrow().get(id) {
+            if compiled.revision == revision && now - compiled.compiled_at < Duration::minutes(1) {
+                return Ok(compiled.clone());
+            }
+        }
+        let matcher = self
+            .try_get_smart_folder_by_id(id)?
+            .rule
+            .try_compile(
+                |folder_id| {
+                    self.try_get_entry_ids_in_folder_tree(folder_id)
+                        .unwrap_or_default()
+                },
+                now,
+            )
+            .map(Rc::new)
+            .map_err(|err| format!("{err:#}"));
+        let compiled = CompiledRule {
+            revision,
+            compiled_at: now,
+            matcher,
+            count: None,
+        };
+        self.compiled_rules
+            .borrow_mut()
+            .insert(*id, compiled.clone());
+        Ok(compiled)
+    }
+
+    /// Attempts to return the IDs of the articles matching the rule of the smart folder,
+    /// from the latest. Fails if the rule has an invalid pattern.
+    pub fn try_get_article_ids_by_smart_folder_id(
+        &self,
+        id: &SmartFolderUuid,
+    ) -> Result<Vec<ArticleUuid>> {
+        let matcher = self
+            .try_compile_smart_folder_rule(id)?
+            .matcher
+            .map_err(Error::msg)?;
+        Ok(self.get_article_ids_where(|article| matcher.matches(article)))
+    }
+
+    /// Attempts to count the articles matching the rule of the smart folder, which is counted
+    /// again only when the rule is compiled again. Fails if the rule has an invalid pattern.
+    pub fn try_count_articles_by_smart_folder_id(&self, id: &SmartFolderUuid) -> Result<usize> {
+        let compiled = self.try_compile_smart_folder_rule(id)?;
+        if let Some(count) = compiled.count {
+            return Ok(count);
+        }
+        let matcher = compiled.matcher.map_err(Error::msg)?;
+        let count = self
+            .articles_map
+            .lock()
+            .expect("Failed to get the lock on article map")
+            .values()
+            .filter(|article| matcher.matches(&article.lock().unwrap()))
+            .count();
+        if let Some(compiled) = self.compiled_rules.borrow_mut().get_mut(id) {
+            compiled.count = Some(count);
+        }
+        Ok(count)
+    }
+
     /// Attempts to return the IDs of all articles in a feed entry by giving entry ID.
     #[allow(unused)]
     pub fn try_get_all_article_ids_by_entry_id(
@@ -620,7 +2412,7 @@ impl Feed {
         entry_id: &EntryUuid,
     ) -> Result<Vec<ArticleUuid>> {
         let entry = self.try_get_entry_by_id(entry_id)?;
-        let article_ids = entry
+        let mut article_ids: Vec<ArticleUuid> = entry
             .borrow()
             .articles
             .lock()
@@ -628,30 +2420,43 @@ impl Feed {
             .iter()
             .map(ArticleUuid::clone)
             .collect();
+        self.sort_article_ids(&mut article_ids);
         Ok(article_ids)
     }
 
-    /// Attempts to return the IDs of all articles in a feed folder by giving folder ID.
-    #[allow(unused)]
+    /// Attempts to return the IDs of all articles in a feed folder and in all folders inside it
+    /// by giving folder ID, from the latest.
     pub fn get_all_article_ids_by_folder_id(
         &self,
         folder_id: &FolderUuid,
     ) -> Result<Vec<ArticleUuid>> {
-        let entry_ids = self.try_get_entry_ids_by_folder_id(folder_id)?;
-        let article_ids = entry_ids
-            .iter()
-            .flat_map(|entry_id| self.try_get_all_article_ids_by_entry_id(entry_id).unwrap())
-            .collect();
+        let entry_ids = self.try_get_entry_ids_in_folder_tree(folder_id)?;
+        let mut article_ids = self.get_article_ids_of_entries(&entry_ids);
+        self.sort_article_ids(&mut article_ids);
         Ok(article_ids)
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::subscription::article::Flag;
     use crate::subscription::feed::Entry;
+    use crate::subscription::feed::EntryUuid;
     use crate::subscription::feed::Feed;
+    use crate::subscription::feed::Folder;
+    use crate::subscription::feed::SmartFolder;
+    use crate::subscription::feed::SyncErrorKind;
+    use crate::subscription::feed::Validators;
+    use crate::subscription::fetch::{fixture_fetcher, Fixture, FixtureFetcher};
+    use crate::subscription::fetch::{ATOM_URL, JSON_URL, RSS_URL};
     use crate::subscription::opml::Opml;
+    use crate::subscription::order::SortMode;
+    use crate::subscription::retention::{Retention, RetentionPolicy};
+    use crate::subscription::rule::{Condition, Rule};
+    use crate::subscription::search::{Query, SearchFilter, SearchIndex};
+    use chrono::{TimeZone, Utc};
     use std::fs::read_to_string;
+    use std::time::Duration;
     use url::Url;
 
     #[test]
@@ -780,14 +2585,667 @@ mod test {
     }
 
     #[test]
-    fn feed_update() {
-        let opml = Opml::try_from_str(&read_to_string("./OPMLs/complex.opml").unwrap()).unwrap();
+    fn ordering() {
+        let opml = Opml::try_from_str(&read_to_string("./OPMLs/nested.opml").unwrap()).unwrap();
         let mut feed = Feed::try_from(opml).unwrap();
+        let names = |feed: &Feed, entry_ids: Vec<EntryUuid>| {
+            entry_ids
+                .iter()
+                .map(|id| feed.try_get_entry_by_id(id).unwrap().borrow().title())
+                .collect::<Vec<_>>()
+        };
+        let blogs = *feed.get_folder_ids_by_name("Blogs").first().unwrap();
+        let inside_rust = *feed.get_entry_ids_by_name("Inside Rust").first().unwrap();
+
+        // In the order of OPML, which is kept when exported.
+        let blog_ids = feed.try_get_entry_ids_by_folder_id(&blogs).unwrap();
+        assert_eq!(names(&feed, blog_ids.clone()), ["Rust Blog", "Inside Rust"]);
+        assert_eq!(
+            names(&feed, feed.sort_entry_ids(blog_ids, SortMode::Name)),
+            ["Inside Rust", "Rust Blog"]
+        );
+        let exported = Feed::try_from(Opml::from(feed.clone())).unwrap();
+        let exported_blogs = *exported.get_folder_ids_by_name("Blogs").first().unwrap();
+        assert_eq!(
+            names(
+                &exported,
+                exported
+                    .try_get_entry_ids_by_folder_id(&exported_blogs)
+                    .unwrap()
+            ),
+            ["Rust Blog", "Inside Rust"]
+        );
+
+        feed.try_shift_entry(&inside_rust, -1).unwrap();
+        assert_eq!(
+            names(&feed, feed.try_get_entry_ids_by_folder_id(&blogs).unwrap()),
+            ["Inside Rust", "Rust Blog"]
+        );
+
+        // Added and moved entries go last.
+        let hacker_news = *feed.get_entry_ids_by_name("Hacker News").first().unwrap();
+        feed.try_move_entry_to_folder(&hacker_news, Some(&blogs))
+            .unwrap();
+        assert_eq!(
+            names(&feed, feed.try_get_entry_ids_by_folder_id(&blogs).unwrap()),
+            ["Inside Rust", "Rust Blog", "Hacker News"]
+        );
+        feed.add_orphan_entry(Entry::new_with_alias(
+            "Alpha",
+            Url::parse("https://alpha.example/feed").unwrap(),
+        ));
+        assert_eq!(
+            names(&feed, feed.get_all_orphan_entry_ids()),
+            ["少数派", "Alpha"]
+        );
+
+        feed.sort_once(SortMode::Name);
+        assert_eq!(
+            names(&feed, feed.get_all_orphan_entry_ids()),
+            ["Alpha", "少数派"]
+        );
+        assert_eq!(
+            names(&feed, feed.try_get_entry_ids_by_folder_id(&blogs).unwrap()),
+            ["Hacker News", "Inside Rust", "Rust Blog"]
+        );
+        assert_eq!(feed.sort_mode(), SortMode::Manual);
+    }
+
+    #[test]
+    fn nested_folders() {
+        let opml = Opml::try_from_str(&read_to_string("./OPMLs/nested.opml").unwrap()).unwrap();
+        let mut feed = Feed::try_from(opml).unwrap();
+        let tech = *feed.get_folder_ids_by_name("Tech").first().unwrap();
+        let rust = *feed.get_folder_ids_by_name("Rust").first().unwrap();
+        let blogs = *feed.get_folder_ids_by_name("Blogs").first().unwrap();
+        assert_eq!(
+            feed.try_get_folder_ids_in_tree(&tech).unwrap(),
+            vec![tech, rust, blogs]
+        );
+        assert_eq!(
+            feed.try_get_folder_path(&blogs).unwrap(),
+            vec![tech, rust, blogs]
+        );
+        assert_eq!(
+            feed.try_get_entry_ids_in_folder_tree(&tech).unwrap().len(),
+            4
+        );
+        assert_eq!(
+            feed.try_get_entry_ids_in_folder_tree(&rust).unwrap().len(),
+            3
+        );
+
+        // A folder can't be moved into itself.
+        assert!(feed.try_move_folder_to_folder(&tech, Some(&blogs)).is_err());
+        feed.try_move_folder_to_folder(&blogs, None).unwrap();
+        assert_eq!(feed.get_top_level_folder_ids().len(), 2);
+        assert_eq!(
+            feed.try_get_entry_ids_in_folder_tree(&tech).unwrap().len(),
+            2
+        );
+        feed.try_move_folder_to_folder(&blogs, Some(&tech)).unwrap();
+        assert_eq!(feed.try_get_folder_path(&blogs).unwrap(), vec![tech, blogs]);
+
+        // Removed with the folders and entries inside.
+        feed.try_remove_folder_by_id(&tech).unwrap();
+        assert!(feed.get_all_folder_ids().is_empty());
+        assert_eq!(feed.get_all_entry_ids().len(), 1);
+    }
+
+    #[test]
+    fn conditional_request_headers() {
+        let mut request = ehttp::Request::get("https://sspai.com/feed");
+        Validators::default().apply_to(&mut request);
+        assert!(!request.headers.contains_key("If-None-Match"));
+        assert!(!request.headers.contains_key("If-Modified-Since"));
+
+        let response = ehttp::Response {
+            url: "https://sspai.com/feed".to_owned(),
+            ok: true,
+            status: 200,
+            status_text: "OK".to_owned(),
+            headers: ehttp::headers(&[
+                ("etag", "\"5f1b\""),
+                ("last-modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+            ]),
+            bytes: vec![],
+        };
+        let validators = Validators::from_response(&response);
+        validators.apply_to(&mut request);
+        assert_eq!(request.headers.get("If-None-Match").unwrap(), "\"5f1b\"");
+        assert_eq!(
+            request.headers.get("If-Modified-Since").unwrap(),
+            "Wed, 21 Oct 2015 07:28:00 GMT"
+        );
+    }
+
+    fn fixture_feed(fetcher: &FixtureFetcher, url: &str) -> (Feed, EntryUuid) {
+        let mut feed = Feed::default();
+        feed.set_fetcher(fetcher.clone());
+        let id = feed.add_orphan_entry(Entry::new(Url::parse(url).unwrap()));
+        (feed, id)
+    }
+
+    /// Returns a feed with the fixture at the URL synchronized, and the fetcher serving it.
+    fn synced_feed(url: &str) -> (FixtureFetcher, Feed, EntryUuid) {
+        let fetcher = fixture_fetcher();
+        let (mut feed, id) = fixture_feed(&fetcher, url);
+        feed.try_sync_entry_by_id(&id).unwrap();
+        (fetcher, feed, id)
+    }
+
+    #[test]
+    fn feed_update() {
+        let fetcher = fixture_fetcher();
+        let mut feed = Feed::default();
+        feed.set_fetcher(fetcher.clone());
+        let ids = [
+            (RSS_URL, 3, "RSS Fixture"),
+            (ATOM_URL, 2, "Atom Fixture"),
+            (JSON_URL, 2, "JSON Feed Fixture"),
+        ]
+        .map(|(url, count, title)| {
+            let id = feed.add_orphan_entry(Entry::new(Url::parse(url).unwrap()));
+            (id, count, title)
+        });
+        feed.try_sync_all().unwrap();
+        for (id, count, title) in &ids {
+            assert_eq!(feed.is_entry_synchronizing(id), Some(false));
+            assert_eq!(
+                feed.try_get_all_article_ids_by_entry_id(id).unwrap().len(),
+                *count
+            );
+            assert_eq!(
+                feed.try_get_entry_by_id(id).unwrap().borrow().title(),
+                *title
+            );
+            let record = feed.get_entry_sync_record(id).unwrap();
+            assert!(record.error.is_none());
+            assert_eq!(record.http_status, Some(200));
+            assert_eq!(record.new_articles, *count);
+        }
+        assert_eq!(feed.get_all_article_ids().len(), 7);
+
+        // Synchronizing again brings nothing new.
         feed.try_sync_all().unwrap();
-        for i in 0..20 {
-            std::thread::sleep(std::time::Duration::from_secs(1));
-            let fetched_article = feed.articles_map.lock().unwrap().len();
-            println!("Fetched {fetched_article} articles in {i} secs.");
+        assert_eq!(feed.get_all_article_ids().len(), 7);
+        for (id, _, _) in &ids {
+            assert_eq!(feed.get_entry_sync_record(id).unwrap().new_articles, 0);
         }
     }
+
+    #[test]
+    fn feed_not_modified() {
+        let (fetcher, mut feed, id) = synced_feed(RSS_URL);
+        assert_eq!(feed.get_all_article_ids().len(), 3);
+
+        fetcher.serve(RSS_URL, Fixture::status(304));
+        feed.try_sync_entry_by_id(&id).unwrap();
+        let request = fetcher.requests().pop().unwrap();
+        assert_eq!(request.headers.get("If-None-Match").unwrap(), "\"rss-v1\"");
+        let record = feed.get_entry_sync_record(&id).unwrap();
+        assert!(record.error.is_none());
+        assert_eq!(record.http_status, Some(304));
+        assert_eq!(record.new_articles, 0);
+        assert_eq!(feed.get_all_article_ids().len(), 3);
+    }
+
+    #[test]
+    fn feed_prefetched() {
+        let fetcher = FixtureFetcher::new();
+        let (mut feed, id) = fixture_feed(&fetcher, RSS_URL);
+        let response = ehttp::Response {
+            url: RSS_URL.to_owned(),
+            ok: true,
+            status: 200,
+            status_text: "OK".to_owned(),
+            headers: Default::default(),
+            bytes: std::fs::read("./fixtures/rss.xml").unwrap(),
+        };
+        assert!(feed.try_sync_entry_with_response(&id, response).unwrap());
+        assert_eq!(feed.get_all_article_ids().len(), 3);
+        assert!(fetcher.requests().is_empty());
+    }
+
+    #[test]
+    fn feed_sync_errors() {
+        let fetcher = FixtureFetcher::new();
+        fetcher
+            .serve(RSS_URL, Fixture::status(500))
+            .serve(ATOM_URL, Fixture::error("connection refused"))
+            .serve(
+                JSON_URL,
+                Fixture::ok("<html><body>Not a feed</body></html>"),
+            );
+        for (url, status, kind) in [
+            (RSS_URL, Some(500), SyncErrorKind::Http),
+            (ATOM_URL, None, SyncErrorKind::Network),
+            (JSON_URL, Some(200), SyncErrorKind::Parse),
+        ] {
+            let (mut feed, id) = fixture_feed(&fetcher, url);
+            feed.try_sync_entry_by_id(&id).unwrap();
+            let record = feed.get_entry_sync_record(&id).unwrap();
+            assert_eq!(record.http_status, status);
+            assert_eq!(record.error.unwrap().kind, kind);
+            assert!(record.last_attempt.is_some());
+            assert!(record.last_success.is_none());
+            assert_eq!(feed.is_entry_synchronizing(&id), Some(false));
+        }
+    }
+
+    #[test]
+    fn feed_update_delayed() {
+        let fetcher = FixtureFetcher::new();
+        fetcher.serve(
+            RSS_URL,
+            Fixture::try_from_file("./fixtures/rss.xml")
+                .unwrap()
+                .with_delay(Duration::from_millis(100)),
+        );
+        let (mut feed, id) = fixture_feed(&fetcher, RSS_URL);
+        assert!(feed.try_sync_entry_by_id(&id).unwrap());
+        assert_eq!(feed.is_entry_synchronizing(&id), Some(true));
+        // Already synchronizing.
+        assert!(!feed.try_sync_entry_by_id(&id).unwrap());
+        while feed.is_entry_synchronizing(&id) == Some(true) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(feed.get_all_article_ids().len(), 3);
+    }
+
+    #[test]
+    fn feed_redirects() {
+        let fetcher = fixture_fetcher();
+        fetcher
+            .serve(
+                "https://old.example.com/rss.xml",
+                Fixture::status(301).with_header("Location", "https://new.example.com/rss"),
+            )
+            .serve(
+                "https://new.example.com/rss",
+                Fixture::status(308).with_header("Location", RSS_URL),
+            )
+            .serve(
+                "https://temporary.example.com/atom.xml",
+                Fixture::status(302).with_header("Location", ATOM_URL),
+            )
+            .serve(
+                "https://loop.example.com/rss.xml",
+                Fixture::status(307).with_header("Location", "/rss.xml"),
+            )
+            .serve(
+                "https://broken.example.com/rss.xml",
+                Fixture::status(301).with_header("Location", "https://missing.example.com/rss"),
+            );
+
+        // Moved permanently through a chain, the URL is updated once applied.
+        let (mut feed, id) = fixture_feed(&fetcher, "https://old.example.com/rss.xml");
+        feed.try_sync_entry_by_id(&id).unwrap();
+        assert_eq!(feed.get_all_article_ids().len(), 3);
+        let moves = feed.apply_moves();
+        assert_eq!(
+            moves,
+            vec![(id, Url::parse("https://old.example.com/rss.xml").unwrap())]
+        );
+        assert_eq!(
+            feed.try_get_entry_by_id(&id)
+                .unwrap()
+                .borrow()
+                .xml_url
+                .as_str(),
+            RSS_URL
+        );
+        assert!(feed.apply_moves().is_empty());
+
+        // Moved temporarily, the URL is kept.
+        let (mut feed, id) = fixture_feed(&fetcher, "https://temporary.example.com/atom.xml");
+        feed.try_sync_entry_by_id(&id).unwrap();
+        assert_eq!(feed.get_all_article_ids().len(), 2);
+        assert!(feed.apply_moves().is_empty());
+
+        // Moved permanently to where nothing is found, the URL is kept.
+        let (mut feed, id) = fixture_feed(&fetcher, "https://broken.example.com/rss.xml");
+        feed.try_sync_entry_by_id(&id).unwrap();
+        let record = feed.get_entry_sync_record(&id).unwrap();
+        assert_eq!(record.http_status, Some(404));
+        assert!(feed.apply_moves().is_empty());
+
+        // Redirected again and again.
+        let (mut feed, id) = fixture_feed(&fetcher, "https://loop.example.com/rss.xml");
+        feed.try_sync_entry_by_id(&id).unwrap();
+        let record = feed.get_entry_sync_record(&id).unwrap();
+        assert_eq!(record.error.unwrap().kind, SyncErrorKind::Network);
+        assert!(feed.apply_moves().is_empty());
+    }
+
+    #[test]
+    fn feed_gone() {
+        let fetcher = fixture_fetcher();
+        fetcher.serve(RSS_URL, Fixture::status(410)).serve(
+            ATOM_URL,
+            Fixture::ok("<!DOCTYPE html><html><head><title>Please log in</title></head></html>")
+                .with_header("Content-Type", "text/html; charset=utf-8"),
+        );
+
+        let (mut feed, id) = fixture_feed(&fetcher, RSS_URL);
+        feed.try_sync_entry_by_id(&id).unwrap();
+        let entry = feed.try_get_entry_by_id(&id).unwrap();
+        assert!(entry.borrow().is_dead());
+        let record = feed.get_entry_sync_record(&id).unwrap();
+        assert_eq!(record.http_status, Some(410));
+        assert_eq!(record.error.unwrap().kind, SyncErrorKind::Http);
+
+        // Revived if it comes back.
+        fetcher.serve(
+            RSS_URL,
+            Fixture::try_from_file("./fixtures/rss.xml").unwrap(),
+        );
+        feed.try_sync_entry_by_id(&id).unwrap();
+        assert!(!entry.borrow().is_dead());
+
+        // HTML pages are explained rather than failing to parse.
+        let (mut feed, id) = fixture_feed(&fetcher, ATOM_URL);
+        feed.try_sync_entry_by_id(&id).unwrap();
+        let error = feed.get_entry_sync_record(&id).unwrap().error.unwrap();
+        assert_eq!(error.kind, SyncErrorKind::Parse);
+        assert!(error.message.contains("Please log in"));
+    }
+
+    #[test]
+    fn feed_article_revised() {
+        let (fetcher, mut feed, id) = synced_feed(RSS_URL);
+        let article_ids = feed.get_all_article_ids();
+        assert_eq!(article_ids.len(), 3);
+
+        let source = std::fs::read_to_string("./fixtures/rss.xml").unwrap();
+        fetcher.serve(
+            RSS_URL,
+            Fixture::ok(source.replace("Second post", "Second post, edited")),
+        );
+        feed.try_sync_entry_by_id(&id).unwrap();
+        assert_eq!(feed.get_all_article_ids(), article_ids);
+        assert_eq!(feed.get_entry_sync_record(&id).unwrap().new_articles, 0);
+        let revised: Vec<_> = article_ids
+            .iter()
+            .map(|id| feed.try_get_article_by_id(id).unwrap())
+            .filter(|article| !article.lock().unwrap().revisions.is_empty())
+            .collect();
+        assert_eq!(revised.len(), 1);
+        let article = revised[0].lock().unwrap();
+        assert_eq!(article.title, "Second post, edited");
+        assert_eq!(article.revisions[0].title, "Second post");
+    }
+
+    #[test]
+    fn feed_unread() {
+        let fetcher = fixture_fetcher();
+        let (mut feed, rss_id) = fixture_feed(&fetcher, RSS_URL);
+        let folder_id = feed.add_empty_folder(Folder::new("News"));
+        let atom_id = feed
+            .try_add_entry_to_folder(Entry::new(Url::parse(ATOM_URL).unwrap()), &folder_id)
+            .unwrap();
+        feed.try_sync_all().unwrap();
+        assert_eq!(feed.try_count_unread_by_entry_id(&rss_id).unwrap(), 3);
+        assert_eq!(feed.try_count_unread_by_folder_id(&folder_id).unwrap(), 2);
+        assert_eq!(feed.count_unread(), 5);
+
+        // Only older articles are marked.
+        let before = Utc.with_ymd_and_hms(2023, 11, 7, 12, 0, 0).unwrap();
+        assert_eq!(feed.try_mark_entry_read(&rss_id, Some(before)).unwrap(), 2);
+        assert_eq!(feed.try_count_unread_by_entry_id(&rss_id).unwrap(), 1);
+        assert_eq!(feed.try_mark_folder_read(&folder_id, None).unwrap(), 2);
+        assert_eq!(feed.try_count_unread_by_entry_id(&atom_id).unwrap(), 0);
+        assert_eq!(feed.mark_all_read(None), 1);
+        assert_eq!(feed.count_unread(), 0);
+
+        let article_id = feed.get_all_article_ids()[0].clone();
+        feed.try_mark_article_unread(&article_id).unwrap();
+        assert_eq!(feed.count_unread(), 1);
+    }
+
+    #[test]
+    fn feed_duplicates() {
+        let fetcher = fixture_fetcher();
+        fetcher.serve(
+            "https://mirror.example.com/rss.xml",
+            Fixture::try_from_file("./fixtures/rss.xml").unwrap(),
+        );
+        let (mut feed, id) = fixture_feed(&fetcher, RSS_URL);
+        let mirror_id = feed.add_orphan_entry(Entry::new(
+            Url::parse("https://mirror.example.com/rss.xml").unwrap(),
+        ));
+        feed.try_sync_entry_by_id(&id).unwrap();
+        feed.try_sync_entry_by_id(&mirror_id).unwrap();
+        let article_ids = feed.get_all_article_ids();
+        assert_eq!(article_ids.len(), 6);
+        assert_eq!(
+            feed.collapse_duplicate_article_ids(article_ids.clone())
+                .len(),
+            3
+        );
+        let index = feed.build_duplicate_index();
+        let read = feed.try_get_all_article_ids_by_entry_id(&id).unwrap()[0].clone();
+        feed.try_mark_article_read(&read).unwrap();
+        // Reading articles doesn't change which are copies.
+        assert!(std::rc::Rc::ptr_eq(&index, &feed.build_duplicate_index()));
+        let unread: Vec<_> = article_ids
+            .iter()
+            .filter(|id| {
+                feed.try_get_article_by_id(id)
+                    .unwrap()
+                    .lock()
+                    .unwrap()
+                    .unread
+            })
+            .collect();
+        assert_eq!(unread.len(), 4);
+    }
+
+    #[test]
+    fn feed_pruned() {
+        let fetcher = fixture_fetcher();
+        let (mut feed, id) = fixture_feed(&fetcher, RSS_URL);
+        feed.set_retention_policy(RetentionPolicy {
+            retention: Retention {
+                max_articles: Some(1),
+                max_age_days: None,
+            },
+            keep_unread: false,
+        });
+        feed.try_sync_entry_by_id(&id).unwrap();
+        let record = feed.get_entry_sync_record(&id).unwrap();
+        assert_eq!(record.new_articles, 3);
+        assert_eq!(record.pruned_articles, 2);
+        assert_eq!(feed.get_all_article_ids().len(), 1);
+
+        // Pruned articles are not added again.
+        feed.try_sync_entry_by_id(&id).unwrap();
+        let record = feed.get_entry_sync_record(&id).unwrap();
+        assert_eq!(record.new_articles, 0);
+        assert_eq!(record.pruned_articles, 0);
+        assert_eq!(feed.get_all_article_ids().len(), 1);
+    }
+
+    #[test]
+    fn feed_tags() {
+        let (_, feed, _) = synced_feed(RSS_URL);
+        let article_ids = feed.get_all_article_ids();
+        feed.try_add_article_tag(&article_ids[0], "weekly").unwrap();
+        feed.try_add_article_tag(&article_ids[1], " weekly ")
+            .unwrap();
+        assert_eq!(feed.get_all_tags(), vec![("weekly".to_owned(), 2)]);
+        // Counted again once the tags change.
+        feed.try_remove_article_tag(&article_ids[1], "weekly")
+            .unwrap();
+        assert_eq!(feed.get_all_tags(), vec![("weekly".to_owned(), 1)]);
+        assert_eq!(
+            feed.get_article_ids_by_tag("weekly"),
+            vec![article_ids[0].clone()]
+        );
+    }
+
+    #[test]
+    fn feed_flags() {
+        let (_, mut feed, id) = synced_feed(RSS_URL);
+        let article_ids = feed.get_all_article_ids();
+        feed.try_set_article_flag(&article_ids[2], Flag::Starred, true)
+            .unwrap();
+        feed.try_set_article_flag(&article_ids[0], Flag::Starred, true)
+            .unwrap();
+        feed.try_set_article_flag(&article_ids[1], Flag::ReadLater, true)
+            .unwrap();
+        assert_eq!(
+            feed.get_article_ids_by_flag(Flag::Starred),
+            vec![article_ids[0].clone(), article_ids[2].clone()]
+        );
+        feed.try_set_article_flag(&article_ids[1], Flag::ReadLater, false)
+            .unwrap();
+        assert!(feed.get_article_ids_by_flag(Flag::ReadLater).is_empty());
+
+        // Flagged articles are not pruned.
+        feed.try_set_article_flag(&article_ids[2], Flag::Archived, true)
+            .unwrap();
+        feed.set_retention_policy(RetentionPolicy {
+            retention: Retention {
+                max_articles: Some(1),
+                max_age_days: None,
+            },
+            keep_unread: false,
+        });
+        feed.try_sync_entry_by_id(&id).unwrap();
+        assert_eq!(feed.get_entry_sync_record(&id).unwrap().pruned_articles, 1);
+        assert_eq!(
+            feed.get_all_article_ids(),
+            vec![article_ids[0].clone(), article_ids[2].clone()]
+        );
+        // Neither are starred ones once they are no longer the latest.
+        feed.set_retention_policy(RetentionPolicy {
+            retention: Retention {
+                max_articles: Some(0),
+                max_age_days: None,
+            },
+            keep_unread: false,
+        });
+        feed.try_sync_entry_by_id(&id).unwrap();
+        assert_eq!(feed.get_entry_sync_record(&id).unwrap().pruned_articles, 0);
+        assert_eq!(
+            feed.get_all_article_ids(),
+            vec![article_ids[0].clone(), article_ids[2].clone()]
+        );
+    }
+
+    #[test]
+    fn revision() {
+        let fetcher = fixture_fetcher();
+        let (mut feed, id) = fixture_feed(&fetcher, RSS_URL);
+        assert_ne!(feed.revision(), Feed::default().revision());
+        let revision = feed.revision();
+        feed.try_sync_entry_by_id(&id).unwrap();
+        assert_ne!(feed.revision(), revision);
+
+        // Listing articles doesn't change them, while reading them does.
+        let revision = feed.revision();
+        let article_ids = feed.get_all_article_ids();
+        feed.get_unread_article_ids();
+        assert_eq!(feed.revision(), revision);
+        feed.try_mark_article_read(&article_ids[0]).unwrap();
+        assert_ne!(feed.revision(), revision);
+        let revision = feed.revision();
+        feed.try_set_article_flag(&article_ids[0], Flag::Starred, true)
+            .unwrap();
+        assert_ne!(feed.revision(), revision);
+    }
+
+    #[test]
+    fn smart_folders() {
+        let (_, mut feed, _) = synced_feed(RSS_URL);
+        let smart_id = feed.add_smart_folder(SmartFolder::new(
+            "Unread",
+            Rule {
+                conditions: vec![Condition::Unread],
+                match_any: false,
+            },
+        ));
+        let unread = feed.get_unread_article_ids();
+        assert_eq!(
+            feed.try_get_article_ids_by_smart_folder_id(&smart_id)
+                .unwrap(),
+            unread
+        );
+        assert_eq!(
+            feed.try_count_articles_by_smart_folder_id(&smart_id)
+                .unwrap(),
+            unread.len()
+        );
+        // Counted again once the articles change.
+        feed.try_mark_article_read(&unread[0]).unwrap();
+        assert_eq!(
+            feed.try_count_articles_by_smart_folder_id(&smart_id)
+                .unwrap(),
+            unread.len() - 1
+        );
+        // And once the rule changes.
+        feed.try_get_smart_folder_mut(&smart_id).unwrap().rule = Rule {
+            conditions: vec![Condition::TitleMatches("(".to_owned())],
+            match_any: false,
+        };
+        assert!(feed
+            .try_count_articles_by_smart_folder_id(&smart_id)
+            .is_err());
+    }
+
+    #[test]
+    fn feed_search() {
+        let (fetcher, mut feed, rss_id) = synced_feed(RSS_URL);
+        let search = |feed: &Feed, query: &str, filter: &SearchFilter| {
+            feed.try_search(&Query::parse(query), filter).unwrap()
+        };
+        let all = SearchFilter::default();
+        assert!(feed.try_search(&Query::parse("post"), &all).is_err());
+        feed.try_index_articles(usize::MAX).unwrap();
+        assert_eq!(search(&feed, "webassembly", &all).len(), 1);
+        assert_eq!(search(&feed, "post", &all).len(), 3);
+
+        // Articles of entries synchronized later are indexed as they are added.
+        let atom_id = feed.add_orphan_entry(Entry::new(Url::parse(ATOM_URL).unwrap()));
+        feed.try_sync_entry_by_id(&atom_id).unwrap();
+        assert_eq!(search(&feed, "\"full body\"", &all).len(), 1);
+        assert_eq!(search(&feed, "entr*", &all).len(), 2);
+
+        let atom_only = SearchFilter {
+            entry: Some(atom_id),
+            ..Default::default()
+        };
+        assert!(search(&feed, "post", &atom_only).is_empty());
+        let read = search(&feed, "first", &all)[0].clone();
+        feed.try_mark_article_read(&read).unwrap();
+        let unread_only = SearchFilter {
+            unread_only: true,
+            ..Default::default()
+        };
+        assert_eq!(search(&feed, "post", &unread_only).len(), 2);
+
+        // Articles loaded from the storage are indexed a few at a time.
+        *feed.search_index.lock().unwrap() = SearchIndex::default();
+        let total = feed.articles_map.lock().unwrap().len();
+        assert_eq!(feed.try_index_articles(1).unwrap(), (1, total));
+        assert_eq!(feed.try_index_articles(1).unwrap(), (2, total));
+        assert_eq!(feed.try_index_articles(total).unwrap(), (total, total));
+        assert!(feed.search_index.lock().unwrap().is_built());
+        assert_eq!(search(&feed, "post", &all).len(), 3);
+
+        // Copies of the same article from different entries are found once.
+        let mirror_url = "https://mirror.example.com/rss.xml";
+        fetcher.serve(
+            mirror_url,
+            Fixture::try_from_file("./fixtures/rss.xml").unwrap(),
+        );
+        let mirror_id = feed.add_orphan_entry(Entry::new(Url::parse(mirror_url).unwrap()));
+        feed.try_sync_entry_by_id(&mirror_id).unwrap();
+        assert_eq!(search(&feed, "webassembly", &all).len(), 1);
+        feed.try_remove_entry_by_id(&mirror_id).unwrap();
+
+        feed.try_remove_entry_by_id(&rss_id).unwrap();
+        assert!(search(&feed, "post", &all).is_empty());
+    }
 }
@@ -0,0 +1,356 @@
+//! Fetchers which get feeds for synchronization,
+//! over HTTP or from canned fixtures for deterministic tests.
+#[cfg(test)]
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Deref;
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::Mutex;
+#[cfg(test)]
+use std::time::Duration;
+
+/// Callback which receives the result of a fetch.
+pub type OnDone = Box<dyn FnOnce(ehttp::Result<ehttp::Response>) + Send>;
+
+/// Something which fetches a request and calls back with the response,
+/// possibly from another thread.
+pub trait Fetcher: Send + Sync {
+    fn fetch(&self, request: ehttp::Request, on_done: OnDone);
+}
+
+/// Fetches over HTTP.
+///
+/// Natively, redirects are not followed but answered as they are,
+/// so that permanent moves can be told apart (see [`fetch_following_redirects`]).
+/// On the web, the browser follows redirects by itself and they can't be seen.
+#[derive(Debug, Default)]
+pub struct HttpFetcher;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpFetcher {
+    fn fetch_blocking(request: &ehttp::Request) -> ehttp::Result<ehttp::Response> {
+        let agent = ureq::AgentBuilder::new().redirects(0).build();
+        let mut req = agent.request(&request.method, &request.url);
+        for (name, value) in &request.headers {
+            req = req.set(name, value);
+        }
+        let resp = if request.body.is_empty() {
+            req.call()
+        } else {
+            req.send_bytes(&request.body)
+        };
+        let resp = match resp {
+            Ok(resp) => resp,
+            // Still read the body of e.g. 404.
+            Err(ureq::Error::Status(_, resp)) => resp,
+            Err(ureq::Error::Transport(err)) => return Err(err.to_string()),
+        };
+
+        let url = resp.get_url().to_owned();
+        let status = resp.status();
+        let status_text = resp.status_text().to_owned();
+        let headers = resp
+            .headers_names()
+            .into_iter()
+            .filter_map(|name| {
+                // Lowercase for easy lookup, the same as `ehttp` does.
+                let value = resp.header(&name)?.to_owned();
+                Some((name.to_ascii_lowercase(), value))
+            })
+            .collect();
+        let mut bytes = vec![];
+        std::io::Read::read_to_end(&mut resp.into_reader(), &mut bytes)
+            .map_err(|err| err.to_string())?;
+        Ok(ehttp::Response {
+            url,
+            ok: (200..300).contains(&status),
+            status,
+            status_text,
+            headers,
+            bytes,
+        })
+    }
+}
+
+impl Fetcher for HttpFetcher {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fetch(&self, request: ehttp::Request, on_done: OnDone) {
+        std::thread::Builder::new()
+            .name("fetcher".to_owned())
+            .spawn(move || on_done(Self::fetch_blocking(&request)))
+            .expect("Failed to spawn fetcher thread");
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn fetch(&self, request: ehttp::Request, on_done: OnDone) {
+        ehttp::fetch(request, on_done);
+    }
+}
+
+/// Shared handle of a [`Fetcher`], which fetches over HTTP by default.
+#[derive(Clone)]
+pub struct SharedFetcher(Arc<dyn Fetcher>);
+
+impl SharedFetcher {
+    pub fn new(fetcher: impl Fetcher + 'static) -> Self {
+        SharedFetcher(Arc::new(fetcher))
+    }
+}
+
+impl Default for SharedFetcher {
+    fn default() -> Self {
+        SharedFetcher::new(HttpFetcher)
+    }
+}
+
+impl Deref for SharedFetcher {
+    type Target = dyn Fetcher;
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl std::fmt::Debug for SharedFetcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedFetcher")
+    }
+}
+
+/// Most redirects followed by [`fetch_following_redirects`].
+pub const MAX_REDIRECTS: usize = 5;
+
+/// Response fetched by [`fetch_following_redirects`].
+#[derive(Debug, Clone)]
+pub struct Followed {
+    pub response: ehttp::Response,
+    /// Where the resource has permanently moved to,
+    /// the target of the last permanent redirect before any temporary one.
+    pub moved_to: Option<String>,
+}
+
+/// Callback which receives the result of [`fetch_following_redirects`].
+pub type OnFollowed = Box<dyn FnOnce(ehttp::Result<Followed>) + Send>;
+
+/// Is the status code a permanent redirect?
+pub fn is_permanent_redirect(status: u16) -> bool {
+    matches!(status, 301 | 308)
+}
+
+/// Fetches the request and follows up to [`MAX_REDIRECTS`] redirects,
+/// keeping track of where the resource has permanently moved to.
+pub fn fetch_following_redirects(
+    fetcher: SharedFetcher,
+    request: ehttp::Request,
+    on_done: OnFollowed,
+) {
+    follow(fetcher, request, 0, true, None, on_done);
+}
+
+fn follow(
+    fetcher: SharedFetcher,
+    request: ehttp::Request,
+    redirects: usize,
+    permanent: bool,
+    moved_to: Option<String>,
+    on_done: OnFollowed,
+) {
+    let next = fetcher.clone();
+    fetcher.fetch(
+        request.clone(),
+        Box::new(move |result| {
+            let response = match result {
+                Ok(response) if matches!(response.status, 301 | 302 | 303 | 307 | 308) => response,
+                result => {
+                    on_done(result.map(|response| Followed { response, moved_to }));
+                    return;
+                }
+            };
+            let Some(location) = response
+                .headers
+                .get("location")
+                .and_then(|location| url::Url::parse(&request.url).ok()?.join(location).ok())
+            else {
+                on_done(Err(format!(
+                    "Redirected by {} without a valid location",
+                    response.status
+                )));
+                return;
+            };
+            if redirects >= MAX_REDIRECTS {
+                on_done(Err(format!("Too many redirects, the last to {location}")));
+                return;
+            }
+            let permanent = permanent && is_permanent_redirect(response.status);
+            let moved_to = if permanent {
+                Some(location.to_string())
+            } else {
+                moved_to
+            };
+            let request = ehttp::Request {
+                url: location.to_string(),
+                ..request
+            };
+            follow(next, request, redirects + 1, permanent, moved_to, on_done);
+        }),
+    );
+}
+
+/// Canned response served by [`FixtureFetcher`].
+#[cfg(test)]
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    status: u16,
+    headers: BTreeMap<String, String>,
+    body: Vec<u8>,
+    delay: Duration,
+    /// Fails like a network error instead of responding.
+    error: Option<String>,
+}
+
+#[cfg(test)]
+impl Fixture {
+    /// Responds `200 OK` with the body.
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Fixture {
+            status: 200,
+            headers: BTreeMap::new(),
+            body: body.into(),
+            delay: Duration::ZERO,
+            error: None,
+        }
+    }
+
+    /// Attempts to respond `200 OK` with the content of a file.
+    pub fn try_from_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Fixture::ok(std::fs::read(path)?))
+    }
+
+    /// Responds with the status code and an empty body.
+    pub fn status(status: u16) -> Self {
+        Fixture {
+            status,
+            ..Fixture::ok(vec![])
+        }
+    }
+
+    /// Fails with the error message, like the network is broken.
+    pub fn error(message: impl ToString) -> Self {
+        Fixture {
+            error: Some(message.to_string()),
+            ..Fixture::ok(vec![])
+        }
+    }
+
+    /// Adds a response header.
+    pub fn with_header(mut self, name: impl ToString, value: impl ToString) -> Self {
+        // Headers are looked up in lowercase, the same as `ehttp` does.
+        self.headers
+            .insert(name.to_string().to_ascii_lowercase(), value.to_string());
+        self
+    }
+
+    /// Delays the response.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    fn respond(&self, url: String) -> ehttp::Result<ehttp::Response> {
+        if let Some(error) = &self.error {
+            return Err(error.to_owned());
+        }
+        Ok(ehttp::Response {
+            url,
+            ok: (200..300).contains(&self.status),
+            status: self.status,
+            status_text: String::new(),
+            headers: self.headers.clone(),
+            bytes: self.body.clone(),
+        })
+    }
+}
+
+/// Serves canned responses by URL without touching the network,
+/// and records the requests it gets.
+/// URLs without a fixture get `404 Not Found`.
+#[cfg(test)]
+#[derive(Debug, Default, Clone)]
+pub struct FixtureFetcher {
+    fixtures: Arc<Mutex<HashMap<String, Fixture>>>,
+    requests: Arc<Mutex<Vec<ehttp::Request>>>,
+}
+
+#[cfg(test)]
+impl FixtureFetcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves the fixture for the URL, replacing the former one.
+    pub fn serve(&self, url: impl ToString, fixture: Fixture) -> &Self {
+        self.fixtures
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), fixture);
+        self
+    }
+
+    /// Returns all requests fetched so far.
+    pub fn requests(&self) -> Vec<ehttp::Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl Fetcher for FixtureFetcher {
+    fn fetch(&self, request: ehttp::Request, on_done: OnDone) {
+        self.requests.lock().unwrap().push(request.clone());
+        let fixture = self
+            .fixtures
+            .lock()
+            .unwrap()
+            .get(&request.url)
+            .cloned()
+            .unwrap_or(Fixture::status(404));
+        #[cfg(not(target_arch = "wasm32"))]
+        if !fixture.delay.is_zero() {
+            std::thread::spawn(move || {
+                std::thread::sleep(fixture.delay);
+                on_done(fixture.respond(request.url));
+            });
+            return;
+        }
+        on_done(fixture.respond(request.url));
+    }
+}
+
+/// URLs served by [`fixture_fetcher`].
+#[cfg(test)]
+pub const RSS_URL: &str = "https://rss.example.com/feed.xml";
+#[cfg(test)]
+pub const ATOM_URL: &str = "https://atom.example.com/feed.atom";
+#[cfg(test)]
+pub const JSON_URL: &str = "https://json.example.com/feed.json";
+
+/// Serves the RSS, Atom and JSON Feed fixtures at [`RSS_URL`], [`ATOM_URL`] and [`JSON_URL`].
+/// The RSS one has an `ETag` for conditional requests.
+#[cfg(test)]
+pub fn fixture_fetcher() -> FixtureFetcher {
+    let fetcher = FixtureFetcher::new();
+    fetcher
+        .serve(
+            RSS_URL,
+            Fixture::try_from_file("./fixtures/rss.xml")
+                .unwrap()
+                .with_header("ETag", "\"rss-v1\""),
+        )
+        .serve(
+            ATOM_URL,
+            Fixture::try_from_file("./fixtures/atom.xml").unwrap(),
+        )
+        .serve(
+            JSON_URL,
+            Fixture::try_from_file("./fixtures/feed.json").unwrap(),
+        );
+    fetcher
+}
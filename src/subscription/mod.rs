@@ -1,3 +1,15 @@
 pub mod article;
+pub mod backup;
+pub mod dedup;
+pub mod diff;
+pub mod discovery;
 pub mod feed;
+pub mod fetch;
 pub mod opml;
+pub mod order;
+pub mod retention;
+pub mod rule;
+pub mod schedule;
+pub mod search;
+pub mod storage;
+pub mod tag;
@@ -30,14 +30,14 @@ pub struct Entry {
     pub html_url: Option<Url>,
 }
 
-/// A folder for containing a series of subscriptions on similar topics,
+/// A folder for containing a series of subscriptions on similar topics, and other folders,
 /// which can be converted from [`opml::Outline`]
 #[derive(Debug)]
 #[allow(unused)]
 pub struct Folder {
     pub text: String,
     pub title: Option<String>,
-    pub entries: Vec<Entry>,
+    pub outlines: Vec<Outline>,
 }
 
 /// OPML outline, which can be converted from [`opml::Outline`],
@@ -72,7 +72,7 @@ impl From<&Entry> for opml::Outline {
 impl From<&Folder> for opml::Outline {
     fn from(value: &Folder) -> Self {
         let sub_outlines = value
-            .entries
+            .outlines
             .iter()
             .map(opml::Outline::from)
             .collect::<Vec<_>>();
@@ -131,7 +131,7 @@ impl From<&opml::Outline> for Folder {
         Folder {
             text: value.text.to_owned(),
             title: value.title.as_ref().map(|t| t.to_owned()),
-            entries: Opml::flatten_nested_folder(value),
+            outlines: value.outlines.iter().map(Outline::from).collect(),
         }
     }
 }
@@ -182,25 +182,10 @@ impl From<super::feed::Feed> for Opml {
             let orphan_entry = Entry::from(orphan.borrow().to_owned());
             outlines.push(Outline::Entry(orphan_entry));
         }
-        for folder_id in value.get_all_folder_ids() {
-            let folder = value.try_get_folder_by_id(&folder_id).unwrap();
-            let mut folder_outlines = vec![];
-            for entry_id in value.try_get_entry_ids_by_folder_id(&folder_id).unwrap() {
-                let entry = Entry::from(
-                    value
-                        .try_get_entry_by_id(&entry_id)
-                        .unwrap()
-                        .borrow()
-                        .to_owned(),
-                );
-                folder_outlines.push(entry);
-            }
-            let folder = Folder {
-                text: folder.borrow().title().to_owned(),
-                title: Some(folder.borrow().title().to_owned()),
-                entries: folder_outlines,
-            };
-            outlines.push(Outline::Folder(folder));
+        for folder_id in value.get_top_level_folder_ids() {
+            outlines.push(Outline::Folder(Folder::from_feed_folder(
+                &value, &folder_id,
+            )));
         }
         Opml {
             version,
@@ -244,20 +229,31 @@ impl From<&Opml> for OPML {
     }
 }
 
-impl Opml {
-    #[allow(unused)]
-    fn flatten_nested_folder(outline: &opml::Outline) -> Vec<Entry> {
-        if outline.xml_url.is_some() {
-            vec![Entry::from(outline)]
-        } else {
-            outline
-                .outlines
-                .iter()
-                .flat_map(Self::flatten_nested_folder)
-                .collect::<Vec<Entry>>()
+impl Folder {
+    /// Converts a folder of the feed, with the entries and the folders inside.
+    fn from_feed_folder(feed: &super::feed::Feed, folder_id: &super::feed::FolderUuid) -> Self {
+        let folder = feed.try_get_folder_by_id(folder_id).unwrap();
+        let mut outlines = vec![];
+        for entry_id in feed.try_get_entry_ids_by_folder_id(folder_id).unwrap() {
+            let entry = feed.try_get_entry_by_id(&entry_id).unwrap();
+            outlines.push(Outline::Entry(Entry::from(entry.borrow().to_owned())));
+        }
+        for subfolder_id in feed.try_get_subfolder_ids(folder_id).unwrap() {
+            outlines.push(Outline::Folder(Folder::from_feed_folder(
+                feed,
+                &subfolder_id,
+            )));
+        }
+        let title = folder.borrow().title().to_owned();
+        Folder {
+            text: title.clone(),
+            title: Some(title),
+            outlines,
         }
     }
+}
 
+impl Opml {
     /// Attempts to parse a OPML XML file.
     #[allow(unused)]
     pub fn try_from_str(xml: &str) -> Result<Self> {
@@ -275,6 +271,7 @@ impl Opml {
 
 #[cfg(test)]
 mod test {
+    use crate::subscription::feed::Feed;
     use crate::subscription::opml::Opml;
     use std::fs::read_to_string;
 
@@ -291,6 +288,37 @@ mod test {
         println!("{:?}", opml);
     }
 
+    #[test]
+    fn nested_folders() {
+        let xml = read_to_string("./OPMLs/nested.opml").unwrap();
+        let feed = Feed::try_from(Opml::try_from_str(&xml).unwrap()).unwrap();
+        // Dumped with the hierarchy, and parsed back the same.
+        let dumped = Opml::from(feed).try_dump().unwrap();
+        let feed = Feed::try_from(Opml::try_from_str(&dumped).unwrap()).unwrap();
+        let tech = feed.get_top_level_folder_ids();
+        assert_eq!(tech.len(), 1);
+        let rust = feed.try_get_subfolder_ids(&tech[0]).unwrap();
+        let blogs = feed.try_get_subfolder_ids(&rust[0]).unwrap();
+        let title = |id| {
+            feed.try_get_folder_by_id(id)
+                .unwrap()
+                .borrow()
+                .title()
+                .to_owned()
+        };
+        assert_eq!(
+            (title(&tech[0]), title(&rust[0]), title(&blogs[0])),
+            ("Tech".to_owned(), "Rust".to_owned(), "Blogs".to_owned())
+        );
+        assert_eq!(
+            feed.try_get_entry_ids_by_folder_id(&blogs[0])
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(feed.get_all_orphan_entry_ids().len(), 1);
+    }
+
     #[test]
     fn dump_opml() {
         let xml = read_to_string("./OPMLs/example1.opml").unwrap();
@@ -0,0 +1,122 @@
+//! Order of folders and entries among their siblings in the library,
+//! which is given by the reader or by a sort mode.
+use std::cmp::Ordering;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How folders and entries are ordered among their siblings.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SortMode {
+    /// In the order given by the reader, which is the order in OPML when imported.
+    #[default]
+    Manual,
+    /// By names, alphabetically.
+    Name,
+    /// By the numbers of unread articles, the most first.
+    Unread,
+    /// By the dates of the newest articles, the newest first.
+    Updated,
+}
+
+impl SortMode {
+    pub const ALL: [SortMode; 4] = [
+        SortMode::Manual,
+        SortMode::Name,
+        SortMode::Unread,
+        SortMode::Updated,
+    ];
+
+    /// Does it need the articles of the folders and entries to sort them?
+    pub fn uses_articles(self) -> bool {
+        matches!(self, SortMode::Unread | SortMode::Updated)
+    }
+}
+
+/// What a folder or an entry is sorted by. The numbers of unread articles and
+/// the dates of the newest articles are only needed by the modes using them.
+#[derive(Debug, Default, Clone)]
+pub struct SortKey {
+    /// Position given by the reader.
+    pub position: u32,
+    pub name: String,
+    pub unread: usize,
+    pub updated: Option<DateTime<Utc>>,
+}
+
+/// Sorts items by their keys in the mode, where ties are broken by
+/// the positions given by the reader, then by the names.
+pub fn sort<T>(items: &mut [(T, SortKey)], mode: SortMode) {
+    items.sort_by(|(_, a), (_, b)| {
+        let by_mode = match mode {
+            SortMode::Manual => Ordering::Equal,
+            SortMode::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortMode::Unread => b.unread.cmp(&a.unread),
+            SortMode::Updated => b.updated.cmp(&a.updated),
+        };
+        by_mode
+            .then(a.position.cmp(&b.position))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+/// Moves the item by `offset` places in the items, stopping at either end.
+pub fn shift<T: PartialEq>(items: &mut Vec<T>, item: &T, offset: isize) {
+    let Some(index) = items.iter().position(|other| other == item) else {
+        return;
+    };
+    let to = index.saturating_add_signed(offset).min(items.len() - 1);
+    let item = items.remove(index);
+    items.insert(to, item);
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use super::{shift, sort, SortKey, SortMode};
+
+    #[test]
+    fn sort_modes() {
+        let key = |position, name: &str, unread, day: Option<u32>| SortKey {
+            position,
+            name: name.to_owned(),
+            unread,
+            updated: day.map(|day| Utc.with_ymd_and_hms(2023, 11, day, 0, 0, 0).unwrap()),
+        };
+        let mut items = vec![
+            ("b", key(0, "b", 1, Some(2))),
+            ("C", key(1, "C", 3, None)),
+            ("a", key(1, "a", 1, Some(5))),
+        ];
+        let order = |items: &[(&str, SortKey)]| {
+            items
+                .iter()
+                .map(|(id, _)| id.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // Same positions, which are left by older versions, are ordered by names.
+        sort(&mut items, SortMode::Manual);
+        assert_eq!(order(&items), ["b", "C", "a"]);
+        sort(&mut items, SortMode::Name);
+        assert_eq!(order(&items), ["a", "b", "C"]);
+        sort(&mut items, SortMode::Unread);
+        assert_eq!(order(&items), ["C", "b", "a"]);
+        sort(&mut items, SortMode::Updated);
+        assert_eq!(order(&items), ["a", "b", "C"]);
+    }
+
+    #[test]
+    fn shift_items() {
+        let mut items = vec![1, 2, 3, 4];
+        shift(&mut items, &3, -1);
+        assert_eq!(items, [1, 3, 2, 4]);
+        shift(&mut items, &1, -1);
+        assert_eq!(items, [1, 3, 2, 4]);
+        shift(&mut items, &3, 5);
+        assert_eq!(items, [1, 2, 4, 3]);
+        shift(&mut items, &5, 1);
+        assert_eq!(items, [1, 2, 4, 3]);
+    }
+}
@@ -0,0 +1,189 @@
+//! Retention rules which prune old articles so that the library doesn't grow forever.
+use super::article::{Article, ArticleUuid, Flag};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Rules for pruning the articles of an entry, each of which is not applied if `None`.
+/// Rules of entries and folders take precedence over those of their parents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Retention {
+    /// Most articles kept for an entry.
+    pub max_articles: Option<usize>,
+    /// Most days an article is kept since its date, or since it's first seen if it's undated.
+    pub max_age_days: Option<u32>,
+}
+
+impl Retention {
+    /// Returns the rules, with those not set taken from the fallback.
+    pub fn or(self, fallback: Retention) -> Retention {
+        Retention {
+            max_articles: self.max_articles.or(fallback.max_articles),
+            max_age_days: self.max_age_days.or(fallback.max_age_days),
+        }
+    }
+}
+
+/// Global retention policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// Rules for entries which set none of their own.
+    pub retention: Retention,
+    /// Never prunes unread articles.
+    pub keep_unread: bool,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            retention: Retention::default(),
+            keep_unread: true,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Is the article exempted from pruning?
+    /// Flagged and tagged articles are always kept, and unread ones are if `keep_unread` is set.
+    pub fn keeps(&self, article: &Article) -> bool {
+        Flag::ALL.iter().any(|flag| article.flag(*flag))
+            || !article.tags.is_empty()
+            || self.keep_unread && article.unread
+    }
+
+    /// Selects the articles of an entry to prune by the rules at the time.
+    pub fn select_pruned<'a>(
+        &self,
+        retention: &Retention,
+        articles: impl IntoIterator<Item = (&'a ArticleUuid, &'a Article)>,
+        now: DateTime<Utc>,
+    ) -> Vec<ArticleUuid> {
+        let mut articles: Vec<_> = articles.into_iter().collect();
+        // From the latest, undated articles are dated when they are first seen.
+        articles.sort_by_key(|(id, article)| (std::cmp::Reverse(article.sort_date()), *id));
+        let expire = retention
+            .max_age_days
+            .map(|days| now - Duration::days(days as i64));
+        articles
+            .into_iter()
+            .enumerate()
+            .filter(|(index, (_, article))| {
+                let too_many = retention.max_articles.is_some_and(|max| *index >= max);
+                let too_old = expire.is_some_and(|expire| article.sort_date() < expire);
+                (too_many || too_old) && !self.keeps(article)
+            })
+            .map(|(_, (id, _))| id.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Retention, RetentionPolicy};
+    use crate::subscription::article::{Article, ArticleUuid, Flag};
+    use crate::subscription::feed::EntryUuid;
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+<channel>
+  <title>Retention</title>
+  <item><guid>1</guid><title>1</title><pubDate>Mon, 06 Nov 2023 08:00:00 GMT</pubDate></item>
+  <item><guid>2</guid><title>2</title><pubDate>Sun, 05 Nov 2023 08:00:00 GMT</pubDate></item>
+  <item><guid>3</guid><title>3</title><pubDate>Sat, 04 Nov 2023 08:00:00 GMT</pubDate></item>
+  <item><guid>4</guid><title>4</title><pubDate>Fri, 03 Nov 2023 08:00:00 GMT</pubDate></item>
+  <item><guid>5</guid><title>5</title></item>
+</channel>
+</rss>"#;
+
+    fn articles_of(articles: &[(ArticleUuid, Article)]) -> Vec<(&ArticleUuid, &Article)> {
+        articles.iter().map(|(id, article)| (id, article)).collect()
+    }
+
+    fn guids(ids: Vec<ArticleUuid>) -> Vec<String> {
+        ids.iter().map(|id| id.guid().to_owned()).collect()
+    }
+
+    #[test]
+    fn prune() {
+        let feed_id = EntryUuid::from(Uuid::new_v4());
+        let entries = feed_rs::parser::parse(RSS.as_bytes()).unwrap().entries;
+        let mut articles: Vec<(ArticleUuid, Article)> =
+            ArticleUuid::from_entries(&feed_id, RSS.as_bytes(), &entries)
+                .into_iter()
+                .zip(entries.into_iter().map(Article::from))
+                .collect();
+        let now = Utc.with_ymd_and_hms(2023, 11, 6, 12, 0, 0).unwrap();
+        let policy = RetentionPolicy {
+            keep_unread: false,
+            ..Default::default()
+        };
+
+        // Nothing is pruned without rules.
+        assert!(policy
+            .select_pruned(&Retention::default(), articles_of(&articles), now)
+            .is_empty());
+        // The undated article is the latest, which is first seen now.
+        let retention = Retention {
+            max_articles: Some(2),
+            max_age_days: None,
+        };
+        assert_eq!(
+            guids(policy.select_pruned(&retention, articles_of(&articles), now)),
+            vec!["2", "3", "4"]
+        );
+        let retention = Retention {
+            max_articles: None,
+            max_age_days: Some(2),
+        };
+        assert_eq!(
+            guids(policy.select_pruned(&retention, articles_of(&articles), now)),
+            vec!["3", "4"]
+        );
+
+        // Unread articles are kept.
+        articles[2].1.set_read();
+        let policy = RetentionPolicy::default();
+        assert_eq!(
+            guids(policy.select_pruned(&retention, articles_of(&articles), now)),
+            vec!["3"]
+        );
+        // So are flagged and tagged articles, even if they are read.
+        for flag in Flag::ALL {
+            articles[2].1.set_flag(flag, true);
+            assert!(policy
+                .select_pruned(&retention, articles_of(&articles), now)
+                .is_empty());
+            articles[2].1.set_flag(flag, false);
+        }
+        articles[2].1.tags.insert("weekly".to_owned());
+        assert!(policy
+            .select_pruned(&retention, articles_of(&articles), now)
+            .is_empty());
+    }
+
+    #[test]
+    fn override_rules() {
+        let global = Retention {
+            max_articles: Some(100),
+            max_age_days: Some(30),
+        };
+        let folder = Retention {
+            max_articles: None,
+            max_age_days: Some(7),
+        };
+        let entry = Retention {
+            max_articles: Some(10),
+            max_age_days: None,
+        };
+        assert_eq!(
+            entry.or(folder).or(global),
+            Retention {
+                max_articles: Some(10),
+                max_age_days: Some(7),
+            }
+        );
+    }
+}
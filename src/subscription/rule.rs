@@ -0,0 +1,219 @@
+//! Rules which smart folders collect articles by, rather than by membership.
+use std::collections::HashSet;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use super::article::{Article, Flag};
+use super::feed::{EntryUuid, FolderUuid};
+
+/// A condition which articles are tested by.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Condition {
+    Unread,
+    Flagged(Flag),
+    /// Tagged by the reader with the tag.
+    Tagged(String),
+    /// Categorized by the feed in the category, case-insensitively.
+    Category(String),
+    /// In the entry.
+    InEntry(EntryUuid),
+    /// In the folder, or in folders inside it.
+    InFolder(FolderUuid),
+    /// Dated (or first seen if undated) within the days.
+    WithinDays(u32),
+    /// The title matches the regular expression, case-insensitively.
+    TitleMatches(String),
+}
+
+/// A saved rule, which is the conditions all or any of which articles meet.
+/// A rule without conditions matches all articles.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Rule {
+    pub conditions: Vec<Condition>,
+    /// Do articles meet any of the conditions rather than all of them?
+    pub match_any: bool,
+}
+
+impl Rule {
+    /// Attempts to make the rule ready to match articles, by resolving folders into
+    /// their entries with `entries_in_folder` and compiling the patterns of titles.
+    pub fn try_compile(
+        &self,
+        entries_in_folder: impl Fn(&FolderUuid) -> Vec<EntryUuid>,
+        now: DateTime<Utc>,
+    ) -> Result<Matcher> {
+        let tests = self
+            .conditions
+            .iter()
+            .map(|condition| {
+                Ok(match condition {
+                    Condition::Unread => Test::Unread,
+                    Condition::Flagged(flag) => Test::Flagged(*flag),
+                    Condition::Tagged(name) => Test::Tagged(name.clone()),
+                    Condition::Category(name) => Test::Category(name.to_lowercase()),
+                    Condition::InEntry(id) => Test::InEntries(HashSet::from([*id])),
+                    Condition::InFolder(id) => {
+                        Test::InEntries(entries_in_folder(id).into_iter().collect())
+                    }
+                    Condition::WithinDays(days) => Test::Since(now - Duration::days(*days as i64)),
+                    Condition::TitleMatches(pattern) => Test::Title(
+                        RegexBuilder::new(pattern)
+                            .case_insensitive(true)
+                            .build()
+                            .with_context(|| format!("Invalid pattern `{pattern}`"))?,
+                    ),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Matcher {
+            tests,
+            match_any: self.match_any,
+        })
+    }
+}
+
+/// A condition ready to test articles.
+#[derive(Debug, Clone)]
+enum Test {
+    Unread,
+    Flagged(Flag),
+    Tagged(String),
+    /// The lowercased category.
+    Category(String),
+    InEntries(HashSet<EntryUuid>),
+    Since(DateTime<Utc>),
+    Title(Regex),
+}
+
+impl Test {
+    fn passes(&self, article: &Article) -> bool {
+        match self {
+            Test::Unread => article.unread,
+            Test::Flagged(flag) => article.flag(*flag),
+            Test::Tagged(name) => article.tags.contains(name),
+            Test::Category(name) => article
+                .categories
+                .iter()
+                .any(|category| category.to_lowercase() == *name),
+            Test::InEntries(entries) => article
+                .belong_to
+                .is_some_and(|entry| entries.contains(&entry)),
+            Test::Since(since) => article.sort_date() >= *since,
+            Test::Title(pattern) => pattern.is_match(&article.title),
+        }
+    }
+}
+
+/// A rule ready to match articles (see [`Rule::try_compile`]).
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    tests: Vec<Test>,
+    match_any: bool,
+}
+
+impl Matcher {
+    /// Does the article match the rule?
+    pub fn matches(&self, article: &Article) -> bool {
+        if self.tests.is_empty() {
+            true
+        } else if self.match_any {
+            self.tests.iter().any(|test| test.passes(article))
+        } else {
+            self.tests.iter().all(|test| test.passes(article))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    use super::{Condition, Rule};
+    use crate::subscription::article::{Article, Flag};
+    use crate::subscription::feed::{EntryUuid, FolderUuid};
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+<channel>
+  <title>Rules</title>
+  <item><guid>1</guid><title>Rust 1.74 released</title><category>Release</category><pubDate>Mon, 06 Nov 2023 08:00:00 GMT</pubDate></item>
+  <item><guid>2</guid><title>WASM in the browser</title><pubDate>Fri, 03 Nov 2023 08:00:00 GMT</pubDate></item>
+  <item><guid>3</guid><title>Weekly notes</title><category>release</category><pubDate>Sun, 05 Nov 2023 08:00:00 GMT</pubDate></item>
+</channel>
+</rss>"#;
+
+    #[test]
+    fn match_articles() {
+        let entry = EntryUuid::from(Uuid::new_v4());
+        let folder = FolderUuid::from(Uuid::new_v4());
+        let mut articles: Vec<Article> = feed_rs::parser::parse(RSS.as_bytes())
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|item| Article::from(item).set_belonging(&entry))
+            .collect();
+        articles[0].set_flag(Flag::Starred, true);
+        articles[1].set_read();
+        let now = Utc.with_ymd_and_hms(2023, 11, 6, 12, 0, 0).unwrap();
+        let matched = |rule: Rule| {
+            let matcher = rule
+                .try_compile(|id| if *id == folder { vec![entry] } else { vec![] }, now)
+                .unwrap();
+            articles
+                .iter()
+                .filter(|article| matcher.matches(article))
+                .map(|article| article.title.clone())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matched(Rule::default()).len(), 3);
+        assert_eq!(
+            matched(Rule {
+                conditions: vec![
+                    Condition::Unread,
+                    Condition::InFolder(folder),
+                    Condition::WithinDays(3),
+                ],
+                match_any: false,
+            }),
+            ["Rust 1.74 released", "Weekly notes"]
+        );
+        assert_eq!(
+            matched(Rule {
+                conditions: vec![Condition::TitleMatches("rust|wasm".to_owned())],
+                match_any: false,
+            }),
+            ["Rust 1.74 released", "WASM in the browser"]
+        );
+        assert_eq!(
+            matched(Rule {
+                conditions: vec![
+                    Condition::Flagged(Flag::Starred),
+                    Condition::Category("release".to_owned()),
+                ],
+                match_any: false,
+            }),
+            ["Rust 1.74 released"]
+        );
+        assert_eq!(
+            matched(Rule {
+                conditions: vec![
+                    Condition::Flagged(Flag::Starred),
+                    Condition::Category("release".to_owned()),
+                ],
+                match_any: true,
+            }),
+            ["Rust 1.74 released", "Weekly notes"]
+        );
+        assert!(Rule {
+            conditions: vec![Condition::TitleMatches("(".to_owned())],
+            match_any: false,
+        }
+        .try_compile(|_| vec![], now)
+        .is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Refresh hints published by feeds and the computation of refresh times.
+use chrono::{DateTime, Datelike, Duration, DurationRound, Timelike, Utc, Weekday};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+lazy_static! {
+    static ref SKIP_HOURS_PATTERN: Regex = Regex::new(r"(?s)<skipHours>(.*?)</skipHours>").unwrap();
+    static ref HOUR_PATTERN: Regex = Regex::new(r"<hour>\s*(\d+)\s*</hour>").unwrap();
+    static ref SKIP_DAYS_PATTERN: Regex = Regex::new(r"(?s)<skipDays>(.*?)</skipDays>").unwrap();
+    static ref DAY_PATTERN: Regex = Regex::new(r"<day>\s*(\w+)\s*</day>").unwrap();
+    static ref UPDATE_PERIOD_PATTERN: Regex =
+        Regex::new(r"<sy:updatePeriod>\s*(\w+)\s*</sy:updatePeriod>").unwrap();
+    static ref UPDATE_FREQUENCY_PATTERN: Regex =
+        Regex::new(r"<sy:updateFrequency>\s*(\d+)\s*</sy:updateFrequency>").unwrap();
+}
+
+/// Hints about how often a feed should be refreshed, published by the feed itself
+/// through RSS `<ttl>`, `<skipHours>`, `<skipDays>` and the syndication module
+/// (`<sy:updatePeriod>` and `<sy:updateFrequency>`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RefreshHints {
+    /// Minutes the feed may be cached before refreshing.
+    pub ttl: Option<u32>,
+    /// Hours (in GMT) during which the feed should not be refreshed.
+    pub skip_hours: BTreeSet<u32>,
+    /// Days during which the feed should not be refreshed.
+    pub skip_days: Vec<Weekday>,
+    /// Minutes between two updates announced by the syndication module.
+    pub update_interval: Option<u32>,
+}
+
+impl RefreshHints {
+    /// Collects the hints of a parsed feed and its raw document.
+    pub fn from_feed(feed: &feed_rs::model::Feed, source: &[u8]) -> Self {
+        let source = String::from_utf8_lossy(source);
+        let skip_hours = SKIP_HOURS_PATTERN
+            .captures(&source)
+            .map(|block| {
+                HOUR_PATTERN
+                    .captures_iter(&block[1])
+                    .filter_map(|hour| hour[1].parse::<u32>().ok())
+                    // Some feeds use 24 for midnight.
+                    .map(|hour| hour % 24)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let skip_days = SKIP_DAYS_PATTERN
+            .captures(&source)
+            .map(|block| {
+                let mut days: Vec<Weekday> = DAY_PATTERN
+                    .captures_iter(&block[1])
+                    .filter_map(|day| day[1].parse::<Weekday>().ok())
+                    .collect();
+                days.sort_by_key(Weekday::num_days_from_monday);
+                days.dedup();
+                days
+            })
+            .unwrap_or_default();
+        let update_interval = UPDATE_PERIOD_PATTERN
+            .captures(&source)
+            .and_then(|period| match &period[1] {
+                "hourly" => Some(60),
+                "daily" => Some(60 * 24),
+                "weekly" => Some(60 * 24 * 7),
+                "monthly" => Some(60 * 24 * 30),
+                "yearly" => Some(60 * 24 * 365),
+                _ => None,
+            })
+            .map(|period: u32| {
+                let frequency = UPDATE_FREQUENCY_PATTERN
+                    .captures(&source)
+                    .and_then(|frequency| frequency[1].parse::<u32>().ok())
+                    .filter(|frequency| *frequency > 0)
+                    .unwrap_or(1);
+                period / frequency
+            });
+        RefreshHints {
+            ttl: feed.ttl,
+            skip_hours,
+            skip_days,
+            update_interval,
+        }
+    }
+
+    /// Returns the interval in minutes the feed asks to be refreshed at,
+    /// the longer of `ttl` and `sy:updatePeriod` if it has both, to be polite.
+    pub fn requested_interval(&self) -> Option<u32> {
+        self.ttl.into_iter().chain(self.update_interval).max()
+    }
+
+    /// Is refreshing skipped at the time?
+    pub fn is_skipped(&self, time: &DateTime<Utc>) -> bool {
+        self.skip_hours.contains(&time.hour()) || self.skip_days.contains(&time.weekday())
+    }
+
+    /// Returns the time of the next refresh after the latest one with the interval in minutes,
+    /// which is postponed to the first hour that is not skipped.
+    pub fn next_refresh(&self, last_refresh: DateTime<Utc>, interval: u32) -> DateTime<Utc> {
+        let interval = self
+            .requested_interval()
+            .map_or(interval, |requested| interval.max(requested));
+        let mut next = last_refresh + Duration::minutes(interval as i64);
+        // A week is enough to find an hour which is not skipped, if there is one.
+        for _ in 0..24 * 7 {
+            if !self.is_skipped(&next) {
+                break;
+            }
+            next = next.duration_trunc(Duration::hours(1)).unwrap_or(next) + Duration::hours(1);
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RefreshHints;
+    use chrono::{TimeZone, Utc, Weekday};
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:sy="http://purl.org/rss/1.0/modules/syndication/">
+<channel>
+  <title>Hints</title>
+  <link>https://example.com/</link>
+  <description>Feed with refresh hints</description>
+  <ttl>90</ttl>
+  <sy:updatePeriod>daily</sy:updatePeriod>
+  <sy:updateFrequency>4</sy:updateFrequency>
+  <skipHours><hour>0</hour><hour>1</hour><hour>24</hour></skipHours>
+  <skipDays><day>Sunday</day></skipDays>
+</channel>
+</rss>"#;
+
+    #[test]
+    fn parse_hints() {
+        let feed = feed_rs::parser::parse(RSS.as_bytes()).unwrap();
+        let hints = RefreshHints::from_feed(&feed, RSS.as_bytes());
+        assert_eq!(hints.ttl, Some(90));
+        assert_eq!(hints.update_interval, Some(360));
+        // The longer one of the hints is kept.
+        assert_eq!(hints.requested_interval(), Some(360));
+        assert_eq!(hints.skip_hours.into_iter().collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(hints.skip_days, vec![Weekday::Sun]);
+    }
+
+    #[test]
+    fn next_refresh() {
+        let feed = feed_rs::parser::parse(RSS.as_bytes()).unwrap();
+        let hints = RefreshHints::from_feed(&feed, RSS.as_bytes());
+        // Saturday, the interval is raised to what the feed asks for.
+        let last = Utc.with_ymd_and_hms(2023, 11, 4, 12, 0, 0).unwrap();
+        assert_eq!(
+            hints.next_refresh(last, 30),
+            Utc.with_ymd_and_hms(2023, 11, 4, 18, 0, 0).unwrap()
+        );
+        // Postponed through the whole Sunday and the skipped hours of Monday.
+        let last = Utc.with_ymd_and_hms(2023, 11, 4, 20, 30, 0).unwrap();
+        assert_eq!(
+            hints.next_refresh(last, 30),
+            Utc.with_ymd_and_hms(2023, 11, 6, 2, 0, 0).unwrap()
+        );
+        // Feeds without hints are refreshed at the given interval.
+        assert_eq!(
+            RefreshHints::default().next_refresh(last, 30),
+            Utc.with_ymd_and_hms(2023, 11, 4, 21, 0, 0).unwrap()
+        );
+    }
+}
@@ -0,0 +1,501 @@
+//! Full-text search of articles, by an inverted index of their titles, bodies,
+//! authors and categories.
+//!
+//! A query is a list of clauses which all have to match, where:
+//! - a word matches the same word, case insensitively;
+//! - `prefix*` matches the words starting with the prefix;
+//! - `"a phrase"` matches the words next to each other in order;
+//! - a word of several tokens, like `e-mail` or CJK text, is matched as a phrase.
+//!
+//! CJK characters are tokens by themselves, since words are not separated by spaces there.
+use super::article::{Article, ArticleUuid};
+use super::feed::{EntryUuid, FolderUuid};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+
+/// Positions skipped between the fields of an article, so that phrases don't span fields.
+const FIELD_GAP: u32 = 8;
+
+/// Token of a text, lowercased, with where it is in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub range: Range<usize>,
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch,
+        '\u{2E80}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FFFF}')
+}
+
+/// Splits the text into words of letters and digits, and CJK characters.
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut push = |range: Range<usize>| {
+        tokens.push(Token {
+            text: text[range.clone()].to_lowercase(),
+            range,
+        })
+    };
+    let mut word_start = None;
+    for (index, ch) in text.char_indices() {
+        let cjk = is_cjk(ch);
+        if ch.is_alphanumeric() && !cjk {
+            word_start.get_or_insert(index);
+            continue;
+        }
+        if let Some(start) = word_start.take() {
+            push(start..index);
+        }
+        if cjk {
+            push(index..index + ch.len_utf8());
+        }
+    }
+    if let Some(start) = word_start {
+        push(start..text.len());
+    }
+    tokens
+}
+
+/// Extracts the plain text of an HTML fragment.
+pub fn plain_text(html: &str) -> String {
+    let fragment = scraper::Html::parse_fragment(html);
+    let text: Vec<&str> = fragment.root_element().text().collect();
+    text.join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    text: String,
+    prefix: bool,
+}
+
+impl Term {
+    fn matches(&self, token: &str) -> bool {
+        if self.prefix {
+            token.starts_with(&self.text)
+        } else {
+            token == self.text
+        }
+    }
+}
+
+/// Parsed search query, whose clauses, words or phrases, all have to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    clauses: Vec<Vec<Term>>,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Self {
+        let mut clauses = vec![];
+        let mut push = |chunk: &str| {
+            let prefix = chunk.trim_end().ends_with('*');
+            let mut terms: Vec<Term> = tokenize(chunk)
+                .into_iter()
+                .map(|token| Term {
+                    text: token.text,
+                    prefix: false,
+                })
+                .collect();
+            if let Some(last) = terms.last_mut() {
+                last.prefix = prefix;
+                clauses.push(terms);
+            }
+        };
+        // Phrases are the odd parts between quotes.
+        for (index, part) in text.split('"').enumerate() {
+            if index % 2 == 1 {
+                push(part);
+            } else {
+                part.split_whitespace().for_each(&mut push);
+            }
+        }
+        Self { clauses }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// Does the token match any term of the query? For highlighting.
+    pub fn matches_token(&self, token: &str) -> bool {
+        self.clauses
+            .iter()
+            .flatten()
+            .any(|term| term.matches(token))
+    }
+}
+
+/// Filters of search results.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFilter {
+    pub entry: Option<EntryUuid>,
+    pub folder: Option<FolderUuid>,
+    /// Articles dated (or first seen if undated) since then.
+    pub since: Option<DateTime<Utc>>,
+    /// Articles dated (or first seen if undated) before then.
+    pub until: Option<DateTime<Utc>>,
+    pub unread_only: bool,
+}
+
+impl SearchFilter {
+    /// Does the article pass the filters, whose entries are resolved from the entry and folder?
+    pub fn accepts(&self, entries: Option<&HashSet<EntryUuid>>, article: &Article) -> bool {
+        let date = article.sort_date();
+        entries.map_or(true, |entries| {
+            article
+                .belong_to
+                .is_some_and(|entry| entries.contains(&entry))
+        }) && self.since.map_or(true, |since| date >= since)
+            && self.until.map_or(true, |until| date < until)
+            && (!self.unread_only || article.unread)
+    }
+}
+
+#[derive(Debug)]
+struct Document {
+    /// Distinct terms, for removing the document.
+    terms: Vec<String>,
+    /// Number of positions of the title, which come first.
+    title_len: u32,
+}
+
+/// Inverted index of articles, updated as articles are added, updated and removed.
+/// Articles loaded from the storage are indexed before the first search,
+/// since their bodies are not loaded until then.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    built: bool,
+    /// Positions of each term in the articles.
+    terms: BTreeMap<String, HashMap<ArticleUuid, Vec<u32>>>,
+    documents: HashMap<ArticleUuid, Document>,
+}
+
+impl SearchIndex {
+    /// Are all articles in the library indexed?
+    pub fn is_built(&self) -> bool {
+        self.built
+    }
+
+    /// Marks the index as having all articles, or not, so that it's built before the next search.
+    pub fn set_built(&mut self, built: bool) {
+        self.built = built;
+    }
+
+    pub fn contains(&self, id: &ArticleUuid) -> bool {
+        self.documents.contains_key(id)
+    }
+
+    /// Takes the articles of another index which are not in this one,
+    /// whose versions here are newer.
+    pub fn absorb(&mut self, other: SearchIndex) {
+        for (term, postings) in other.terms {
+            for (id, positions) in postings {
+                if !self.documents.contains_key(&id) {
+                    self.terms
+                        .entry(term.clone())
+                        .or_default()
+                        .insert(id, positions);
+                }
+            }
+        }
+        for (id, document) in other.documents {
+            self.documents.entry(id).or_insert(document);
+        }
+    }
+
+    /// Indexes the article, replacing the former version of it.
+    /// Its body should be loaded, or only the rest of it is indexed.
+    pub fn insert(&mut self, id: &ArticleUuid, article: &Article) {
+        self.remove(id);
+        let authors: Vec<&str> = article
+            .authors
+            .iter()
+            .map(|person| person.name.as_str())
+            .collect();
+        let fields = [
+            article.title.clone(),
+            article
+                .summary
+                .as_deref()
+                .map(plain_text)
+                .unwrap_or_default(),
+            article
+                .content
+                .as_deref()
+                .map(plain_text)
+                .unwrap_or_default(),
+            authors.join(" "),
+            article.categories.join(" "),
+        ];
+        let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+        let mut position = 0;
+        let mut title_len = 0;
+        for (index, field) in fields.iter().enumerate() {
+            for token in tokenize(field) {
+                positions.entry(token.text).or_default().push(position);
+                position += 1;
+            }
+            if index == 0 {
+                title_len = position;
+            }
+            position += FIELD_GAP;
+        }
+        let terms = positions.keys().cloned().collect();
+        for (term, positions) in positions {
+            self.terms
+                .entry(term)
+                .or_default()
+                .insert(id.clone(), positions);
+        }
+        self.documents
+            .insert(id.clone(), Document { terms, title_len });
+    }
+
+    pub fn remove(&mut self, id: &ArticleUuid) {
+        let Some(document) = self.documents.remove(id) else {
+            return;
+        };
+        for term in document.terms {
+            if let Some(postings) = self.terms.get_mut(&term) {
+                postings.remove(id);
+                if postings.is_empty() {
+                    self.terms.remove(&term);
+                }
+            }
+        }
+    }
+
+    /// Positions of the term in the articles, of all words starting with it if it's a prefix.
+    fn postings(&self, term: &Term) -> HashMap<&ArticleUuid, Vec<u32>> {
+        let mut merged: HashMap<&ArticleUuid, Vec<u32>> = HashMap::new();
+        let words = self
+            .terms
+            .range(term.text.clone()..)
+            .take_while(|(word, _)| term.matches(word));
+        for (_, postings) in words {
+            for (id, positions) in postings {
+                merged.entry(id).or_default().extend(positions);
+            }
+        }
+        merged
+    }
+
+    /// Matches of the clause in the articles, as the positions where they start.
+    fn matches(&self, clause: &[Term]) -> HashMap<&ArticleUuid, Vec<u32>> {
+        let postings: Vec<_> = clause.iter().map(|term| self.postings(term)).collect();
+        let Some((first, rest)) = postings.split_first() else {
+            return HashMap::new();
+        };
+        first
+            .iter()
+            .filter_map(|(id, starts)| {
+                let rest: Vec<HashSet<u32>> = rest
+                    .iter()
+                    .map(|postings| Some(postings.get(id)?.iter().copied().collect()))
+                    .collect::<Option<_>>()?;
+                let starts: Vec<u32> = starts
+                    .iter()
+                    .copied()
+                    .filter(|start| {
+                        rest.iter().enumerate().all(|(offset, positions)| {
+                            positions.contains(&(start + 1 + offset as u32))
+                        })
+                    })
+                    .collect();
+                (!starts.is_empty()).then_some((*id, starts))
+            })
+            .collect()
+    }
+
+    /// Searches the articles matching all clauses of the query, from the most relevant.
+    /// Matches in titles, and of rarer clauses, are more relevant.
+    pub fn search(&self, query: &Query) -> Vec<(ArticleUuid, f32)> {
+        let mut scores: Option<HashMap<&ArticleUuid, f32>> = None;
+        for clause in &query.clauses {
+            let matches = self.matches(clause);
+            let idf = (1.0 + self.documents.len() as f32 / matches.len().max(1) as f32).ln();
+            let clause_scores = matches.into_iter().filter_map(|(id, starts)| {
+                let in_title = starts.iter().any(|start| {
+                    self.documents
+                        .get(id)
+                        .is_some_and(|document| *start < document.title_len)
+                });
+                let weight = if in_title { 2.0 } else { 1.0 };
+                let score = (1.0 + (starts.len() as f32).ln()) * idf * weight;
+                match &scores {
+                    Some(scores) => Some((id, scores.get(id)? + score)),
+                    None => Some((id, score)),
+                }
+            });
+            scores = Some(clause_scores.collect());
+        }
+        let mut results: Vec<(ArticleUuid, f32)> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, score)| (id.clone(), score))
+            .collect();
+        results.sort_by(|(id, score), (other_id, other_score)| {
+            other_score.total_cmp(score).then_with(|| id.cmp(other_id))
+        });
+        results
+    }
+}
+
+/// Part of a text around the first match of a query, with the matches to highlight.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub text: String,
+    /// Byte ranges of the matches in the text.
+    pub highlights: Vec<Range<usize>>,
+}
+
+impl Snippet {
+    /// Takes about `max_chars` characters of the text around the first match of the query.
+    pub fn new(text: &str, query: &Query, max_chars: usize) -> Self {
+        const CONTEXT_CHARS: usize = 24;
+        let tokens = tokenize(text);
+        let matched: Vec<&Token> = tokens
+            .iter()
+            .filter(|token| query.matches_token(&token.text))
+            .collect();
+        let first = matched.first().map_or(0, |token| token.range.start);
+        let char_starts: Vec<usize> = text.char_indices().map(|(index, _)| index).collect();
+        let first_char = char_starts.partition_point(|index| *index < first);
+        let start = char_starts
+            .get(first_char.saturating_sub(CONTEXT_CHARS))
+            .copied()
+            .unwrap_or(0);
+        // Start at a word if it's cut off.
+        let start = match tokens.iter().find(|token| token.range.contains(&start)) {
+            Some(token) if token.range.start < start => token.range.end,
+            _ => start,
+        };
+        let start_char = char_starts.partition_point(|index| *index < start);
+        let end = char_starts
+            .get(start_char + max_chars)
+            .copied()
+            .unwrap_or(text.len());
+
+        let mut snippet = String::new();
+        if start > 0 {
+            snippet.push('…');
+        }
+        let offset = snippet.len();
+        snippet.push_str(text[start..end].trim_end());
+        let highlights = matched
+            .iter()
+            .filter(|token| token.range.start >= start && token.range.end <= end)
+            .map(|token| token.range.start - start + offset..token.range.end - start + offset)
+            .collect();
+        if end < text.len() {
+            snippet.push('…');
+        }
+        Self {
+            text: snippet,
+            highlights,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{tokenize, Query, SearchIndex, Snippet};
+    use crate::subscription::article::{Article, ArticleUuid};
+    use crate::subscription::feed::EntryUuid;
+    use uuid::Uuid;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0">
+<channel>
+  <title>Search</title>
+  <item>
+    <guid>rust</guid>
+    <title>Rust 1.74 released</title>
+    <description>&lt;p&gt;The Rust team is happy to announce a new version.&lt;/p&gt;</description>
+    <category>Programming</category>
+  </item>
+  <item>
+    <guid>rusty</guid>
+    <title>Rusty bikes</title>
+    <description>How to keep a bike from rusting, by the team.</description>
+  </item>
+  <item>
+    <guid>cjk</guid>
+    <title>中文搜索</title>
+    <description>全文搜索支持中文。</description>
+  </item>
+</channel>
+</rss>"#;
+
+    fn index() -> (SearchIndex, Vec<ArticleUuid>) {
+        let feed_id = EntryUuid::from(Uuid::new_v4());
+        let mut index = SearchIndex::default();
+        let mut ids = vec![];
+        let entries = feed_rs::parser::parse(RSS.as_bytes()).unwrap().entries;
+        let article_ids = ArticleUuid::from_entries(&feed_id, RSS.as_bytes(), &entries);
+        for (id, entry) in article_ids.into_iter().zip(entries) {
+            index.insert(&id, &Article::from(entry));
+            ids.push(id);
+        }
+        (index, ids)
+    }
+
+    fn search(index: &SearchIndex, query: &str) -> Vec<String> {
+        index
+            .search(&Query::parse(query))
+            .into_iter()
+            .map(|(id, _)| id.guid().to_owned())
+            .collect()
+    }
+
+    #[test]
+    fn tokens() {
+        let tokens: Vec<String> = tokenize("Hello, 世界! e-mail")
+            .into_iter()
+            .map(|token| token.text)
+            .collect();
+        assert_eq!(tokens, vec!["hello", "世", "界", "e", "mail"]);
+    }
+
+    #[test]
+    fn queries() {
+        let (mut index, ids) = index();
+        assert_eq!(search(&index, "RUST"), vec!["rust"]);
+        assert_eq!(search(&index, "rust*"), vec!["rust", "rusty"]);
+        assert_eq!(search(&index, "team rust*"), vec!["rust", "rusty"]);
+        assert_eq!(search(&index, "\"rust team\""), vec!["rust"]);
+        assert_eq!(search(&index, "\"team rust\""), Vec::<String>::new());
+        assert_eq!(search(&index, "programming"), vec!["rust"]);
+        // Phrases don't span fields.
+        assert_eq!(search(&index, "\"released the\""), Vec::<String>::new());
+        assert_eq!(search(&index, "中文"), vec!["cjk"]);
+        assert_eq!(search(&index, "文中"), Vec::<String>::new());
+
+        index.remove(&ids[0]);
+        assert_eq!(search(&index, "rust*"), vec!["rusty"]);
+        assert!(!index.terms.contains_key("programming"));
+    }
+
+    #[test]
+    fn snippets() {
+        let query = Query::parse("team");
+        let text = "The Rust team is happy to announce a new version.";
+        let snippet = Snippet::new(text, &query, 16);
+        assert_eq!(snippet.text, "The Rust team is…");
+        assert_eq!(&snippet.text[snippet.highlights[0].clone()], "team");
+
+        let text = format!("{} the team.", "word ".repeat(20));
+        let snippet = Snippet::new(&text, &query, 100);
+        assert!(snippet.text.starts_with('…'));
+        assert_eq!(&snippet.text[snippet.highlights[0].clone()], "team");
+    }
+}
@@ -0,0 +1,648 @@
+//! Storage of the library in a directory for the native build,
+//! so that neither the whole library is rewritten on each save,
+//! nor every article body is loaded into memory at startup.
+//!
+//! A library is a directory of:
+//! - `library.json`: entries and folders, rewritten on commits when changed;
+//! - `articles.log`: append-only log of articles without bodies, replayed at startup;
+//! - `bodies.log`: append-only log of article bodies in JSON, read on demand.
+//!
+//! The logs are compacted on opening when most of their records are stale,
+//! or when the library is upgraded from an older schema version,
+//! so that all records in them are of the version of the library.
+//! Compacted logs, with the upgraded library, are written aside and then renamed over
+//! the old ones, which is finished on the next opening if it's interrupted by a crash,
+//! so that the logs are never of another version than the library.
+use super::{digest, Changes, CommitStats, Loaded, Snapshot, Storage};
+use crate::schema;
+use crate::subscription::article::{Article, ArticleUuid, Body, BodySource};
+use crate::subscription::feed::Feed;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const LIBRARY_FILE: &str = "library.json";
+const ARTICLES_FILE: &str = "articles.log";
+const BODIES_FILE: &str = "bodies.log";
+/// Marks that the compacted logs are completely written aside, and to be renamed.
+const COMPACTED_FILE: &str = "compacted";
+/// Extension of the files written aside by compaction.
+const COMPACTED_EXTENSION: &str = "compacted";
+
+/// Bodies were written as raw summaries before this version, rather than in JSON.
+const JSON_BODY_VERSION: u32 = 2;
+
+/// Logs are compacted if there are more stale records than this, and than live ones.
+const COMPACT_THRESHOLD: usize = 1024;
+
+/// Where a body is in the body log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+struct BodyLocation {
+    offset: u64,
+    len: u64,
+    digest: u64,
+}
+
+/// Record in the article log, whose article is read as a JSON value to be upgraded.
+#[derive(Debug, Deserialize, Serialize)]
+enum Record<A = Box<Article>> {
+    /// Puts an article, whose body is left in the body log.
+    Put {
+        id: ArticleUuid,
+        article: A,
+        body: Option<BodyLocation>,
+    },
+    Delete {
+        id: ArticleUuid,
+    },
+}
+
+/// What is committed of an article.
+#[derive(Debug, Clone, Copy)]
+struct Committed {
+    digest: u64,
+    body: Option<BodyLocation>,
+}
+
+/// Library stored in a directory.
+#[derive(Debug)]
+pub struct FileStore {
+    dir: PathBuf,
+    committed: HashMap<ArticleUuid, Committed>,
+    library_digest: Option<u64>,
+    articles_log: File,
+    bodies_log: File,
+}
+
+impl FileStore {
+    /// Attempts to open the library in the directory, which is created if it doesn't exist.
+    pub fn try_open(dir: impl AsRef<Path>) -> Result<(Self, Loaded)> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create the library at {}", dir.display()))?;
+        Self::try_finish_compact(&dir)?;
+
+        let (feed, version, mut library_digest) = match fs::read(dir.join(LIBRARY_FILE)) {
+            Ok(bytes) => {
+                let (feed, version) = schema::LIBRARY.try_decode::<Feed>(&bytes)?;
+                (Some(feed), version, Some(digest(&bytes)))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                (None, schema::LIBRARY.version(), None)
+            }
+            Err(err) => return Err(err).context("Failed to read the library file"),
+        };
+
+        let (mut articles, records) = Self::try_replay(&dir.join(ARTICLES_FILE), version)?;
+        let upgraded = version < schema::LIBRARY.version();
+        if upgraded || (records > COMPACT_THRESHOLD && records > 2 * articles.len()) {
+            let library = match (upgraded, &feed) {
+                (true, Some(feed)) => Some(schema::LIBRARY.try_encode(feed)?),
+                _ => None,
+            };
+            Self::try_compact(&dir, &articles, version, library.as_deref())?;
+            if let Some(library) = library {
+                log::info!("Upgraded the library from schema version {version}");
+                library_digest = Some(digest(&library));
+            }
+            (articles, _) = Self::try_replay(&dir.join(ARTICLES_FILE), schema::LIBRARY.version())?;
+        }
+
+        let open = |name: &str| {
+            OpenOptions::new()
+                .read(true)
+                .append(true)
+                .create(true)
+                .open(dir.join(name))
+                .with_context(|| format!("Failed to open {name}"))
+        };
+        let store = FileStore {
+            committed: articles
+                .iter()
+                .map(|(id, (_, committed))| (id.clone(), *committed))
+                .collect(),
+            library_digest,
+            articles_log: open(ARTICLES_FILE)?,
+            bodies_log: open(BODIES_FILE)?,
+            dir,
+        };
+        let articles = articles
+            .into_iter()
+            .map(|(id, (mut article, committed))| {
+                if committed.body.is_some() {
+                    article.take_body();
+                }
+                (id, article)
+            })
+            .collect();
+        Ok((store, Loaded { feed, articles }))
+    }
+
+    /// Replays the article log of the schema version,
+    /// returns the live articles and the number of records.
+    #[allow(clippy::type_complexity)]
+    fn try_replay(
+        path: &Path,
+        version: u32,
+    ) -> Result<(HashMap<ArticleUuid, (Article, Committed)>, usize)> {
+        let mut articles = HashMap::new();
+        let mut records = 0;
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok((articles, records))
+            }
+            Err(err) => return Err(err).context("Failed to open the article log"),
+        };
+        for line in BufReader::new(file).lines() {
+            let line = line.context("Failed to read the article log")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Only the last record can be torn by a crash, which is dropped.
+            let Ok(record) = serde_json::from_str::<Record<serde_json::Value>>(&line) else {
+                log::warn!("Dropped a broken record in the article log");
+                continue;
+            };
+            records += 1;
+            match record {
+                Record::Put { id, article, body } => {
+                    let article: Article = schema::ARTICLE.try_decode_value(version, article)?;
+                    let digest = digest(serde_json::to_string(&article)?.as_bytes());
+                    articles.insert(id, (article, Committed { digest, body }));
+                }
+                Record::Delete { id } => {
+                    articles.remove(&id);
+                }
+            }
+        }
+        Ok((articles, records))
+    }
+
+    /// Attempts to decode a body in the body log of the schema version.
+    fn try_decode_body(version: u32, bytes: Vec<u8>) -> Result<Body> {
+        let value = if version < JSON_BODY_VERSION {
+            serde_json::Value::String(String::from_utf8(bytes)?)
+        } else {
+            serde_json::from_slice(&bytes).context("Invalid article body")?
+        };
+        schema::BODY.try_decode_value(version, value)
+    }
+
+    /// Returns the path of the file written aside by compaction.
+    fn compacted(dir: &Path, name: &str) -> PathBuf {
+        dir.join(format!("{name}.{COMPACTED_EXTENSION}"))
+    }
+
+    /// Rewrites the logs of the schema version with the live articles only,
+    /// upgrading bodies to the current version, along with the library if it's upgraded.
+    fn try_compact(
+        dir: &Path,
+        articles: &HashMap<ArticleUuid, (Article, Committed)>,
+        version: u32,
+        library: Option<&[u8]>,
+    ) -> Result<()> {
+        // Left by a compaction interrupted before it's completely written.
+        let _ = fs::remove_file(Self::compacted(dir, LIBRARY_FILE));
+        if let Some(library) = library {
+            let mut file = File::create(Self::compacted(dir, LIBRARY_FILE))?;
+            file.write_all(library)?;
+            file.sync_all()?;
+        }
+        let mut old_bodies = File::open(dir.join(BODIES_FILE)).ok();
+        let mut new_articles = BufWriter::new(File::create(Self::compacted(dir, ARTICLES_FILE))?);
+        let mut new_bodies = BufWriter::new(File::create(Self::compacted(dir, BODIES_FILE))?);
+        let mut offset = 0;
+        for (id, (article, committed)) in articles {
+            let body = match (committed.body, old_bodies.as_mut()) {
+                (Some(location), Some(old_bodies)) => {
+                    let mut bytes = vec![0; location.len as usize];
+                    old_bodies.seek(SeekFrom::Start(location.offset))?;
+                    old_bodies.read_exact(&mut bytes)?;
+                    if version < schema::BODY.version() {
+                        bytes = serde_json::to_vec(&Self::try_decode_body(version, bytes)?)?;
+                    }
+                    new_bodies.write_all(&bytes)?;
+                    offset += bytes.len() as u64;
+                    Some(BodyLocation {
+                        offset: offset - bytes.len() as u64,
+                        len: bytes.len() as u64,
+                        digest: digest(&bytes),
+                    })
+                }
+                _ => None,
+            };
+            let record = Record::Put {
+                id: id.clone(),
+                article: Box::new(article.clone()),
+                body,
+            };
+            writeln!(new_articles, "{}", serde_json::to_string(&record)?)?;
+        }
+        new_articles.into_inner()?.sync_all()?;
+        new_bodies.into_inner()?.sync_all()?;
+        File::create(dir.join(COMPACTED_FILE))?.sync_all()?;
+        Self::try_finish_compact(dir)
+    }
+
+    /// Renames the compacted logs and library over the old ones if they are completely written,
+    /// since none of them can be used with the others of another compaction.
+    fn try_finish_compact(dir: &Path) -> Result<()> {
+        if !dir.join(COMPACTED_FILE).exists() {
+            return Ok(());
+        }
+        for name in [ARTICLES_FILE, BODIES_FILE, LIBRARY_FILE] {
+            let compacted = Self::compacted(dir, name);
+            if compacted.exists() {
+                fs::rename(&compacted, dir.join(name))
+                    .with_context(|| format!("Failed to replace {name} by the compacted one"))?;
+            }
+        }
+        fs::remove_file(dir.join(COMPACTED_FILE))?;
+        Ok(())
+    }
+
+    /// Replaces the library file atomically.
+    fn try_write_library(dir: &Path, library: &[u8]) -> Result<()> {
+        let temp = dir.join("library.json.tmp");
+        fs::write(&temp, library)?;
+        fs::rename(&temp, dir.join(LIBRARY_FILE))?;
+        Ok(())
+    }
+
+    fn try_load_body(&mut self, id: &ArticleUuid) -> Result<Option<Body>> {
+        let Some(location) = self.committed.get(id).and_then(|committed| committed.body) else {
+            return Ok(None);
+        };
+        let mut bytes = vec![0; location.len as usize];
+        self.bodies_log.seek(SeekFrom::Start(location.offset))?;
+        self.bodies_log.read_exact(&mut bytes)?;
+        Ok(Some(Self::try_decode_body(schema::BODY.version(), bytes)?))
+    }
+
+    /// Writes what's changed since the last commit.
+    fn try_write(&mut self, library: Vec<u8>, changes: Changes) -> Result<CommitStats> {
+        let Changes { snapshots, removed } = changes;
+        let mut stats = CommitStats::default();
+        let mut articles_log = BufWriter::new(&self.articles_log);
+        let mut bodies_len = self.bodies_log.seek(SeekFrom::End(0))?;
+        let mut live = HashSet::new();
+        for Snapshot { id, article, body } in snapshots {
+            let serialized = serde_json::to_string(&article)?;
+            let digest = digest(serialized.as_bytes());
+            let committed = self.committed.get(&id).copied();
+            let body = match body {
+                // The body is not loaded, so it's not changed.
+                None => committed.and_then(|committed| committed.body),
+                Some(body) if body == Body::default() => None,
+                Some(body) => {
+                    let body = serde_json::to_vec(&body)?;
+                    let body_digest = self::digest(&body);
+                    match committed.and_then(|committed| committed.body) {
+                        Some(location) if location.digest == body_digest => Some(location),
+                        _ => {
+                            self.bodies_log.write_all(&body)?;
+                            bodies_len += body.len() as u64;
+                            Some(BodyLocation {
+                                offset: bodies_len - body.len() as u64,
+                                len: body.len() as u64,
+                                digest: body_digest,
+                            })
+                        }
+                    }
+                }
+            };
+            live.insert(id.clone());
+            if committed
+                .is_some_and(|committed| committed.digest == digest && committed.body == body)
+            {
+                continue;
+            }
+            let record = Record::Put {
+                id: id.clone(),
+                article: Box::new(article),
+                body,
+            };
+            writeln!(articles_log, "{}", serde_json::to_string(&record)?)?;
+            self.committed.insert(id, Committed { digest, body });
+            stats.written += 1;
+        }
+        let deleted: Vec<ArticleUuid> = match removed {
+            Some(removed) => removed,
+            None => self
+                .committed
+                .keys()
+                .filter(|id| !live.contains(*id))
+                .cloned()
+                .collect(),
+        };
+        for id in deleted {
+            if self.committed.remove(&id).is_none() {
+                continue;
+            }
+            writeln!(
+                articles_log,
+                "{}",
+                serde_json::to_string(&Record::<Box<Article>>::Delete { id })?
+            )?;
+            stats.deleted += 1;
+        }
+        articles_log.flush()?;
+        drop(articles_log);
+        self.bodies_log.flush()?;
+
+        // Entries are written after their articles,
+        // so that a crash in between leaves no entry with missing articles.
+        let library_digest = digest(&library);
+        if self.library_digest != Some(library_digest) {
+            Self::try_write_library(&self.dir, &library)?;
+            self.library_digest = Some(library_digest);
+        }
+        Ok(stats)
+    }
+}
+
+/// Shared handle of a [`FileStore`].
+#[derive(Debug, Clone)]
+pub struct SharedFileStore(Arc<Mutex<FileStore>>);
+
+impl SharedFileStore {
+    pub fn new(store: FileStore) -> Self {
+        SharedFileStore(Arc::new(Mutex::new(store)))
+    }
+}
+
+impl Storage for SharedFileStore {
+    fn try_write(&mut self, library: Vec<u8>, changes: Changes) -> Result<CommitStats> {
+        self.0.lock().unwrap().try_write(library, changes)
+    }
+
+    fn body_source(&self) -> Option<Arc<dyn BodySource>> {
+        Some(Arc::new(self.clone()))
+    }
+}
+
+impl BodySource for SharedFileStore {
+    fn try_load_body(&self, id: &ArticleUuid) -> Result<Option<Body>> {
+        self.0
+            .lock()
+            .unwrap()
+            .try_load_body(id)
+            .context("Failed to load the body of an article")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BodyLocation, FileStore, Record, SharedFileStore, COMPACT_THRESHOLD};
+    use crate::schema;
+    use crate::subscription::article::BodySource;
+    use crate::subscription::feed::{Entry, Feed};
+    use crate::subscription::fetch::{Fixture, FixtureFetcher};
+    use crate::subscription::storage::try_commit;
+    use chrono::{DateTime, Local, Utc};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use url::Url;
+    use uuid::Uuid;
+
+    const RSS_URL: &str = "https://rss.example.com/rss.xml";
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("rssucks-test-{}", Uuid::new_v4()))
+    }
+
+    fn synced_feed() -> Feed {
+        let fetcher = FixtureFetcher::new();
+        fetcher.serve(
+            RSS_URL,
+            Fixture::try_from_file("./fixtures/rss.xml").unwrap(),
+        );
+        let mut feed = Feed::default();
+        feed.set_fetcher(fetcher);
+        let id = feed.add_orphan_entry(Entry::new(Url::parse(RSS_URL).unwrap()));
+        feed.try_sync_entry_by_id(&id).unwrap();
+        feed
+    }
+
+    fn reopen(dir: &PathBuf) -> (SharedFileStore, Feed) {
+        let (store, loaded) = FileStore::try_open(dir).unwrap();
+        let store = SharedFileStore::new(store);
+        let mut feed = loaded.feed.unwrap();
+        feed.attach_articles(loaded.articles);
+        feed.set_body_source(Arc::new(store.clone()));
+        (store, feed)
+    }
+
+    #[test]
+    fn commit_and_reopen() {
+        let dir = temp_dir();
+        let (store, loaded) = FileStore::try_open(&dir).unwrap();
+        assert!(loaded.feed.is_none());
+        let mut store = SharedFileStore::new(store);
+        let feed = synced_feed();
+        let stats = try_commit(&mut store, &feed).unwrap();
+        assert_eq!((stats.written, stats.deleted), (3, 0));
+        // Nothing is changed.
+        assert_eq!(try_commit(&mut store, &feed).unwrap().written, 0);
+        drop(store);
+
+        let (mut store, feed) = reopen(&dir);
+        let article_ids = feed.get_all_article_ids();
+        assert_eq!(article_ids.len(), 3);
+        let article = feed.try_get_article_by_id(&article_ids[0]).unwrap();
+        assert!(!article.lock().unwrap().is_body_loaded());
+        feed.try_load_article_body(&article_ids[0]).unwrap();
+        assert!(article.lock().unwrap().summary.is_some());
+
+        // Only changed articles are written.
+        feed.try_mark_article_read(&article_ids[0]).unwrap();
+        let stats = try_commit(&mut store, &feed).unwrap();
+        assert_eq!((stats.written, stats.deleted), (1, 0));
+        drop(store);
+
+        let (_, feed) = reopen(&dir);
+        let article = feed.try_get_article_by_id(&article_ids[0]).unwrap();
+        assert!(!article.lock().unwrap().unread);
+        fs_cleanup(&dir);
+    }
+
+    #[test]
+    fn unreadable_body() {
+        let dir = temp_dir();
+        let (store, _) = FileStore::try_open(&dir).unwrap();
+        let mut store = SharedFileStore::new(store);
+        try_commit(&mut store, &synced_feed()).unwrap();
+        drop(store);
+
+        let (mut store, feed) = reopen(&dir);
+        std::fs::write(dir.join(super::BODIES_FILE), "").unwrap();
+        let id = &feed.get_all_article_ids()[0];
+        assert!(feed.try_load_article_body(id).is_err());
+        assert!(feed.try_get_article_body(id).is_err());
+        let article = feed.try_get_article_by_id(id).unwrap();
+        assert!(!article.lock().unwrap().is_body_loaded());
+        // The body is still where it was, rather than dropped for an empty one.
+        assert_eq!(try_commit(&mut store, &feed).unwrap().written, 0);
+        fs_cleanup(&dir);
+    }
+
+    #[test]
+    fn delete_and_compact() {
+        let dir = temp_dir();
+        let (store, _) = FileStore::try_open(&dir).unwrap();
+        let mut store = SharedFileStore::new(store);
+        let feed = synced_feed();
+        try_commit(&mut store, &feed).unwrap();
+        let article_ids = feed.get_all_article_ids();
+        let kept = feed.try_get_article_by_id(&article_ids[1]).unwrap();
+        for round in 0..=COMPACT_THRESHOLD {
+            kept.lock().unwrap().title = format!("Title {round}");
+            feed.mark_articles_changed([article_ids[1].clone()]);
+            try_commit(&mut store, &feed).unwrap();
+        }
+        feed.get_articles_map()
+            .lock()
+            .unwrap()
+            .remove(&article_ids[0]);
+        feed.mark_articles_changed([article_ids[0].clone()]);
+        let stats = try_commit(&mut store, &feed).unwrap();
+        assert_eq!((stats.written, stats.deleted), (0, 1));
+        drop(store);
+
+        let (store, feed) = reopen(&dir);
+        assert_eq!(feed.get_all_article_ids().len(), 2);
+        let log = std::fs::read_to_string(dir.join(super::ARTICLES_FILE)).unwrap();
+        assert_eq!(log.lines().count(), 2);
+        let kept = feed.try_get_article_by_id(&article_ids[1]).unwrap();
+        assert_eq!(
+            kept.lock().unwrap().title,
+            format!("Title {COMPACT_THRESHOLD}")
+        );
+        assert!(store.try_load_body(&article_ids[1]).unwrap().is_some());
+        fs_cleanup(&dir);
+    }
+
+    #[test]
+    fn finish_interrupted_compact() {
+        let dir = temp_dir();
+        let (store, _) = FileStore::try_open(&dir).unwrap();
+        let mut store = SharedFileStore::new(store);
+        try_commit(&mut store, &synced_feed()).unwrap();
+        drop(store);
+        // Crashed after the articles log is replaced, but before the bodies log is.
+        let bodies = std::fs::read(dir.join(super::BODIES_FILE)).unwrap();
+        std::fs::write(FileStore::compacted(&dir, super::BODIES_FILE), bodies).unwrap();
+        std::fs::write(dir.join(super::BODIES_FILE), "stale").unwrap();
+        std::fs::write(dir.join(super::COMPACTED_FILE), "").unwrap();
+
+        let (store, feed) = reopen(&dir);
+        assert!(!dir.join(super::COMPACTED_FILE).exists());
+        for id in feed.get_all_article_ids() {
+            assert!(store.try_load_body(&id).unwrap().is_some());
+        }
+        fs_cleanup(&dir);
+    }
+
+    #[test]
+    fn upgrade_unversioned() {
+        let dir = temp_dir();
+        std::fs::create_dir_all(&dir).unwrap();
+        let feed = synced_feed();
+        // Saved before the schema was versioned, when links were plain URLs,
+        // dates were formatted in the local timezone and bodies were raw summaries.
+        std::fs::write(
+            dir.join(super::LIBRARY_FILE),
+            serde_json::to_vec(&feed).unwrap(),
+        )
+        .unwrap();
+        let (mut articles_log, mut bodies_log) = (String::new(), String::new());
+        for id in feed.get_all_article_ids() {
+            let mut article = feed
+                .try_get_article_by_id(&id)
+                .unwrap()
+                .lock()
+                .unwrap()
+                .clone();
+            let summary = article.take_body().summary.unwrap();
+            let mut article = serde_json::to_value(article).unwrap();
+            article["links"] = article["links"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|link| link["href"].clone())
+                .collect();
+            let local = |date: &serde_json::Value| {
+                serde_json::from_value::<Option<DateTime<Utc>>>(date.clone())
+                    .unwrap()
+                    .map(|date| {
+                        date.with_timezone(&Local)
+                            .format("%Y/%m/%d %H:%M")
+                            .to_string()
+                    })
+            };
+            article["date"] = match &article["updated"] {
+                serde_json::Value::Null => article["published"].clone(),
+                updated => updated.clone(),
+            };
+            article["updated"] = serde_json::json!(local(&article["updated"]));
+            article["published"] = serde_json::json!(local(&article["published"]));
+            article.as_object_mut().unwrap().remove("first_seen");
+            let record = Record::Put {
+                id,
+                article,
+                body: Some(BodyLocation {
+                    offset: bodies_log.len() as u64,
+                    len: summary.len() as u64,
+                    digest: super::digest(summary.as_bytes()),
+                }),
+            };
+            bodies_log.push_str(&summary);
+            articles_log.push_str(&serde_json::to_string(&record).unwrap());
+            articles_log.push('\n');
+        }
+        std::fs::write(dir.join(super::ARTICLES_FILE), articles_log).unwrap();
+        std::fs::write(dir.join(super::BODIES_FILE), bodies_log).unwrap();
+
+        let (store, upgraded) = reopen(&dir);
+        let article_ids = upgraded.get_all_article_ids();
+        assert_eq!(article_ids.len(), 3);
+        for id in &article_ids {
+            let article = upgraded.try_get_article_by_id(id).unwrap();
+            let expected = feed.try_get_article_by_id(id).unwrap();
+            let (article, expected) = (article.lock().unwrap(), expected.lock().unwrap());
+            assert_eq!(article.links, expected.links);
+            assert_eq!(article.updated, expected.updated);
+            assert_eq!(article.published, expected.published);
+            assert_eq!(
+                store.try_load_body(id).unwrap().unwrap().summary,
+                expected.summary
+            );
+        }
+        let library = std::fs::read(dir.join(super::LIBRARY_FILE)).unwrap();
+        let library: serde_json::Value = serde_json::from_slice(&library).unwrap();
+        assert_eq!(library["version"], schema::LIBRARY.version());
+        drop(store);
+
+        // Not upgraded again.
+        let (_, reopened) = reopen(&dir);
+        for id in &article_ids {
+            let article = reopened.try_get_article_by_id(id).unwrap();
+            let expected = feed.try_get_article_by_id(id).unwrap();
+            assert_eq!(
+                article.lock().unwrap().updated,
+                expected.lock().unwrap().updated
+            );
+        }
+        assert!(!FileStore::compacted(&dir, super::LIBRARY_FILE).exists());
+        fs_cleanup(&dir);
+    }
+
+    fn fs_cleanup(dir: &PathBuf) {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,345 @@
+//! Storage of the library in IndexedDB of the browser for the web build,
+//! which isn't limited to a few megabytes like the local storage.
+//!
+//! The database has an object store `library` for entries and folders,
+//! and an object store `articles` for articles with their bodies, keyed by their IDs.
+//! Bodies stay in memory once loaded, since IndexedDB can't be read synchronously.
+//! Articles are of the schema version of the library,
+//! and all of them are rewritten when the library is upgraded.
+use super::{digest, Changes, CommitStats, Loaded, Snapshot, Storage};
+use crate::schema;
+use crate::subscription::article::{Article, ArticleUuid, Body};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{IdbDatabase, IdbRequest, IdbTransaction, IdbTransactionMode};
+
+const VERSION: u32 = 1;
+const LIBRARY_STORE: &str = "library";
+const ARTICLES_STORE: &str = "articles";
+const LIBRARY_KEY: &str = "library";
+
+/// Value in the object store of articles,
+/// whose article and body are read as JSON values to be upgraded.
+#[derive(Deserialize, Serialize)]
+struct StoredArticle<A = Article, B = Body> {
+    id: ArticleUuid,
+    article: A,
+    body: Option<B>,
+}
+
+fn js_error(value: JsValue) -> anyhow::Error {
+    anyhow!("{value:?}")
+}
+
+fn store_names() -> JsValue {
+    js_sys::Array::of2(&LIBRARY_STORE.into(), &ARTICLES_STORE.into()).into()
+}
+
+type OnOpened = Box<dyn FnOnce(Result<(IndexedDbStore, Loaded)>)>;
+
+/// Callback of opening, which is called once, either on success or on error.
+#[derive(Clone)]
+struct Opening(Rc<RefCell<Option<OnOpened>>>);
+
+impl Opening {
+    fn finish(&self, result: Result<(IndexedDbStore, Loaded)>) {
+        if let Some(on_done) = self.0.borrow_mut().take() {
+            on_done(result);
+        }
+    }
+
+    fn on_error(&self, message: &'static str) -> JsValue {
+        let opening = self.clone();
+        Closure::once_into_js(move |event: web_sys::Event| {
+            opening.finish(Err(anyhow!("{message}: {:?}", event.type_())))
+        })
+    }
+}
+
+/// What's stored in the database, which is updated once a transaction writing it completes.
+#[derive(Debug, Default)]
+struct Committed {
+    /// Digests of the stored articles.
+    articles: HashMap<ArticleUuid, u64>,
+    library_digest: Option<u64>,
+    /// Whether a transaction failed, after which all articles are written again.
+    failed: bool,
+}
+
+/// What a transaction writes, to be committed once it completes.
+#[derive(Default)]
+struct Pending {
+    written: Vec<(ArticleUuid, u64)>,
+    deleted: Vec<ArticleUuid>,
+    library_digest: Option<u64>,
+}
+
+/// Library stored in an IndexedDB database.
+#[derive(Debug)]
+pub struct IndexedDbStore {
+    db: IdbDatabase,
+    committed: Rc<RefCell<Committed>>,
+}
+
+impl IndexedDbStore {
+    /// Opens the database, which is created if it doesn't exist,
+    /// and calls back with the store and the library loaded from it.
+    pub fn open(name: &str, on_done: impl FnOnce(Result<(Self, Loaded)>) + 'static) {
+        let opening = Opening(Rc::new(RefCell::new(Some(Box::new(on_done)))));
+        if let Err(err) = Self::try_open(name, opening.clone()) {
+            opening.finish(Err(err));
+        }
+    }
+
+    fn try_open(name: &str, opening: Opening) -> Result<()> {
+        let factory = web_sys::window()
+            .context("No window")?
+            .indexed_db()
+            .map_err(js_error)?
+            .context("IndexedDB is not available")?;
+        let request = factory.open_with_u32(name, VERSION).map_err(js_error)?;
+
+        let upgrading = request.clone();
+        let on_upgrade_needed = Closure::once_into_js(move |_: web_sys::Event| {
+            let Ok(db) = upgrading.result() else {
+                return;
+            };
+            let db: IdbDatabase = db.unchecked_into();
+            for name in [LIBRARY_STORE, ARTICLES_STORE] {
+                if let Err(err) = db.create_object_store(name) {
+                    log::error!("Failed to create object store {name}: {err:?}");
+                }
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade_needed.unchecked_ref()));
+
+        let opened = request.clone();
+        let on_error = opening.on_error("Failed to open the database");
+        let on_success = Closure::once_into_js(move |_: web_sys::Event| {
+            let result = opened
+                .result()
+                .map_err(js_error)
+                .and_then(|db| Self::try_load(db.unchecked_into(), opening.clone()));
+            if let Err(err) = result {
+                opening.finish(Err(err));
+            }
+        });
+        request.set_onsuccess(Some(on_success.unchecked_ref()));
+        request.set_onerror(Some(on_error.unchecked_ref()));
+        Ok(())
+    }
+
+    fn try_load(db: IdbDatabase, opening: Opening) -> Result<()> {
+        let transaction = db
+            .transaction_with_str_sequence(&store_names())
+            .map_err(js_error)?;
+        let library = transaction
+            .object_store(LIBRARY_STORE)
+            .and_then(|store| store.get(&LIBRARY_KEY.into()))
+            .map_err(js_error)?;
+        let articles = transaction
+            .object_store(ARTICLES_STORE)
+            .and_then(|store| store.get_all())
+            .map_err(js_error)?;
+
+        let on_error = opening.on_error("Failed to load the library");
+        let on_complete = Closure::once_into_js(move |_: web_sys::Event| {
+            opening.finish(Self::try_parse(db, &library, &articles));
+        });
+        transaction.set_oncomplete(Some(on_complete.unchecked_ref()));
+        transaction.set_onerror(Some(on_error.unchecked_ref()));
+        Ok(())
+    }
+
+    fn try_parse(
+        db: IdbDatabase,
+        library: &IdbRequest,
+        articles: &IdbRequest,
+    ) -> Result<(Self, Loaded)> {
+        let library = library.result().map_err(js_error)?.as_string();
+        let (feed, version) = match &library {
+            Some(library) => {
+                let (feed, version) = schema::LIBRARY.try_decode(library.as_bytes())?;
+                (Some(feed), version)
+            }
+            None => (None, schema::LIBRARY.version()),
+        };
+        // Everything is rewritten by the next commit if the library is upgraded.
+        let upgraded = version < schema::LIBRARY.version();
+        let mut committed = Committed {
+            library_digest: library
+                .filter(|_| !upgraded)
+                .map(|library| digest(library.as_bytes())),
+            ..Default::default()
+        };
+        let mut loaded = vec![];
+        let articles: js_sys::Array = articles.result().map_err(js_error)?.unchecked_into();
+        for value in articles.iter() {
+            let Some(value) = value.as_string() else {
+                continue;
+            };
+            let Ok(StoredArticle { id, article, body }) =
+                serde_json::from_str::<StoredArticle<serde_json::Value, serde_json::Value>>(&value)
+            else {
+                log::warn!("Dropped a broken article in the database");
+                continue;
+            };
+            let mut article: Article = schema::ARTICLE.try_decode_value(version, article)?;
+            let body = match body {
+                Some(body) => schema::BODY.try_decode_value(version, body)?,
+                None => Body::default(),
+            };
+            if !upgraded {
+                committed
+                    .articles
+                    .insert(id.clone(), digest(value.as_bytes()));
+            }
+            article.set_body(body);
+            loaded.push((id, article));
+        }
+        let store = IndexedDbStore {
+            db,
+            committed: Rc::new(RefCell::new(committed)),
+        };
+        Ok((
+            store,
+            Loaded {
+                feed,
+                articles: loaded,
+            },
+        ))
+    }
+}
+
+impl IndexedDbStore {
+    /// Puts the changes into the transaction, returns what's to be committed.
+    fn try_put(
+        &self,
+        transaction: &IdbTransaction,
+        library: Vec<u8>,
+        changes: Changes,
+    ) -> Result<(Pending, CommitStats)> {
+        let Changes { snapshots, removed } = changes;
+        let committed = self.committed.borrow();
+        let articles = transaction.object_store(ARTICLES_STORE).map_err(js_error)?;
+        let mut pending = Pending::default();
+        let mut stats = CommitStats::default();
+        let mut live = HashSet::new();
+        for Snapshot { id, article, body } in snapshots {
+            let key = serde_json::to_string(&id)?;
+            // Bodies are never left in this storage, so they are always loaded.
+            let value = serde_json::to_string(&StoredArticle {
+                id: id.clone(),
+                article,
+                body: body.filter(|body| *body != Body::default()),
+            })?;
+            let digest = digest(value.as_bytes());
+            live.insert(id.clone());
+            if committed.articles.get(&id) == Some(&digest) {
+                continue;
+            }
+            articles
+                .put_with_key(&value.into(), &key.into())
+                .map_err(js_error)?;
+            pending.written.push((id, digest));
+            stats.written += 1;
+        }
+        let deleted: Vec<ArticleUuid> = match removed {
+            Some(removed) => removed,
+            None => committed
+                .articles
+                .keys()
+                .filter(|id| !live.contains(*id))
+                .cloned()
+                .collect(),
+        };
+        for id in deleted {
+            if !committed.articles.contains_key(&id) {
+                continue;
+            }
+            articles
+                .delete(&serde_json::to_string(&id)?.into())
+                .map_err(js_error)?;
+            pending.deleted.push(id);
+            stats.deleted += 1;
+        }
+
+        let library_digest = digest(&library);
+        if committed.library_digest != Some(library_digest) {
+            transaction
+                .object_store(LIBRARY_STORE)
+                .and_then(|store| {
+                    store.put_with_key(
+                        &String::from_utf8_lossy(&library).as_ref().into(),
+                        &LIBRARY_KEY.into(),
+                    )
+                })
+                .map_err(js_error)?;
+            pending.library_digest = Some(library_digest);
+        }
+        Ok((pending, stats))
+    }
+}
+
+impl Storage for IndexedDbStore {
+    fn try_write(&mut self, library: Vec<u8>, changes: Changes) -> Result<CommitStats> {
+        let transaction = self
+            .db
+            .transaction_with_str_sequence_and_mode(&store_names(), IdbTransactionMode::Readwrite)
+            .map_err(js_error)?;
+        if changes.removed.is_none() {
+            self.committed.borrow_mut().failed = false;
+        }
+        let (pending, stats) = match self.try_put(&transaction, library, changes) {
+            Ok(put) => put,
+            Err(err) => {
+                let _ = transaction.abort();
+                return Err(err);
+            }
+        };
+
+        // The transaction is committed in the background,
+        // and what it writes is known to be stored only once it completes.
+        let pending = Rc::new(RefCell::new(Some(pending)));
+        let (committed, completed) = (Rc::clone(&self.committed), Rc::clone(&pending));
+        let on_complete = Closure::once_into_js(move |_: web_sys::Event| {
+            let Some(pending) = completed.borrow_mut().take() else {
+                return;
+            };
+            let mut committed = committed.borrow_mut();
+            committed.articles.extend(pending.written);
+            for id in pending.deleted {
+                committed.articles.remove(&id);
+            }
+            if let Some(library_digest) = pending.library_digest {
+                committed.library_digest = Some(library_digest);
+            }
+        });
+        // Aborted on any error, when what it writes may or may not be stored,
+        // so that all of it is written again.
+        let committed = Rc::clone(&self.committed);
+        let on_abort = Closure::once_into_js(move |event: web_sys::Event| {
+            log::error!("Failed to commit the library: {:?}", event.type_());
+            let Some(pending) = pending.borrow_mut().take() else {
+                return;
+            };
+            let mut committed = committed.borrow_mut();
+            for (id, _) in pending.written {
+                committed.articles.remove(&id);
+            }
+            committed.library_digest = None;
+            committed.failed = true;
+        });
+        transaction.set_oncomplete(Some(on_complete.unchecked_ref()));
+        transaction.set_onabort(Some(on_abort.unchecked_ref()));
+        Ok(stats)
+    }
+
+    fn needs_all_articles(&self) -> bool {
+        self.committed.borrow().failed
+    }
+}
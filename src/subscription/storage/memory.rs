@@ -0,0 +1,157 @@
+//! Storage of the library in memory, which is reopened by tests as if it were on disk.
+use super::{Changes, CommitStats, Loaded, Snapshot, Storage};
+use crate::schema;
+use crate::subscription::article::{Article, ArticleUuid, Body};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default)]
+struct Committed {
+    library: Option<Vec<u8>>,
+    /// Serialized articles with their bodies.
+    articles: HashMap<ArticleUuid, (String, Body)>,
+}
+
+/// Library stored in memory, shared by its clones.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryStore(Arc<Mutex<Committed>>);
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attempts to load what's committed, with article bodies.
+    pub fn try_load(&self) -> Result<Loaded> {
+        let committed = self.0.lock().unwrap();
+        let feed = match &committed.library {
+            Some(library) => Some(schema::LIBRARY.try_decode(library)?.0),
+            None => None,
+        };
+        let mut articles = vec![];
+        for (id, (article, body)) in &committed.articles {
+            let mut article: Article = serde_json::from_str(article)?;
+            article.set_body(body.clone());
+            articles.push((id.clone(), article));
+        }
+        Ok(Loaded { feed, articles })
+    }
+}
+
+impl Storage for MemoryStore {
+    fn try_write(&mut self, library: Vec<u8>, changes: Changes) -> Result<CommitStats> {
+        let mut committed = self.0.lock().unwrap();
+        let mut stats = CommitStats::default();
+        let mut articles = HashMap::new();
+        for Snapshot { id, article, body } in changes.snapshots {
+            let article = serde_json::to_string(&article)?;
+            let former = committed.articles.remove(&id);
+            let body = match (body, &former) {
+                (Some(body), _) => body,
+                (None, Some((_, body))) => body.clone(),
+                (None, None) => Body::default(),
+            };
+            if former.as_ref() != Some(&(article.clone(), body.clone())) {
+                stats.written += 1;
+            }
+            articles.insert(id, (article, body));
+        }
+        match changes.removed {
+            Some(removed) => {
+                for id in removed {
+                    stats.deleted += committed.articles.remove(&id).is_some() as usize;
+                }
+                committed.articles.extend(articles);
+            }
+            None => {
+                stats.deleted = committed.articles.len();
+                committed.articles = articles;
+            }
+        }
+        committed.library = Some(library);
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MemoryStore;
+    use crate::subscription::fetch::{Fixture, FixtureFetcher};
+    use crate::subscription::storage::Loaded;
+    use crate::utils::rss_client_ng::RssClient;
+    use url::Url;
+
+    #[test]
+    fn commit_and_reload() {
+        let fetcher = FixtureFetcher::new();
+        fetcher.serve(
+            "https://rss.example.com/rss.xml",
+            Fixture::try_from_file("./fixtures/rss.xml").unwrap(),
+        );
+        let store = MemoryStore::new();
+        let client = RssClient::default();
+        client
+            .try_open_storage(Box::new(store.clone()), Loaded::default(), || None)
+            .unwrap();
+        client.set_fetcher(fetcher);
+        let id = client.create_entry(
+            Url::parse("https://rss.example.com/rss.xml").unwrap(),
+            None::<String>,
+        );
+        client.try_start_sync_entry(id).unwrap();
+        while client.pump_sync_queue() {}
+
+        // Committed after the entry is synchronized.
+        let loaded = store.try_load().unwrap();
+        assert_eq!(loaded.articles.len(), 3);
+        assert_eq!(loaded.feed.unwrap().get_all_entry_ids(), vec![id.get()]);
+
+        let reloaded = RssClient::default();
+        reloaded
+            .try_open_storage(Box::new(store.clone()), store.try_load().unwrap(), || None)
+            .unwrap();
+        assert_eq!(reloaded.get().borrow().get_all_article_ids().len(), 3);
+
+        client.delete_entry(id);
+        client.try_commit().unwrap();
+        assert!(store.try_load().unwrap().articles.is_empty());
+    }
+
+    #[test]
+    fn open_after_changes() {
+        let store = MemoryStore::new();
+        let client = RssClient::default();
+        client
+            .try_open_storage(Box::new(store.clone()), Loaded::default(), || None)
+            .unwrap();
+        client.create_entry(
+            Url::parse("https://rss.example.com/rss.xml").unwrap(),
+            None::<String>,
+        );
+        client.try_commit().unwrap();
+
+        // Opened in the background, after an entry is added in the meantime.
+        let reloaded = RssClient::default();
+        let id = reloaded.create_entry(
+            Url::parse("https://atom.example.com/atom.xml").unwrap(),
+            None::<String>,
+        );
+        reloaded
+            .try_open_storage(Box::new(store.clone()), store.try_load().unwrap(), || None)
+            .unwrap();
+        let feed = reloaded.get();
+        assert_eq!(feed.borrow().get_all_entry_ids().len(), 2);
+        assert!(feed.borrow().try_get_entry_by_id(&id.get()).is_ok());
+        assert_eq!(
+            store
+                .try_load()
+                .unwrap()
+                .feed
+                .unwrap()
+                .get_all_entry_ids()
+                .len(),
+            2
+        );
+    }
+}
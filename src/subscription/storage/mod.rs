@@ -0,0 +1,132 @@
+//! Storages of the library, which keep entries, folders and articles apart,
+//! and are written incrementally by commits.
+//!
+//! - [`file::FileStore`] keeps the library in a directory, for the native build;
+//! - [`indexed_db::IndexedDbStore`] keeps it in IndexedDB of the browser, for the web build;
+//! - [`memory::MemoryStore`] keeps it in memory, for tests.
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file;
+#[cfg(target_arch = "wasm32")]
+pub mod indexed_db;
+#[cfg(test)]
+pub mod memory;
+
+use super::article::{Article, ArticleUuid, Body, BodySource};
+use super::feed::Feed;
+use crate::schema;
+use anyhow::Result;
+use std::sync::Arc;
+
+/// FNV-1a, which unlike the hasher of the standard library is stable across builds.
+pub(crate) fn digest(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Article to commit, without its body.
+#[derive(Debug)]
+pub struct Snapshot {
+    pub id: ArticleUuid,
+    pub article: Article,
+    /// The body, if it's loaded, otherwise it's not changed since it was loaded.
+    pub body: Option<Body>,
+}
+
+/// Articles to commit.
+#[derive(Debug, Default)]
+pub struct Changes {
+    /// Snapshots of the articles added or changed.
+    pub snapshots: Vec<Snapshot>,
+    /// Articles removed, or `None` if the snapshots are of all articles,
+    /// when the articles missing from them are removed.
+    pub removed: Option<Vec<ArticleUuid>>,
+}
+
+/// Result of a commit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CommitStats {
+    /// Number of articles written.
+    pub written: usize,
+    /// Number of articles deleted.
+    pub deleted: usize,
+}
+
+/// Library loaded from a storage.
+#[derive(Debug, Default)]
+pub struct Loaded {
+    /// Entries and folders, `None` if nothing has been committed yet.
+    pub feed: Option<Feed>,
+    /// Articles, whose bodies may be left in the storage.
+    pub articles: Vec<(ArticleUuid, Article)>,
+}
+
+/// Storage of the library.
+pub trait Storage {
+    /// Attempts to write the library, which is the [`Feed`] without articles
+    /// encoded by [`schema::LIBRARY`], and the articles changed since the last commit.
+    fn try_write(&mut self, library: Vec<u8>, changes: Changes) -> Result<CommitStats>;
+
+    /// Whether all articles are to be written by the next commit, since what's stored of them
+    /// is not known, e.g. after writing them failed in the background.
+    fn needs_all_articles(&self) -> bool {
+        false
+    }
+
+    /// Source of the bodies of articles, if they are left in the storage when loaded.
+    fn body_source(&self) -> Option<Arc<dyn BodySource>> {
+        None
+    }
+}
+
+/// Attempts to commit the library to the storage incrementally,
+/// where only the articles changed since the last commit are written.
+pub fn try_commit(storage: &mut dyn Storage, feed: &Feed) -> Result<CommitStats> {
+    let library = schema::LIBRARY.try_encode(feed)?;
+    let mut changed = feed.take_changed_articles();
+    changed.all |= storage.needs_all_articles();
+    // Take snapshots before the storage is locked by writing,
+    // since articles are locked before the storage when loading bodies.
+    let articles: Vec<_> = {
+        let article_map = feed.get_articles_map();
+        let article_map = article_map
+            .lock()
+            .expect("Failed to get the lock on article map");
+        if changed.all {
+            article_map
+                .iter()
+                .map(|(id, article)| (id.clone(), Some(Arc::clone(article))))
+                .collect()
+        } else {
+            changed
+                .ids
+                .iter()
+                .map(|id| (id.clone(), article_map.get(id).map(Arc::clone)))
+                .collect()
+        }
+    };
+    let mut changes = Changes {
+        snapshots: vec![],
+        removed: (!changed.all).then_some(vec![]),
+    };
+    for (id, article) in articles {
+        let Some(article) = article else {
+            if let Some(removed) = &mut changes.removed {
+                removed.push(id);
+            }
+            continue;
+        };
+        let mut article = article.lock().unwrap().clone();
+        let loaded = article.is_body_loaded();
+        let body = article.take_body();
+        changes.snapshots.push(Snapshot {
+            id,
+            article,
+            body: loaded.then_some(body),
+        });
+    }
+    storage.try_write(library, changes).map_err(|err| {
+        feed.restore_changed_articles(changed);
+        err
+    })
+}
@@ -0,0 +1,51 @@
+//! Tags which the reader puts on articles, apart from the categories supplied by feeds.
+use serde::{Deserialize, Serialize};
+
+/// Colors given to tags which are not colored by the reader, picked by their names.
+const PALETTE: [TagColor; 8] = [
+    TagColor([0xe5, 0x48, 0x4d]),
+    TagColor([0xf7, 0x6b, 0x15]),
+    TagColor([0xff, 0xc5, 0x3d]),
+    TagColor([0x46, 0xa7, 0x58]),
+    TagColor([0x12, 0xa5, 0x94]),
+    TagColor([0x00, 0x90, 0xff]),
+    TagColor([0x6e, 0x56, 0xcf]),
+    TagColor([0xd6, 0x40, 0x9f]),
+];
+
+/// Color of a tag in RGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TagColor(pub [u8; 3]);
+
+impl TagColor {
+    /// Returns the color of a tag not colored by the reader, which is the same for the name.
+    pub fn default_for(name: &str) -> Self {
+        // FNV-1a, which is stable across builds unlike the hasher of std.
+        let hash = name.bytes().fold(0x811c9dc5u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        });
+        PALETTE[hash as usize % PALETTE.len()]
+    }
+}
+
+/// Normalizes the name of a tag typed by the reader, by trimming it
+/// and collapsing whitespace. Returns `None` if it's blank.
+pub fn normalize(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{normalize, TagColor};
+
+    #[test]
+    fn names_and_colors() {
+        assert_eq!(
+            normalize("  weekly \t review "),
+            Some("weekly review".to_owned())
+        );
+        assert_eq!(normalize(" \n "), None);
+        assert_eq!(TagColor::default_for("周报"), TagColor::default_for("周报"));
+    }
+}
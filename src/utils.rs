@@ -279,9 +279,11 @@
 
 pub mod rss_client_ng {
     use anyhow::Result;
+    use chrono::{DateTime, Utc};
     use serde::{Deserialize, Serialize};
     use std::{
         cell::RefCell,
+        collections::VecDeque,
         rc::Rc,
         sync::{Arc, Mutex},
     };
@@ -289,8 +291,17 @@ pub mod rss_client_ng {
     use uuid::Uuid;
 
     use crate::subscription::{
-        article::{self, ArticleUuid},
-        feed::{self, EntryUuid, FolderUuid},
+        article::{self, ArticleUuid, Flag},
+        discovery::Discovery,
+        feed::{self, EntryUuid, FolderUuid, SmartFolderUuid, SyncRecord},
+        fetch::Fetcher,
+        order::SortMode,
+        retention::{Retention, RetentionPolicy},
+        rule::Rule,
+        schedule::RefreshHints,
+        search::{Query, SearchFilter},
+        storage::{self, Loaded, Storage},
+        tag::TagColor,
     };
 
     #[derive(Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
@@ -309,6 +320,10 @@ pub mod rss_client_ng {
     }
 
     impl FolderId {
+        pub fn get(&self) -> FolderUuid {
+            self.0
+        }
+
         pub fn new() -> Self {
             Self::from(Uuid::new_v4())
         }
@@ -320,6 +335,15 @@ pub mod rss_client_ng {
         }
     }
 
+    #[derive(Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+    pub struct SmartFolderId(SmartFolderUuid);
+
+    impl From<SmartFolderUuid> for SmartFolderId {
+        fn from(value: SmartFolderUuid) -> Self {
+            Self(value)
+        }
+    }
+
     #[derive(Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
     pub struct EntryId(EntryUuid);
 
@@ -379,6 +403,11 @@ pub mod rss_client_ng {
         pub fn name(&self) -> String {
             self.folder.borrow().title().to_owned()
         }
+
+        /// Returns the folder which this folder is inside, if any.
+        pub fn parent(&self) -> Option<FolderId> {
+            self.folder.borrow().parent().map(FolderId::from)
+        }
     }
 
     #[derive(Serialize, Deserialize)]
@@ -404,6 +433,11 @@ pub mod rss_client_ng {
         pub fn get_name(&self) -> String {
             self.entry.borrow().title().to_owned()
         }
+
+        /// Returns the folder to which this subscription belongs, if any.
+        pub fn folder(&self) -> Option<FolderId> {
+            self.entry.borrow().belonging().map(FolderId::from)
+        }
     }
 
     pub struct Article {
@@ -424,15 +458,109 @@ pub mod rss_client_ng {
         }
     }
 
+    /// What the user is looking at, whose entries are synchronized first.
+    #[derive(Clone, Copy, Eq, PartialEq)]
+    pub enum SyncFocus {
+        Entry(EntryId),
+        Folder(FolderId),
+    }
+
+    #[derive(Clone, Copy, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct SyncLimits {
+        /// Maximum number of entries synchronizing at the same time.
+        pub max_concurrent: usize,
+        /// Maximum number of entries of the same host synchronizing at the same time.
+        pub max_per_host: usize,
+    }
+
+    impl Default for SyncLimits {
+        fn default() -> Self {
+            Self {
+                max_concurrent: 8,
+                max_per_host: 2,
+            }
+        }
+    }
+
+    /// Entries waiting for synchronization and entries synchronizing now.
+    #[derive(Default)]
+    struct SyncQueue {
+        pending: VecDeque<EntryId>,
+        running: Vec<(EntryId, Option<String>)>,
+        focus: Option<SyncFocus>,
+        /// Number of entries queued since the queue was empty last time.
+        total: usize,
+        /// Number of entries finished since the queue was empty last time.
+        finished: usize,
+    }
+
+    impl SyncQueue {
+        fn contains(&self, id: EntryId) -> bool {
+            self.pending.contains(&id) || self.running.iter().any(|(running, _)| *running == id)
+        }
+
+        fn push(&mut self, id: EntryId) -> bool {
+            if self.contains(id) {
+                return false;
+            }
+            self.pending.push_back(id);
+            self.total += 1;
+            true
+        }
+
+        fn running_on(&self, host: &Option<String>) -> usize {
+            self.running
+                .iter()
+                .filter(|(_, running_host)| running_host == host)
+                .count()
+        }
+
+        /// Takes the next entry which is allowed to start by the limits,
+        /// preferring the entries in focus.
+        fn pop(
+            &mut self,
+            limits: &SyncLimits,
+            host_of: impl Fn(EntryId) -> Option<String>,
+            in_focus: impl Fn(EntryId) -> bool,
+        ) -> Option<(EntryId, Option<String>)> {
+            if self.running.len() >= limits.max_concurrent {
+                return None;
+            }
+            let startable = |id: &EntryId| self.running_on(&host_of(*id)) < limits.max_per_host;
+            let index = self
+                .pending
+                .iter()
+                .position(|id| in_focus(*id) && startable(id))
+                .or_else(|| self.pending.iter().position(startable))?;
+            let id = self.pending.remove(index)?;
+            Some((id, host_of(id)))
+        }
+
+        fn progress(&self) -> Option<(usize, usize)> {
+            (self.total > 0).then_some((self.finished, self.total))
+        }
+    }
+
     #[derive(Default, Serialize, Deserialize, Clone)]
     pub struct RssClient {
+        /// The library is kept in its own storage instead of the app state.
+        #[serde(skip)]
         feed: Rc<RefCell<feed::Feed>>,
+
+        #[serde(default)]
+        sync_limits: Rc<RefCell<SyncLimits>>,
+        #[serde(skip)]
+        sync_queue: Rc<RefCell<SyncQueue>>,
+        #[serde(skip)]
+        storage: Rc<RefCell<Option<Box<dyn Storage>>>>,
     }
 
     impl RssClient {
         pub fn new(feed: feed::Feed) -> Self {
             RssClient {
                 feed: Rc::new(RefCell::new(feed)),
+                ..Default::default()
             }
         }
 
@@ -440,6 +568,96 @@ pub mod rss_client_ng {
             Rc::clone(&self.feed)
         }
 
+        /// Replaces the library, e.g. by an imported one, keeping the storage.
+        pub fn replace_feed(&self, mut feed: feed::Feed) {
+            if let Some(body_source) = self
+                .storage
+                .borrow()
+                .as_ref()
+                .and_then(|storage| storage.body_source())
+            {
+                feed.set_body_source(body_source);
+            }
+            *self.feed.borrow_mut() = feed;
+        }
+
+        /// Replaces the library with one restored from a backup.
+        pub fn replace_library(
+            &self,
+            mut feed: feed::Feed,
+            articles: Vec<(ArticleUuid, article::Article)>,
+        ) {
+            feed.attach_articles(articles);
+            self.replace_feed(feed);
+        }
+
+        /// Merges a library restored from a backup into the current one,
+        /// returns the numbers of entries and articles added.
+        pub fn merge_library(
+            &self,
+            feed: feed::Feed,
+            articles: Vec<(ArticleUuid, article::Article)>,
+        ) -> (usize, usize) {
+            self.feed.borrow_mut().merge(feed, articles)
+        }
+
+        /// Attempts to take the library loaded from the storage, and commits to the storage
+        /// since then. The current library is merged into it (see [`feed::Feed::merge`]),
+        /// which has what's changed while the storage is opening in the background.
+        /// If nothing is stored there yet, the library from `legacy` is moved into it.
+        pub fn try_open_storage(
+            &self,
+            storage: Box<dyn Storage>,
+            loaded: Loaded,
+            legacy: impl FnOnce() -> Option<(feed::Feed, Vec<(ArticleUuid, article::Article)>)>,
+        ) -> Result<()> {
+            let (mut feed, articles) = match loaded.feed {
+                Some(feed) => (feed, loaded.articles),
+                None => legacy().unwrap_or_default(),
+            };
+            feed.attach_articles(articles);
+            let current = std::mem::take(&mut *self.feed.borrow_mut());
+            let current_articles: Vec<_> = current
+                .get_articles_map()
+                .lock()
+                .expect("Failed to get the lock on article map")
+                .iter()
+                .map(|(id, article)| (id.clone(), article.lock().unwrap().clone()))
+                .collect();
+            feed.merge(current, current_articles);
+            if let Some(body_source) = storage.body_source() {
+                feed.set_body_source(body_source);
+            }
+            *self.feed.borrow_mut() = feed;
+            *self.storage.borrow_mut() = Some(storage);
+            self.try_commit()
+        }
+
+        /// Attempts to write what's changed in the library to the storage, if there is one.
+        pub fn try_commit(&self) -> Result<()> {
+            if let Some(storage) = self.storage.borrow_mut().as_mut() {
+                let stats = storage::try_commit(storage.as_mut(), &self.feed.borrow())?;
+                if stats.written > 0 || stats.deleted > 0 {
+                    log::info!(
+                        "Committed {} articles and deleted {}",
+                        stats.written,
+                        stats.deleted
+                    );
+                }
+            }
+            Ok(())
+        }
+
+        #[allow(unused)]
+        pub fn set_fetcher(&self, fetcher: impl Fetcher + 'static) {
+            self.feed.borrow_mut().set_fetcher(fetcher);
+        }
+
+        /// Starts discovering feeds from the URL of a feed or a web page.
+        pub fn discover_feeds(&self, url: url::Url) -> Discovery {
+            Discovery::start(self.feed.borrow().fetcher(), url)
+        }
+
         pub fn create_folder(&self, name: impl ToString) -> FolderId {
             let result = self
                 .feed
@@ -448,6 +666,19 @@ pub mod rss_client_ng {
             FolderId::from(result)
         }
 
+        /// Creates a folder inside another folder.
+        pub fn try_create_folder_in(
+            &self,
+            name: impl ToString,
+            parent: FolderId,
+        ) -> Result<FolderId> {
+            Ok(FolderId::from(
+                self.feed
+                    .borrow_mut()
+                    .try_add_folder_to_folder(feed::Folder::new(name), &parent.0)?,
+            ))
+        }
+
         // pub fn add_folder(&self, folder: Folder) -> Option<Folder> {
         //     self.folders.lock().unwrap().insert(folder.id, folder)
         // }
@@ -479,6 +710,80 @@ pub mod rss_client_ng {
                 .collect()
         }
 
+        /// Renames the folder, unless the name is blank.
+        pub fn rename_folder(&self, id: FolderId, name: &str) {
+            let name = name.trim();
+            if name.is_empty() {
+                return;
+            }
+            if let Ok(folder) = self.feed.borrow().try_get_folder_by_id(&id.0) {
+                folder.borrow_mut().rename(name);
+            }
+        }
+
+        /// Moves the folder into another folder, or to the top level if `to` is `None`.
+        pub fn try_move_folder(&self, id: FolderId, to: Option<FolderId>) -> Result<()> {
+            self.feed
+                .borrow_mut()
+                .try_move_folder_to_folder(&id.0, to.as_ref().map(|to| &to.0))
+        }
+
+        /// Lists the folder and all folders inside it at any depth.
+        pub fn try_list_folder_in_tree(&self, folder_id: FolderId) -> Result<Vec<FolderId>> {
+            Ok(self
+                .feed
+                .borrow()
+                .try_get_folder_ids_in_tree(&folder_id.0)?
+                .into_iter()
+                .map(FolderId::from)
+                .collect())
+        }
+
+        /// Lists the folders which are not inside any folders, in the sort mode.
+        pub fn list_top_level_folder(&self) -> Vec<FolderId> {
+            let feed = self.feed.borrow();
+            feed.sort_folder_ids(feed.get_top_level_folder_ids(), feed.sort_mode())
+                .into_iter()
+                .map(FolderId::from)
+                .collect()
+        }
+
+        /// Lists the folders inside the folder, not including those inside them, in the sort mode.
+        pub fn try_list_subfolder(&self, folder_id: FolderId) -> Result<Vec<FolderId>> {
+            let feed = self.feed.borrow();
+            Ok(feed
+                .sort_folder_ids(feed.try_get_subfolder_ids(&folder_id.0)?, feed.sort_mode())
+                .into_iter()
+                .map(FolderId::from)
+                .collect())
+        }
+
+        /// Moves the folder by `offset` places among its siblings, in the order given by the reader.
+        pub fn try_shift_folder(&self, id: FolderId, offset: isize) -> Result<()> {
+            self.feed.borrow_mut().try_shift_folder(&id.0, offset)
+        }
+
+        /// Returns the names of the folders from the top-level one to the folder,
+        /// such as "Tech / Rust / Blogs".
+        pub fn folder_path(&self, folder_id: &FolderId) -> Option<String> {
+            let feed = self.feed.borrow();
+            let names: Vec<String> = feed
+                .try_get_folder_path(&folder_id.0)
+                .ok()?
+                .iter()
+                .map(|id| {
+                    Some(
+                        feed.try_get_folder_by_id(id)
+                            .ok()?
+                            .borrow()
+                            .title()
+                            .to_owned(),
+                    )
+                })
+                .collect::<Option<_>>()?;
+            Some(names.join(" / "))
+        }
+
         pub fn create_entry(&self, url: url::Url, alias: Option<impl ToString>) -> EntryId {
             let entry = if let Some(alias) = alias {
                 feed::Entry::new_with_alias(alias, url)
@@ -515,6 +820,25 @@ pub mod rss_client_ng {
                 .map(Entry::from)
         }
 
+        /// Renames the subscription, unless the name is blank.
+        pub fn rename_entry(&self, id: EntryId, name: &str) {
+            let name = name.trim();
+            if name.is_empty() {
+                return;
+            }
+            if let Ok(entry) = self.feed.borrow().try_get_entry_by_id(&id.0) {
+                entry.borrow_mut().rename(name);
+            }
+        }
+
+        /// Moves the subscription into the folder, or out of folders if `to` is `None`.
+        pub fn try_move_entry(&self, id: EntryId, to: Option<FolderId>) -> Result<()> {
+            self.feed
+                .borrow_mut()
+                .try_move_entry_to_folder(&id.0, to.as_ref().map(|to| &to.0))?;
+            Ok(())
+        }
+
         pub fn delete_entry(&self, id: EntryId) -> Option<Entry> {
             self.feed
                 .borrow_mut()
@@ -532,25 +856,63 @@ pub mod rss_client_ng {
                 .collect()
         }
 
+        /// Lists the subscriptions which don't belong to any folders, in the sort mode.
         pub fn list_orphan_entry(&self) -> Vec<EntryId> {
-            self.feed
-                .borrow()
-                .get_all_orphan_entry_ids()
+            let feed = self.feed.borrow();
+            feed.sort_entry_ids(feed.get_all_orphan_entry_ids(), feed.sort_mode())
                 .into_iter()
                 .map(EntryId::from)
                 .collect()
         }
 
+        /// Lists the subscriptions in the folder, in the sort mode.
         pub fn try_list_entry_by_folder(&self, folder_id: FolderId) -> Result<Vec<EntryId>> {
+            let feed = self.feed.borrow();
+            Ok(feed
+                .sort_entry_ids(
+                    feed.try_get_entry_ids_by_folder_id(&folder_id.0)?,
+                    feed.sort_mode(),
+                )
+                .into_iter()
+                .map(EntryId::from)
+                .collect())
+        }
+
+        /// Moves the subscription by `offset` places among its siblings,
+        /// in the order given by the reader.
+        pub fn try_shift_entry(&self, id: EntryId, offset: isize) -> Result<()> {
+            self.feed.borrow_mut().try_shift_entry(&id.0, offset)
+        }
+
+        /// Returns how folders and subscriptions are ordered, which is kept until changed.
+        pub fn sort_mode(&self) -> SortMode {
+            self.feed.borrow().sort_mode()
+        }
+
+        /// Sets how folders and subscriptions are ordered, which is kept until changed.
+        pub fn set_sort_mode(&self, mode: SortMode) {
+            self.feed.borrow_mut().set_sort_mode(mode);
+        }
+
+        /// Sorts all folders and subscriptions once in the mode,
+        /// which becomes the order given by the reader.
+        pub fn sort_once(&self, mode: SortMode) {
+            self.feed.borrow_mut().sort_once(mode);
+        }
+
+        /// Lists the subscriptions in the folder and in all folders inside it.
+        pub fn try_list_entry_in_folder_tree(&self, folder_id: FolderId) -> Result<Vec<EntryId>> {
             Ok(self
                 .feed
                 .borrow()
-                .try_get_entry_ids_by_folder_id(&folder_id.0)?
+                .try_get_entry_ids_in_folder_tree(&folder_id.0)?
                 .into_iter()
                 .map(EntryId::from)
                 .collect())
         }
 
+        /// Returns the article, whose body may be left in the storage,
+        /// which is enough for anything but showing its text.
         pub fn get_article_by_id(&self, article_id: &ArticleId) -> Option<Article> {
             self.feed
                 .borrow()
@@ -559,24 +921,664 @@ pub mod rss_client_ng {
                 .ok()
         }
 
+        /// Returns the article with its body, which is loaded from the storage and kept,
+        /// for reading it.
+        pub fn try_load_article_by_id(&self, article_id: &ArticleId) -> Result<Article> {
+            let feed = self.feed.borrow();
+            feed.try_load_article_body(&article_id.0)?;
+            Ok(Article::from(feed.try_get_article_by_id(&article_id.0)?))
+        }
+
+        /// Returns a copy of the article with its body, which is loaded without being kept
+        /// if it's left in the storage, for previewing it.
+        pub fn get_article_copy_with_body(
+            &self,
+            article_id: &ArticleId,
+        ) -> Option<article::Article> {
+            let feed = self.feed.borrow();
+            let mut article = feed
+                .try_get_article_by_id(&article_id.0)
+                .ok()?
+                .lock()
+                .unwrap()
+                .clone();
+            if !article.is_body_loaded() {
+                match feed.try_get_article_body(&article_id.0) {
+                    Ok(body) => article.set_body(body),
+                    Err(err) => log::error!("{err:#}"),
+                }
+            }
+            Some(article)
+        }
+
+        /// Indexes at most `limit` articles for searching,
+        /// returning how many articles are indexed and in all.
+        pub fn try_index_articles(&self, limit: usize) -> Result<(usize, usize)> {
+            self.feed.borrow().try_index_articles(limit)
+        }
+
+        /// Searches articles by the query and the filters, from the most relevant.
+        pub fn try_search(&self, query: &Query, filter: &SearchFilter) -> Result<Vec<ArticleId>> {
+            Ok(self
+                .feed
+                .borrow()
+                .try_search(query, filter)?
+                .into_iter()
+                .map(ArticleId::from)
+                .collect())
+        }
+
+        /// Marks the article read, along with its copies in other subscriptions.
+        pub fn mark_article_read(&self, article_id: &ArticleId) {
+            let _ = self.feed.borrow().try_mark_article_read(&article_id.0);
+        }
+
+        pub fn mark_article_unread(&self, article_id: &ArticleId) {
+            let _ = self.feed.borrow().try_mark_article_unread(&article_id.0);
+        }
+
+        pub fn unread_count_of_entry(&self, id: EntryId) -> Option<usize> {
+            self.feed.borrow().try_count_unread_by_entry_id(&id.0).ok()
+        }
+
+        pub fn unread_count_of_folder(&self, id: FolderId) -> Option<usize> {
+            self.feed.borrow().try_count_unread_by_folder_id(&id.0).ok()
+        }
+
+        pub fn unread_count(&self) -> usize {
+            self.feed.borrow().count_unread()
+        }
+
+        /// Marks the articles of the subscription read, only those dated before `before`
+        /// if it's given, returns the number of articles marked.
+        pub fn try_mark_entry_read(
+            &self,
+            id: EntryId,
+            before: Option<DateTime<Utc>>,
+        ) -> Result<usize> {
+            self.feed.borrow().try_mark_entry_read(&id.0, before)
+        }
+
+        /// Marks the articles of the subscriptions in the folder read, like
+        /// [`RssClient::try_mark_entry_read`].
+        pub fn try_mark_folder_read(
+            &self,
+            id: FolderId,
+            before: Option<DateTime<Utc>>,
+        ) -> Result<usize> {
+            self.feed.borrow().try_mark_folder_read(&id.0, before)
+        }
+
+        /// Marks the articles of all subscriptions read, like
+        /// [`RssClient::try_mark_entry_read`].
+        pub fn mark_all_read(&self, before: Option<DateTime<Utc>>) -> usize {
+            self.feed.borrow().mark_all_read(before)
+        }
+
+        pub fn set_article_flag(&self, article_id: &ArticleId, flag: Flag, value: bool) {
+            let _ = self
+                .feed
+                .borrow()
+                .try_set_article_flag(&article_id.0, flag, value);
+        }
+
+        /// Lists the articles across all subscriptions with the flag set, from the latest.
+        pub fn list_article_by_flag(&self, flag: Flag) -> Vec<ArticleId> {
+            self.feed
+                .borrow()
+                .get_article_ids_by_flag(flag)
+                .into_iter()
+                .map(ArticleId::from)
+                .collect()
+        }
+
+        /// Returns the revision of the articles, which changes whenever they are changed,
+        /// so that what's listed of them can be cached until then.
+        pub fn revision(&self) -> u64 {
+            self.feed.borrow().revision()
+        }
+
+        /// Lists the articles of the subscription, from the latest.
+        pub fn try_list_article_by_entry(&self, id: EntryId) -> Result<Vec<ArticleId>> {
+            Ok(self
+                .feed
+                .borrow()
+                .try_get_all_article_ids_by_entry_id(&id.0)?
+                .into_iter()
+                .map(ArticleId::from)
+                .collect())
+        }
+
+        /// Lists the articles of the subscriptions in the folder and in all folders inside it,
+        /// from the latest.
+        pub fn try_list_article_by_folder(&self, id: FolderId) -> Result<Vec<ArticleId>> {
+            Ok(self
+                .feed
+                .borrow()
+                .get_all_article_ids_by_folder_id(&id.0)?
+                .into_iter()
+                .map(ArticleId::from)
+                .collect())
+        }
+
+        /// Lists the articles of all subscriptions, from the latest.
+        pub fn list_article(&self) -> Vec<ArticleId> {
+            self.feed
+                .borrow()
+                .get_all_article_ids()
+                .into_iter()
+                .map(ArticleId::from)
+                .collect()
+        }
+
+        /// Lists the unread articles of all subscriptions, from the latest.
+        pub fn list_unread_article(&self) -> Vec<ArticleId> {
+            self.feed
+                .borrow()
+                .get_unread_article_ids()
+                .into_iter()
+                .map(ArticleId::from)
+                .collect()
+        }
+
+        /// Lists the articles of all subscriptions dated since then, from the latest.
+        pub fn list_article_since(&self, since: DateTime<Utc>) -> Vec<ArticleId> {
+            self.feed
+                .borrow()
+                .get_article_ids_since(since)
+                .into_iter()
+                .map(ArticleId::from)
+                .collect()
+        }
+
+        /// Collapses the copies of the same article from different subscriptions,
+        /// keeping only the first one of them.
+        pub fn collapse_duplicate_article(&self, article_ids: Vec<ArticleId>) -> Vec<ArticleId> {
+            self.feed
+                .borrow()
+                .collapse_duplicate_article_ids(article_ids.into_iter().map(|id| id.0).collect())
+                .into_iter()
+                .map(ArticleId::from)
+                .collect()
+        }
+
+        /// Tags the article, returns the normalized name of the tag.
+        pub fn try_add_article_tag(&self, article_id: &ArticleId, name: &str) -> Result<String> {
+            self.feed.borrow().try_add_article_tag(&article_id.0, name)
+        }
+
+        pub fn remove_article_tag(&self, article_id: &ArticleId, name: &str) {
+            let _ = self
+                .feed
+                .borrow()
+                .try_remove_article_tag(&article_id.0, name);
+        }
+
+        /// Lists all tags with the numbers of articles tagged, by their names.
+        pub fn list_tag(&self) -> Vec<(String, usize)> {
+            self.feed.borrow().get_all_tags()
+        }
+
+        /// Lists the articles across all subscriptions with the tag, from the latest.
+        pub fn list_article_by_tag(&self, name: &str) -> Vec<ArticleId> {
+            self.feed
+                .borrow()
+                .get_article_ids_by_tag(name)
+                .into_iter()
+                .map(ArticleId::from)
+                .collect()
+        }
+
+        pub fn tag_color(&self, name: &str) -> TagColor {
+            self.feed.borrow().tag_color(name)
+        }
+
+        pub fn set_tag_color(&self, name: &str, color: TagColor) {
+            self.feed.borrow_mut().set_tag_color(name, color);
+        }
+
+        /// Creates a smart folder collecting the articles matching the rule.
+        pub fn create_smart_folder(&self, name: impl ToString, rule: Rule) -> SmartFolderId {
+            SmartFolderId::from(
+                self.feed
+                    .borrow_mut()
+                    .add_smart_folder(feed::SmartFolder::new(name, rule)),
+            )
+        }
+
+        /// Returns a copy of the smart folder.
+        pub fn get_smart_folder(&self, id: &SmartFolderId) -> Option<feed::SmartFolder> {
+            self.feed
+                .borrow()
+                .try_get_smart_folder_by_id(&id.0)
+                .ok()
+                .cloned()
+        }
+
+        /// Changes the name and the rule of the smart folder, keeping the name if it's blank.
+        pub fn try_update_smart_folder(
+            &self,
+            id: SmartFolderId,
+            name: &str,
+            rule: Rule,
+        ) -> Result<()> {
+            let mut feed = self.feed.borrow_mut();
+            let folder = feed.try_get_smart_folder_mut(&id.0)?;
+            if !name.trim().is_empty() {
+                folder.rename(name.trim());
+            }
+            folder.rule = rule;
+            Ok(())
+        }
+
+        pub fn delete_smart_folder(&self, id: SmartFolderId) {
+            self.feed.borrow_mut().remove_smart_folder(&id.0);
+        }
+
+        /// Lists the smart folders, in the order they are created.
+        pub fn list_smart_folder(&self) -> Vec<SmartFolderId> {
+            self.feed
+                .borrow()
+                .get_smart_folder_ids()
+                .into_iter()
+                .map(SmartFolderId::from)
+                .collect()
+        }
+
+        /// Counts the articles matching the rule of the smart folder,
+        /// which is kept until the articles change. Fails if the rule has an invalid pattern.
+        pub fn try_count_article_by_smart_folder(&self, id: SmartFolderId) -> Result<usize> {
+            self.feed
+                .borrow()
+                .try_count_articles_by_smart_folder_id(&id.0)
+        }
+
+        /// Lists the articles matching the rule of the smart folder, from the latest.
+        /// Fails if the rule has an invalid pattern.
+        pub fn try_list_article_by_smart_folder(
+            &self,
+            id: SmartFolderId,
+        ) -> Result<Vec<ArticleId>> {
+            Ok(self
+                .feed
+                .borrow()
+                .try_get_article_ids_by_smart_folder_id(&id.0)?
+                .into_iter()
+                .map(ArticleId::from)
+                .collect())
+        }
+
         pub fn try_start_sync_all(&self) -> Result<()> {
-            self.feed.borrow_mut().try_sync_all()
+            for id in self.list_entry() {
+                self.try_start_sync_entry(id)?;
+            }
+
+            Ok(())
         }
 
         pub fn try_start_sync_folder(&self, id: FolderId) -> Result<()> {
-            for feed_id in self.try_list_entry_by_folder(id)? {
+            for feed_id in self.try_list_entry_in_folder_tree(id)? {
                 self.try_start_sync_entry(feed_id)?;
             }
 
             Ok(())
         }
 
+        /// Queues an entry for synchronization.
+        /// Returns `Ok(false)` if it is queued or synchronizing already.
         pub fn try_start_sync_entry(&self, id: EntryId) -> Result<bool> {
-            self.feed.borrow_mut().try_sync_entry_by_id(&id.0)
+            self.feed.borrow().try_get_entry_by_id(&id.0)?;
+            Ok(self.sync_queue.borrow_mut().push(id))
         }
 
+        /// Synchronizes an entry with a response fetched already, such as by discovery,
+        /// instead of queueing it to be fetched again.
+        pub fn try_sync_entry_with_response(
+            &self,
+            id: EntryId,
+            response: ehttp::Response,
+        ) -> Result<bool> {
+            self.feed
+                .borrow_mut()
+                .try_sync_entry_with_response(&id.0, response)
+        }
+
+        /// Is the entry queued or synchronizing now?
         pub fn entry_is_syncing(&self, id: EntryId) -> Option<bool> {
-            self.feed.borrow().is_entry_synchronizing(&id.0)
+            self.feed
+                .borrow()
+                .is_entry_synchronizing(&id.0)
+                .map(|is_syncing| is_syncing || self.sync_queue.borrow().contains(id))
+        }
+
+        pub fn sync_limits(&self) -> Rc<RefCell<SyncLimits>> {
+            Rc::clone(&self.sync_limits)
+        }
+
+        /// Entries in focus are synchronized before the others in the queue.
+        pub fn set_sync_focus(&self, focus: Option<SyncFocus>) {
+            self.sync_queue.borrow_mut().focus = focus;
+        }
+
+        fn list_entry_in_focus(&self) -> Vec<EntryId> {
+            match self.sync_queue.borrow().focus {
+                Some(SyncFocus::Entry(entry_id)) => vec![entry_id],
+                Some(SyncFocus::Folder(folder_id)) => self
+                    .try_list_entry_in_folder_tree(folder_id)
+                    .unwrap_or_default(),
+                None => vec![],
+            }
+        }
+
+        fn host_of(&self, id: EntryId) -> Option<String> {
+            self.get_entry(&id)
+                .and_then(|entry| entry.get().borrow().xml_url.host_str().map(str::to_owned))
+        }
+
+        /// Returns how many of the queued entries have finished synchronizing,
+        /// and how many have been queued in total, if the queue is not empty.
+        pub fn sync_progress(&self) -> Option<(usize, usize)> {
+            self.sync_queue.borrow().progress()
+        }
+
+        /// Collects finished synchronizations and starts queued ones within the limits.
+        /// Returns whether the queue is still busy.
+        pub fn pump_sync_queue(&self) -> bool {
+            let limits = *self.sync_limits.borrow();
+            let in_focus = self.list_entry_in_focus();
+            let committing;
+            {
+                let feed = self.feed.borrow();
+                let mut queue = self.sync_queue.borrow_mut();
+                let running = queue.running.len();
+                queue
+                    .running
+                    .retain(|(id, _)| feed.is_entry_synchronizing(&id.0).unwrap_or(false));
+                queue.finished += running - queue.running.len();
+                committing = running > queue.running.len();
+                // Entries deleted in the meantime are dropped from the queue.
+                let pending = queue.pending.len();
+                queue
+                    .pending
+                    .retain(|id| feed.try_get_entry_by_id(&id.0).is_ok());
+                queue.total -= pending - queue.pending.len();
+            }
+            // Articles of finished entries are committed right away.
+            if committing {
+                if let Err(err) = self.try_commit() {
+                    log::error!("Failed to commit the library: {err:#}");
+                }
+            }
+
+            loop {
+                let next = self.sync_queue.borrow_mut().pop(
+                    &limits,
+                    |id| self.host_of(id),
+                    |id| in_focus.contains(&id),
+                );
+                let Some((id, host)) = next else {
+                    break;
+                };
+                match self.feed.borrow_mut().try_sync_entry_by_id(&id.0) {
+                    Ok(_) => self.sync_queue.borrow_mut().running.push((id, host)),
+                    Err(_) => self.sync_queue.borrow_mut().finished += 1,
+                }
+            }
+
+            let mut queue = self.sync_queue.borrow_mut();
+            if queue.pending.is_empty() && queue.running.is_empty() {
+                queue.total = 0;
+                queue.finished = 0;
+                false
+            } else {
+                true
+            }
+        }
+
+        /// Applies the permanent moves of feeds found by synchronizations,
+        /// returns the moved entries with their former URLs.
+        pub fn apply_feed_moves(&self) -> Vec<(EntryId, url::Url)> {
+            self.feed
+                .borrow_mut()
+                .apply_moves()
+                .into_iter()
+                .map(|(id, url)| (EntryId(id), url))
+                .collect()
+        }
+
+        /// Has the feed of the entry been removed for good?
+        pub fn entry_is_dead(&self, id: EntryId) -> Option<bool> {
+            self.get_entry(&id)
+                .map(|entry| entry.get().borrow().is_dead())
+        }
+
+        pub fn entry_sync_record(&self, id: EntryId) -> Option<SyncRecord> {
+            self.feed.borrow().get_entry_sync_record(&id.0)
+        }
+
+        pub fn entry_next_refresh(
+            &self,
+            id: EntryId,
+            default_interval: u32,
+        ) -> Option<DateTime<Utc>> {
+            self.get_entry(&id)
+                .map(|entry| entry.get().borrow().next_refresh(default_interval))
+        }
+
+        pub fn entry_refresh_interval(&self, id: EntryId) -> Option<u32> {
+            self.get_entry(&id)
+                .and_then(|entry| entry.get().borrow().refresh_interval())
+        }
+
+        pub fn set_entry_refresh_interval(&self, id: EntryId, interval: Option<u32>) {
+            if let Some(entry) = self.get_entry(&id) {
+                entry.get().borrow_mut().set_refresh_interval(interval);
+            }
+        }
+
+        pub fn entry_refresh_hints(&self, id: EntryId) -> Option<RefreshHints> {
+            self.get_entry(&id)
+                .map(|entry| entry.get().borrow().refresh_hints())
+        }
+
+        pub fn retention_policy(&self) -> RetentionPolicy {
+            self.feed.borrow().retention_policy()
+        }
+
+        pub fn set_retention_policy(&self, policy: RetentionPolicy) {
+            self.feed.borrow_mut().set_retention_policy(policy);
+        }
+
+        pub fn entry_retention(&self, id: EntryId) -> Option<Retention> {
+            self.get_entry(&id)
+                .map(|entry| entry.get().borrow().retention())
+        }
+
+        pub fn set_entry_retention(&self, id: EntryId, retention: Retention) {
+            if let Some(entry) = self.get_entry(&id) {
+                entry.get().borrow_mut().set_retention(retention);
+            }
+        }
+
+        pub fn folder_retention(&self, id: FolderId) -> Option<Retention> {
+            self.get_folder(&id)
+                .map(|folder| folder.get().borrow().retention())
+        }
+
+        pub fn set_folder_retention(&self, id: FolderId, retention: Retention) {
+            if let Some(folder) = self.get_folder(&id) {
+                folder.get().borrow_mut().set_retention(retention);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::{EntryId, SyncLimits, SyncQueue};
+        use uuid::Uuid;
+
+        fn entry_ids(count: usize) -> Vec<EntryId> {
+            (0..count).map(|_| EntryId::from(Uuid::new_v4())).collect()
+        }
+
+        /// Starts the queued entries allowed by the limits, returning them.
+        fn start(
+            queue: &mut SyncQueue,
+            limits: &SyncLimits,
+            host_of: impl Fn(EntryId) -> Option<String>,
+            in_focus: impl Fn(EntryId) -> bool,
+        ) -> Vec<EntryId> {
+            let mut started = vec![];
+            while let Some((id, host)) = queue.pop(limits, &host_of, &in_focus) {
+                queue.running.push((id, host));
+                started.push(id);
+            }
+            started
+        }
+
+        #[test]
+        fn sync_queue_limits() {
+            let ids = entry_ids(6);
+            let host_of = |id: EntryId| {
+                let index = ids.iter().position(|queued| *queued == id).unwrap();
+                Some(
+                    if index < 4 {
+                        "a.example.com"
+                    } else {
+                        "b.example.com"
+                    }
+                    .to_owned(),
+                )
+            };
+            let limits = SyncLimits {
+                max_concurrent: 3,
+                max_per_host: 2,
+            };
+            let mut queue = SyncQueue::default();
+            assert!(ids.iter().all(|id| queue.push(*id)));
+            // Queued twice, counted once.
+            assert!(!queue.push(ids[0]));
+            assert_eq!(queue.progress(), Some((0, 6)));
+
+            // The third entry of `a` waits for the first entry of `b`.
+            let started = start(&mut queue, &limits, host_of, |_| false);
+            assert!(started == vec![ids[0], ids[1], ids[4]]);
+            assert!(ids.iter().all(|id| queue.contains(*id)));
+
+            queue.running.clear();
+            queue.finished += 3;
+            let started = start(&mut queue, &limits, host_of, |_| false);
+            assert!(started == vec![ids[2], ids[3], ids[5]]);
+            assert_eq!(queue.progress(), Some((3, 6)));
+        }
+
+        #[test]
+        fn sync_queue_focus() {
+            let ids = entry_ids(3);
+            let limits = SyncLimits {
+                max_concurrent: 1,
+                ..Default::default()
+            };
+            let mut queue = SyncQueue::default();
+            ids.iter().for_each(|id| {
+                queue.push(*id);
+            });
+            let started = start(&mut queue, &limits, |_| None, |id| id == ids[2]);
+            assert!(started == vec![ids[2]]);
+        }
+    }
+}
+
+pub mod scheduler {
+    use chrono::{DateTime, Duration, Utc};
+    use serde::{Deserialize, Serialize};
+    use std::{
+        cell::{Cell, RefCell},
+        rc::Rc,
+    };
+
+    use super::rss_client_ng::{EntryId, RssClient};
+
+    #[derive(Clone, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct ScheduleSettings {
+        /// Refresh entries automatically?
+        pub enabled: bool,
+        /// Refresh interval in minutes for entries without their own one.
+        pub default_interval: u32,
+    }
+
+    impl Default for ScheduleSettings {
+        fn default() -> Self {
+            Self {
+                enabled: true,
+                default_interval: 60,
+            }
+        }
+    }
+
+    /// Refreshes entries automatically when they are due.
+    #[derive(Clone, Default, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Scheduler {
+        settings: Rc<RefCell<ScheduleSettings>>,
+
+        #[serde(skip)]
+        next_check: Rc<Cell<Option<DateTime<Utc>>>>,
+    }
+
+    impl Scheduler {
+        /// Entries added in the meantime are picked up within this many seconds.
+        const MAX_CHECK_DELAY: i64 = 60;
+
+        pub fn settings(&self) -> Rc<RefCell<ScheduleSettings>> {
+            Rc::clone(&self.settings)
+        }
+
+        pub fn is_enabled(&self) -> bool {
+            self.settings.borrow().enabled
+        }
+
+        /// Makes the next tick check all entries again.
+        pub fn reschedule(&self) {
+            self.next_check.set(None);
+        }
+
+        pub fn next_refresh(&self, client: &RssClient, id: EntryId) -> Option<DateTime<Utc>> {
+            client.entry_next_refresh(id, self.settings.borrow().default_interval)
+        }
+
+        /// Starts synchronizing the entries which are due,
+        /// and returns how long to wait before ticking again.
+        pub fn tick(&self, client: &RssClient) -> Option<std::time::Duration> {
+            if !self.is_enabled() {
+                return None;
+            }
+            let now = Utc::now();
+            if let Some(next_check) = self.next_check.get().filter(|time| *time > now) {
+                return (next_check - now).to_std().ok();
+            }
+            let mut next_check = now + Duration::seconds(Self::MAX_CHECK_DELAY);
+            for id in client.list_entry() {
+                // Dead feeds are only synchronized on demand.
+                if client.entry_is_syncing(id).unwrap_or(true)
+                    || client.entry_is_dead(id).unwrap_or(true)
+                {
+                    continue;
+                }
+                if self
+                    .next_refresh(client, id)
+                    .is_some_and(|time| time <= now)
+                {
+                    let _ = client.try_start_sync_entry(id);
+                }
+                // Entries queued just now are checked again after they finish.
+                if let Some(time) = self.next_refresh(client, id).filter(|time| *time > now) {
+                    next_check = next_check.min(time);
+                }
+            }
+            self.next_check.set(Some(next_check));
+            (next_check - now).to_std().ok()
         }
     }
 }
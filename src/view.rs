@@ -4,11 +4,20 @@ use std::rc::Rc;
 use egui::Widget;
 use uuid::Uuid;
 
-use crate::render::article;
+use crate::render::article::{self, DateDisplay, DateStyle, DisplayTimezone};
+use crate::subscription::article::Flag;
+use crate::subscription::backup::{Backup, RestoreMode};
+use crate::subscription::discovery::{Discovery, DiscoveryState};
+use crate::subscription::retention::Retention;
+use crate::subscription::rule::{Condition, Rule};
+use crate::subscription::search::{self, Query, SearchFilter, Snippet};
 use crate::{
     subscription::feed::Feed,
     subscription::opml::Opml,
-    utils::rss_client_ng::{ArticleId, EntryId, FolderId, RssClient},
+    utils::{
+        rss_client_ng::{ArticleId, EntryId, FolderId, RssClient, SmartFolderId, SyncFocus},
+        scheduler::Scheduler,
+    },
     widget::{self, CollapsingFolder},
     RSSucks,
 };
@@ -38,11 +47,16 @@ impl ReaderView {
 impl View for ReaderView {
     fn show(&self, app: Rc<RSSucks>, ui: &mut egui::Ui) {
         if self.cached_detail.borrow().is_none() {
-            let article = app
-                .rss_client
-                .get_article_by_id(&self.article_id)
-                .unwrap()
-                .get();
+            let article = match app.rss_client.try_load_article_by_id(&self.article_id) {
+                Ok(article) => article.get(),
+                Err(err) => {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("加载文章失败：{err:#}"),
+                    );
+                    return;
+                }
+            };
             let detail = article::Detail::from(article::Builder::from_article(
                 article.lock().as_ref().unwrap(),
                 self.article_id.clone(),
@@ -55,87 +69,599 @@ impl View for ReaderView {
     }
 }
 
+/// Where a flow view takes articles from.
+#[derive(Clone, Copy, PartialEq)]
+pub enum FlowSource {
+    /// The articles of a subscription.
+    Entry(EntryId),
+    /// The articles of the subscriptions in a folder and in all folders inside it.
+    Folder(FolderId),
+    /// The articles of all subscriptions.
+    All,
+    /// The unread articles of all subscriptions.
+    Unread,
+    /// The articles of all subscriptions dated today.
+    Today,
+}
+
+impl FlowSource {
+    /// Lists the articles from the latest, where the copies of the same article
+    /// from different subscriptions are collapsed.
+    fn try_list(
+        &self,
+        client: &RssClient,
+        today: chrono::DateTime<chrono::Utc>,
+    ) -> anyhow::Result<Vec<ArticleId>> {
+        let article_ids = match self {
+            FlowSource::Entry(id) => return client.try_list_article_by_entry(*id),
+            FlowSource::Folder(id) => client.try_list_article_by_folder(*id)?,
+            FlowSource::All => client.list_article(),
+            FlowSource::Unread => client.list_unread_article(),
+            FlowSource::Today => client.list_article_since(today),
+        };
+        Ok(client.collapse_duplicate_article(article_ids))
+    }
+}
+
+/// Number of articles shown more each time the reader loads more.
+const PAGE_SIZE: usize = 20;
+
+/// Articles listed from a source, which are kept until what they are listed at changes,
+/// such as the revision of the library, and the previews of the latest of them shown,
+/// which grow by a page each time the reader loads more.
+struct PagedArticles<K> {
+    listed: Option<(K, anyhow::Result<Vec<ArticleId>>)>,
+    shown: usize,
+    /// Previews of the articles shown with their dates, rebuilt when the articles are listed.
+    previews: Option<Vec<(chrono::DateTime<chrono::Utc>, article::Preview)>>,
+}
+
+impl<K: PartialEq> PagedArticles<K> {
+    fn new() -> Self {
+        Self {
+            listed: None,
+            shown: PAGE_SIZE,
+            previews: None,
+        }
+    }
+
+    /// Returns the articles listed, listing them again with `list` unless they are listed at `key`.
+    fn refresh(
+        &mut self,
+        key: K,
+        list: impl FnOnce() -> anyhow::Result<Vec<ArticleId>>,
+    ) -> &anyhow::Result<Vec<ArticleId>> {
+        if self
+            .listed
+            .as_ref()
+            .map_or(true, |(listed_at, _)| *listed_at != key)
+        {
+            self.listed = Some((key, list()));
+            self.previews = None;
+        }
+        &self.listed.as_ref().unwrap().1
+    }
+
+    /// Shows the previews of the articles shown, with the days which they are dated above them
+    /// if `by_day`, and a button to load more if there are.
+    fn show(
+        &mut self,
+        app: &Rc<RSSucks>,
+        ui: &mut egui::Ui,
+        current_view: &Rc<Box<dyn View>>,
+        by_day: bool,
+    ) {
+        let Some((_, Ok(article_ids))) = &self.listed else {
+            return;
+        };
+        let shown_ids = &article_ids[..self.shown.min(article_ids.len())];
+        let previews = self.previews.get_or_insert_with(|| {
+            shown_ids
+                .iter()
+                .filter_map(|article_id| {
+                    let article = app.rss_client.get_article_copy_with_body(article_id)?;
+                    let builder = article::Builder::from_article(
+                        &article,
+                        article_id.clone(),
+                        Some(Rc::clone(current_view)),
+                        Rc::clone(app),
+                    );
+                    Some((article.sort_date(), article::Preview::from(builder)))
+                })
+                .collect()
+        });
+
+        let date_display = *app.date_display.borrow();
+        let now = chrono::Utc::now();
+        let mut load_more = false;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            let mut last_day = None;
+            for (date, preview) in previews.iter() {
+                if by_day {
+                    let day = date_display.day_label(*date, now);
+                    if last_day.as_ref() != Some(&day) {
+                        ui.strong(&day);
+                        ui.separator();
+                        last_day = Some(day);
+                    }
+                }
+                if ui.add(preview).clicked() {
+                    app.set_view(Rc::new(Box::new(ReaderView::new(
+                        preview.article_id.clone(),
+                        Some(Rc::clone(current_view)),
+                    ))));
+                }
+            }
+            load_more = shown_ids.len() < article_ids.len() && ui.button("加载更多").clicked();
+        });
+        if load_more {
+            self.shown += PAGE_SIZE;
+            self.previews = None;
+        }
+    }
+}
+
+/// Revision of the library and the start of today, which flows are listed at.
+type FlowListedAt = (u64, chrono::DateTime<chrono::Utc>);
+
+/// Lists the articles from a source by their dates, grouped by days.
 #[derive(Clone)]
-pub struct FeedFlowView {
-    id: EntryId,
-    page: usize,
-    per_page: usize,
-    cached_previews: Rc<RefCell<Option<Vec<article::Preview>>>>,
+pub struct FlowView {
+    source: FlowSource,
+    articles: Rc<RefCell<PagedArticles<FlowListedAt>>>,
 }
 
-impl FeedFlowView {
-    pub fn new(id: EntryId) -> Self {
+impl FlowView {
+    pub fn new(source: FlowSource) -> Self {
         Self {
-            id,
-            page: 1,
-            per_page: 20,
-            cached_previews: Rc::new(RefCell::new(None)),
+            source,
+            articles: Rc::new(RefCell::new(PagedArticles::new())),
         }
     }
 }
 
-impl View for FeedFlowView {
+impl View for FlowView {
     fn show(&self, app: Rc<RSSucks>, ui: &mut egui::Ui) {
-        if let Some(is_syncing) = app.rss_client.entry_is_syncing(self.id) {
-            if is_syncing {
-                ui.spinner();
+        match self.source {
+            FlowSource::Entry(id) => {
+                let (Some(entry), Some(is_syncing)) = (
+                    app.rss_client.get_entry(&id),
+                    app.rss_client.entry_is_syncing(id),
+                ) else {
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.heading(entry.get_name());
+                    if is_syncing {
+                        ui.spinner();
+                    }
+                });
+            }
+            FlowSource::Folder(id) => {
+                let Some(path) = app.rss_client.folder_path(&id) else {
+                    ui.label("文件夹已删除");
+                    return;
+                };
+                ui.heading(format!("📁 {path}"));
+            }
+            FlowSource::All => {
+                ui.heading("📰 全部文章");
+            }
+            FlowSource::Unread => {
+                ui.heading("● 未读文章");
+            }
+            FlowSource::Today => {
+                ui.heading("📅 今天");
             }
-        } else {
-            return;
         }
 
-        let articles = app
-            .rss_client
-            .get()
+        let today = app
+            .date_display
             .borrow()
-            .try_get_all_article_ids_by_entry_id(&self.id.get());
-
-        match articles {
-            Ok(articles) => {
-                let current_view: Rc<Box<dyn View>> = Rc::new(Box::new((*self).clone()));
-
-                if self.cached_previews.borrow().is_none() {
-                    let previews = articles
-                        .into_iter()
-                        .skip((self.page - 1) * self.per_page)
-                        .take(self.per_page)
-                        .map(ArticleId::from)
-                        .map(|article_id| {
-                            let article =
-                                app.rss_client.get_article_by_id(&article_id).unwrap().get();
-                            let article = article.lock();
-                            let builder = article::Builder::from_article(
-                                article.as_ref().unwrap(),
-                                article_id,
-                                Some(Rc::clone(&current_view)),
-                                Rc::clone(&app),
-                            );
-                            article::Preview::from(builder)
-                        })
-                        .collect();
-                    self.cached_previews.replace(Some(previews));
+            .timezone
+            .start_of_day(chrono::Utc::now());
+        let mut articles = self.articles.borrow_mut();
+        let listed = articles.refresh((app.rss_client.revision(), today), || {
+            self.source.try_list(&app.rss_client, today)
+        });
+        match listed {
+            Ok(article_ids) if article_ids.is_empty() => {
+                ui.label("还没有文章");
+                return;
+            }
+            Ok(article_ids) => {
+                ui.label(format!("共 {} 篇文章", article_ids.len()));
+            }
+            Err(_) if matches!(self.source, FlowSource::Entry(_)) => {
+                ui.label("该订阅尚未同步，现在同步吗？");
+                if ui.button("同步").clicked() {
+                    if let FlowSource::Entry(id) = self.source {
+                        app.rss_client.try_start_sync_entry(id).unwrap();
+                    }
                 }
+                return;
+            }
+            Err(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("{err:#}"));
+                return;
+            }
+        }
+
+        let current_view: Rc<Box<dyn View>> = Rc::new(Box::new((*self).clone()));
+        articles.show(&app, ui, &current_view, true);
+    }
+
+    fn sync_focus(&self) -> Option<SyncFocus> {
+        match self.source {
+            FlowSource::Entry(id) => Some(SyncFocus::Entry(id)),
+            FlowSource::Folder(id) => Some(SyncFocus::Folder(id)),
+            _ => None,
+        }
+    }
+}
+
+/// Articles collected across all subscriptions.
+#[derive(Clone, PartialEq)]
+pub enum Collection {
+    /// Articles with a flag, such as starred ones.
+    Flagged(Flag),
+    /// Articles with a tag put by the reader.
+    Tagged(String),
+    /// Articles matching the rule of a smart folder.
+    Smart(SmartFolderId),
+}
+
+impl Collection {
+    /// Lists the articles from the latest, where the copies of the same article
+    /// from different subscriptions are collapsed.
+    fn list(&self, client: &RssClient) -> anyhow::Result<Vec<ArticleId>> {
+        let article_ids = match self {
+            Collection::Flagged(flag) => client.list_article_by_flag(*flag),
+            Collection::Tagged(name) => client.list_article_by_tag(name),
+            Collection::Smart(id) => client.try_list_article_by_smart_folder(*id)?,
+        };
+        Ok(client.collapse_duplicate_article(article_ids))
+    }
+}
+
+/// Lists the articles of a collection, such as starred ones or those with a tag.
+#[derive(Clone)]
+pub struct CollectionView {
+    collection: Collection,
+    /// Articles listed at a revision of the library.
+    articles: Rc<RefCell<PagedArticles<u64>>>,
+}
+
+impl CollectionView {
+    pub fn new(collection: Collection) -> Self {
+        Self {
+            collection,
+            articles: Rc::new(RefCell::new(PagedArticles::new())),
+        }
+    }
+}
+
+impl View for CollectionView {
+    fn show(&self, app: Rc<RSSucks>, ui: &mut egui::Ui) {
+        match &self.collection {
+            Collection::Flagged(flag) => {
+                let (icon, description) = article::describe_flag(*flag);
+                ui.heading(format!("{icon} {description}"));
+            }
+            Collection::Tagged(name) => {
+                ui.horizontal(|ui| {
+                    let mut color = app.rss_client.tag_color(name);
+                    ui.heading(
+                        egui::RichText::new(format!("🏷 {name}")).color(article::tag_color32(color)),
+                    );
+                    if egui::color_picker::color_edit_button_srgb(ui, &mut color.0)
+                        .on_hover_text("标签颜色")
+                        .changed()
+                    {
+                        app.rss_client.set_tag_color(name, color);
+                    }
+                });
+            }
+            Collection::Smart(id) => {
+                let Some(folder) = app.rss_client.get_smart_folder(id) else {
+                    ui.label("智能文件夹已删除");
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.heading(format!("🔮 {}", folder.title()));
+                    if ui.button("✏").on_hover_text("编辑规则").clicked() {
+                        app.add_window(SmartFolderWindow::edit(app.rss_client.clone(), *id));
+                    }
+                });
+            }
+        }
+        let mut articles = self.articles.borrow_mut();
+        match articles.refresh(app.rss_client.revision(), || {
+            self.collection.list(&app.rss_client)
+        }) {
+            Ok(article_ids) if article_ids.is_empty() => {
+                ui.label("还没有文章");
+                return;
+            }
+            Ok(article_ids) => {
+                ui.label(format!("共 {} 篇文章", article_ids.len()));
+            }
+            Err(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("规则有误：{err:#}"));
+                return;
+            }
+        }
+
+        let current_view: Rc<Box<dyn View>> = Rc::new(Box::new((*self).clone()));
+        articles.show(&app, ui, &current_view, false);
+    }
+}
+
+/// Most results listed with their snippets.
+const MAX_SEARCH_RESULTS: usize = 50;
+/// Articles indexed for searching in a frame, whose bodies are loaded from the storage.
+const SEARCH_INDEX_CHUNK: usize = 200;
+
+struct SearchResult {
+    article_id: ArticleId,
+    title: String,
+    entry_title: Option<String>,
+    date: chrono::DateTime<chrono::Utc>,
+    snippet: Snippet,
+}
+
+#[derive(Default)]
+struct SearchState {
+    query: String,
+    filter: SearchFilter,
+    since: String,
+    until: String,
+    /// Results of the query and the filters, with the total number of them,
+    /// `None` if they are changed since searched.
+    results: Option<Result<(Vec<SearchResult>, usize), String>>,
+    /// Revision of the library when searched, which invalidates the results once changed.
+    searched_at: u64,
+}
+
+impl SearchState {
+    fn search(&self, app: &RSSucks) -> Result<(Vec<SearchResult>, usize), String> {
+        let query = Query::parse(&self.query);
+        let article_ids = app
+            .rss_client
+            .try_search(&query, &self.filter)
+            .map_err(|err| format!("{err:#}"))?;
+        let total = article_ids.len();
+        let feed = app.rss_client.get();
+        let results = article_ids
+            .into_iter()
+            .take(MAX_SEARCH_RESULTS)
+            .filter_map(|article_id| {
+                let article = app.rss_client.get_article_copy_with_body(&article_id)?;
+                let text = article
+                    .text()
+                    .map(|text| search::plain_text(text))
+                    .unwrap_or_default();
+                let entry_title = article.belong_to.and_then(|entry_uuid| {
+                    feed.borrow()
+                        .try_get_entry_by_id(&entry_uuid)
+                        .ok()
+                        .map(|entry| entry.borrow().title())
+                });
+                Some(SearchResult {
+                    title: article.title.clone(),
+                    entry_title,
+                    date: article.sort_date(),
+                    snippet: Snippet::new(&text, &query, 120),
+                    article_id,
+                })
+            })
+            .collect();
+        Ok((results, total))
+    }
+}
+
+/// Parses a date like 2023-11-08 in the local timezone, as the start of the day.
+fn parse_local_date(text: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    use chrono::TimeZone;
+
+    let date = chrono::NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()?;
+    chrono::Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|date| date.with_timezone(&chrono::Utc))
+}
+
+// Lays out a snippet, with the matches highlighted.
+fn snippet_layout(ui: &egui::Ui, snippet: &Snippet) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    let font_id = egui::TextStyle::Body.resolve(ui.style());
+    let visuals = &ui.style().visuals;
+    let plain = egui::TextFormat::simple(font_id.clone(), visuals.text_color());
+    let highlighted = egui::TextFormat {
+        background: visuals.selection.bg_fill,
+        ..egui::TextFormat::simple(font_id, visuals.strong_text_color())
+    };
+    let mut end = 0;
+    for range in &snippet.highlights {
+        job.append(&snippet.text[end..range.start], 0.0, plain.clone());
+        job.append(&snippet.text[range.clone()], 0.0, highlighted.clone());
+        end = range.end;
+    }
+    job.append(&snippet.text[end..], 0.0, plain);
+    job
+}
+
+/// Searches articles by their full text, with filters of subscriptions, folders,
+/// dates and unread, which are kept when coming back from an article.
+#[derive(Clone, Default)]
+pub struct SearchView {
+    state: Rc<RefCell<SearchState>>,
+}
+
+impl View for SearchView {
+    fn show(&self, app: Rc<RSSucks>, ui: &mut egui::Ui) {
+        let mut state = self.state.borrow_mut();
+        let state = &mut *state;
+        let mut changed = false;
+        ui.heading("搜索文章");
+        changed |= ui
+            .add(
+                egui::TextEdit::singleline(&mut state.query)
+                    .hint_text("关键词、\"短语\" 或前缀*")
+                    .desired_width(f32::INFINITY),
+            )
+            .changed();
+        ui.horizontal_wrapped(|ui| {
+            ui.label("订阅");
+            let entry_name = |id: EntryId| {
+                app.rss_client
+                    .get_entry(&id)
+                    .map_or_else(String::new, |entry| entry.get_name())
+            };
+            egui::ComboBox::from_id_source("search_entry")
+                .selected_text(
+                    state
+                        .filter
+                        .entry
+                        .map_or("全部".to_owned(), |id| entry_name(EntryId::from(id))),
+                )
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut state.filter.entry, None, "全部")
+                        .changed();
+                    for entry_id in app.rss_client.list_entry() {
+                        changed |= ui
+                            .selectable_value(
+                                &mut state.filter.entry,
+                                Some(entry_id.get()),
+                                entry_name(entry_id),
+                            )
+                            .changed();
+                    }
+                });
+            ui.label("文件夹");
+            let folder_name = |id: FolderId| app.rss_client.folder_path(&id).unwrap_or_default();
+            egui::ComboBox::from_id_source("search_folder")
+                .selected_text(
+                    state
+                        .filter
+                        .folder
+                        .map_or("全部".to_owned(), |id| folder_name(FolderId::from(id))),
+                )
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut state.filter.folder, None, "全部")
+                        .changed();
+                    for folder_id in app.rss_client.list_folder() {
+                        changed |= ui
+                            .selectable_value(
+                                &mut state.filter.folder,
+                                Some(folder_id.get()),
+                                folder_name(folder_id),
+                            )
+                            .changed();
+                    }
+                });
+            ui.label("日期");
+            for (text, hint) in [
+                (&mut state.since, "起始 YYYY-MM-DD"),
+                (&mut state.until, "截止 YYYY-MM-DD"),
+            ] {
+                changed |= ui
+                    .add(
+                        egui::TextEdit::singleline(text)
+                            .hint_text(hint)
+                            .desired_width(110.0),
+                    )
+                    .changed();
+            }
+            changed |= ui
+                .checkbox(&mut state.filter.unread_only, "只看未读")
+                .changed();
+        });
+        if changed {
+            state.filter.since = parse_local_date(&state.since);
+            // The end date is included.
+            state.filter.until =
+                parse_local_date(&state.until).map(|until| until + chrono::Duration::days(1));
+            state.results = None;
+        }
+        let invalid_date = [&state.since, &state.until]
+            .into_iter()
+            .any(|text| !text.trim().is_empty() && parse_local_date(text).is_none());
+        if invalid_date {
+            ui.colored_label(ui.visuals().error_fg_color, "日期的格式应为 YYYY-MM-DD");
+        }
+        ui.separator();
 
+        if state.query.trim().is_empty() {
+            return;
+        }
+        match app.rss_client.try_index_articles(SEARCH_INDEX_CHUNK) {
+            Err(err) => {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("建立索引失败：{err:#}"),
+                );
+                return;
+            }
+            Ok((indexed, total)) if indexed < total => {
+                ui.label(format!("正在建立索引 {indexed}/{total}"));
+                ui.add(egui::ProgressBar::new(indexed as f32 / total as f32));
+                ui.ctx().request_repaint();
+                return;
+            }
+            Ok(_) => {}
+        }
+        let revision = app.rss_client.revision();
+        if state.searched_at != revision {
+            state.results = None;
+        }
+        if state.results.is_none() {
+            state.results = Some(state.search(&app));
+            state.searched_at = revision;
+        }
+        match state.results.as_ref().unwrap() {
+            Err(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("搜索失败：{err}"));
+            }
+            Ok((results, total)) => {
+                if *total > results.len() {
+                    ui.label(format!(
+                        "找到 {total} 篇文章，显示最相关的 {} 篇",
+                        results.len()
+                    ));
+                } else {
+                    ui.label(format!("找到 {total} 篇文章"));
+                }
+                let date_display = *app.date_display.borrow();
+                let now = chrono::Utc::now();
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    for preview in self.cached_previews.borrow().as_ref().unwrap() {
-                        if ui.add(preview).clicked() {
+                    for result in results.iter() {
+                        ui.add_space(8.0);
+                        if ui
+                            .link(egui::RichText::new(&result.title).strong())
+                            .clicked()
+                        {
                             app.set_view(Rc::new(Box::new(ReaderView::new(
-                                preview.article_id.clone(),
-                                Some(Rc::clone(&current_view)),
+                                result.article_id.clone(),
+                                Some(Rc::new(Box::new(self.clone()))),
                             ))));
                         }
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{} · {}",
+                                result.entry_title.as_deref().unwrap_or_default(),
+                                date_display.format(result.date, now)
+                            ))
+                            .small()
+                            .weak(),
+                        );
+                        ui.label(snippet_layout(ui, &result.snippet));
                     }
                 });
-
-                // ui.label("第一页（暂时还没写翻页的操作");
-            }
-            Err(_) => {
-                ui.label("该订阅尚未同步，现在同步吗？");
-                if ui.button("同步").clicked() {
-                    app.rss_client.try_start_sync_entry(self.id).unwrap();
-                }
             }
-        };
+        }
     }
 }
 
@@ -181,6 +707,8 @@ pub struct NewFeedWindow {
     alias: String,
     folder_id: Option<FolderId>,
     feed_url: String,
+    discovery: Option<Discovery>,
+    selected: Vec<bool>,
 }
 
 impl NewFeedWindow {
@@ -192,8 +720,22 @@ impl NewFeedWindow {
             alias: "稍后自动获取".to_owned(),
             folder_id,
             feed_url: String::new(),
+            discovery: None,
+            selected: vec![],
         }
     }
+
+    /// Subscribes to the feed, which is synchronized with its response if it's fetched already.
+    fn subscribe(&self, url: url::Url, alias: Option<&String>, response: Option<ehttp::Response>) {
+        let entry_id = match self.folder_id {
+            Some(folder_id) => self.client.create_entry_with_folder(url, folder_id, alias),
+            None => self.client.create_entry(url, alias),
+        };
+        let _ = match response {
+            Some(response) => self.client.try_sync_entry_with_response(entry_id, response),
+            None => self.client.try_start_sync_entry(entry_id),
+        };
+    }
 }
 
 impl Window for NewFeedWindow {
@@ -205,7 +747,9 @@ impl Window for NewFeedWindow {
             .title_bar(true)
             .show(ctx, |ui| {
                 let selected = if let Some(select_folder_id) = self.folder_id {
-                    self.client.get_folder(&select_folder_id).unwrap().name()
+                    self.client
+                        .folder_path(&select_folder_id)
+                        .unwrap_or_default()
                 } else {
                     "None".to_owned()
                 };
@@ -215,11 +759,11 @@ impl Window for NewFeedWindow {
                         .selected_text(selected)
                         .show_ui(ui, |ui| {
                             self.client.list_folder().iter().for_each(|folder_id| {
-                                if let Some(folder) = self.client.get_folder(folder_id) {
+                                if let Some(path) = self.client.folder_path(folder_id) {
                                     ui.selectable_value(
                                         &mut self.folder_id,
                                         Some(*folder_id),
-                                        folder.name(),
+                                        path,
                                     );
                                 }
                             });
@@ -233,29 +777,82 @@ impl Window for NewFeedWindow {
                 });
 
                 ui.horizontal(|ui| {
-                    ui.label("订阅链接：");
-                    ui.text_edit_singleline(&mut self.feed_url);
+                    ui.label("订阅或网站链接：");
+                    if ui.text_edit_singleline(&mut self.feed_url).changed() {
+                        self.discovery = None;
+                    }
                 });
 
+                let alias = if self.alias.is_empty() || self.alias == "稍后自动获取" {
+                    None
+                } else {
+                    Some(self.alias.to_owned())
+                };
+
+                match self.discovery.as_ref().map(Discovery::state) {
+                    Some(DiscoveryState::Searching) => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("正在查找订阅……");
+                        });
+                        ui.ctx()
+                            .request_repaint_after(std::time::Duration::from_millis(100));
+                    }
+                    Some(DiscoveryState::IsFeed(feed)) => {
+                        let response = self
+                            .discovery
+                            .as_ref()
+                            .and_then(Discovery::take_feed_response);
+                        self.subscribe(feed.url, alias.as_ref(), response);
+                        self.is_open = false;
+                    }
+                    Some(DiscoveryState::Found(feeds)) => {
+                        ui.label("在该网站上找到了以下订阅：");
+                        self.selected.resize(feeds.len(), true);
+                        for (feed, selected) in feeds.iter().zip(self.selected.iter_mut()) {
+                            ui.checkbox(
+                                selected,
+                                feed.title.as_deref().unwrap_or(feed.url.as_str()),
+                            )
+                            .on_hover_text(feed.url.as_str());
+                        }
+                        if ui.button("订阅所选").clicked() {
+                            // Use the alias only if there is a single feed to subscribe to.
+                            let count = self.selected.iter().filter(|selected| **selected).count();
+                            for (feed, _) in feeds
+                                .into_iter()
+                                .zip(self.selected.iter())
+                                .filter(|(_, selected)| **selected)
+                            {
+                                let alias = if count == 1 { alias.as_ref() } else { None };
+                                self.subscribe(feed.url, alias, None);
+                            }
+                            self.is_open = false;
+                        }
+                    }
+                    Some(DiscoveryState::NotFound(reason)) => {
+                        ui.label(format!("未找到订阅：{reason}"));
+                        // The site may be down for now, or unreachable for discovery on the web.
+                        if let Ok(url) = url::Url::parse(&self.feed_url) {
+                            if ui
+                                .button("仍然订阅")
+                                .on_hover_text("将该链接作为订阅添加")
+                                .clicked()
+                            {
+                                self.subscribe(url, alias.as_ref(), None);
+                                self.is_open = false;
+                            }
+                        }
+                    }
+                    None => {}
+                }
+
                 ui.horizontal(|ui| {
                     match url::Url::parse(&self.feed_url) {
                         Ok(url) => {
                             if ui.button("✔").on_hover_text("确定").clicked() {
-                                let alias = if self.alias.is_empty() || self.alias == "稍后自动获取"
-                                {
-                                    None
-                                } else {
-                                    Some(&self.alias)
-                                };
-                                match self.folder_id {
-                                    Some(folder_id) => {
-                                        self.client.create_entry_with_folder(url, folder_id, alias);
-                                    }
-                                    None => {
-                                        self.client.create_entry(url, alias);
-                                    }
-                                }
-                                self.is_open = false;
+                                self.selected.clear();
+                                self.discovery = Some(self.client.discover_feeds(url));
                             }
                         }
                         Err(err) => {
@@ -279,15 +876,18 @@ pub struct NewFolderWindow {
     id: egui::Id,
     is_open: bool,
     folder_name: String,
+    /// The folder which the new folder is created inside, if any.
+    parent: Option<FolderId>,
 }
 
 impl NewFolderWindow {
-    pub fn new(client: RssClient) -> Self {
+    pub fn new(client: RssClient, parent: Option<FolderId>) -> Self {
         Self {
             client,
             id: egui::Id::new(Uuid::new_v4()),
             is_open: true,
             folder_name: String::new(),
+            parent,
         }
     }
 }
@@ -300,13 +900,37 @@ impl Window for NewFolderWindow {
             .collapsible(true)
             .title_bar(true)
             .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("上级文件夹");
+                    let selected = self
+                        .parent
+                        .and_then(|parent| self.client.folder_path(&parent))
+                        .unwrap_or("不选择".to_owned());
+                    egui::ComboBox::from_id_source(self.id.with("parent"))
+                        .selected_text(selected)
+                        .show_ui(ui, |ui| {
+                            for folder_id in self.client.list_folder() {
+                                if let Some(path) = self.client.folder_path(&folder_id) {
+                                    ui.selectable_value(&mut self.parent, Some(folder_id), path);
+                                }
+                            }
+                            ui.selectable_value(&mut self.parent, None, "不选择");
+                        });
+                });
                 ui.horizontal(|ui| {
                     ui.label("文件夹名称：");
                     ui.text_edit_singleline(&mut self.folder_name);
                 });
                 ui.horizontal(|ui| {
                     if ui.button("确定").clicked() {
-                        self.client.create_folder(&self.folder_name);
+                        match self.parent {
+                            Some(parent) => {
+                                let _ = self.client.try_create_folder_in(&self.folder_name, parent);
+                            }
+                            None => {
+                                self.client.create_folder(&self.folder_name);
+                            }
+                        }
                         self.is_open = false;
                     }
                     if ui.button("取消").clicked() {
@@ -321,86 +945,1013 @@ impl Window for NewFolderWindow {
     }
 }
 
-pub struct LeftSidePanel<'app> {
-    app: &'app RSSucks,
+/// A subscription or folder which can be moved into another folder.
+#[derive(Clone, Copy)]
+pub enum Movable {
+    Entry(EntryId),
+    Folder(FolderId),
 }
 
-impl<'app> LeftSidePanel<'app> {
-    pub fn new(app: &'app RSSucks) -> Self {
-        Self { app }
-    }
+/// Moves a subscription or folder into a folder or to the top level,
+/// usable with the keyboard only.
+pub struct MoveWindow {
+    client: RssClient,
+    item: Movable,
+    id: egui::Id,
+    is_open: bool,
+    /// The folder to move into, or `None` for the top level.
+    destination: Option<FolderId>,
+    error: Option<String>,
+    /// Was focus in this window at the last frame? Escape takes focus away before it's handled.
+    focused: bool,
+    /// Has the window been shown? Focus is put in it only when it's shown at first.
+    shown: bool,
 }
 
-impl<'app> LeftSidePanel<'app> {
-    pub fn show(&mut self, ctx: &egui::Context) {
-        egui::SidePanel::left("left_panel").show(ctx, move |ui| {
-            let former_visuals = self.app.visuals.borrow().clone();
-            ui.ctx().set_visuals(former_visuals.clone());
-            if let Some(visuals) = former_visuals.light_dark_small_toggle_button(ui) {
-                ui.ctx().set_visuals(visuals.clone());
-                if visuals != former_visuals {
-                    self.app.visuals.replace(visuals);
-                }
-            }
-            ui.heading("Rust SuckS");
-            ui.label("用 Rust 写的 RSS 阅读器");
-            ui.hyperlink_to("RSSucks on Github", "https://github.com/jyi2ya/RSSucks");
+impl MoveWindow {
+    pub fn new(client: RssClient, item: Movable) -> Self {
+        let destination = match item {
+            Movable::Entry(id) => client.get_entry(&id).and_then(|entry| entry.folder()),
+            Movable::Folder(id) => client.get_folder(&id).and_then(|folder| folder.parent()),
+        };
+        Self {
+            client,
+            item,
+            id: egui::Id::new(Uuid::new_v4()),
+            is_open: true,
+            destination,
+            error: None,
+            focused: false,
+            shown: false,
+        }
+    }
 
-            ui.separator();
+    fn try_move(&self) -> anyhow::Result<()> {
+        match self.item {
+            Movable::Entry(id) => self.client.try_move_entry(id, self.destination),
+            Movable::Folder(id) => self.client.try_move_folder(id, self.destination),
+        }
+    }
+}
 
-            ui.horizontal(|ui| {
-                ui.label("订阅列表");
-                let app = self.app as *const RSSucks as *mut RSSucks;
-                if ui.button("📥").on_hover_text("导入配置").clicked() {
-                    async_std::task::block_on(async move {
-                        if let Some(file) = rfd::AsyncFileDialog::new().pick_file().await {
-                            let data = file.read().await;
-                            if let Ok(opml) = Opml::try_from_str(&String::from_utf8_lossy(&data)) {
-                                if let Ok(feed) = Feed::try_from(opml) {
-                                    unsafe {
-                                        (*app).import_feed(feed);
-                                    }
-                                }
-                            }
+impl Window for MoveWindow {
+    fn show(&mut self, ctx: &egui::Context) {
+        let name = match self.item {
+            Movable::Entry(id) => self.client.get_entry(&id).map(|entry| entry.get_name()),
+            Movable::Folder(id) => self.client.get_folder(&id).map(|folder| folder.name()),
+        };
+        let Some(name) = name else {
+            self.is_open = false;
+            return;
+        };
+        // A folder can't be moved into itself or the folders inside it.
+        let excluded = match self.item {
+            Movable::Entry(_) => vec![],
+            Movable::Folder(id) => self.client.try_list_folder_in_tree(id).unwrap_or_default(),
+        };
+        let mut destinations: Vec<_> = self
+            .client
+            .list_folder()
+            .into_iter()
+            .filter(|folder_id| !excluded.contains(folder_id))
+            .filter_map(|folder_id| Some((self.client.folder_path(&folder_id)?, folder_id)))
+            .collect();
+        destinations.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let first_shown = !std::mem::replace(&mut self.shown, true);
+
+        egui::Window::new("移动到…")
+            .id(self.id)
+            .movable(true)
+            .collapsible(true)
+            .title_bar(true)
+            .show(ctx, |ui| {
+                ui.label(format!("将“{name}”移动到："));
+                // Keys are handled only when focus is in this window, not in others.
+                let mut destination_focused = false;
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        let top_level =
+                            ui.selectable_value(&mut self.destination, None, "（顶层）");
+                        if first_shown {
+                            top_level.request_focus();
                         }
-                    });
-                }
-                if ui.button("📤").on_hover_text("导出配置").clicked() {
-                    async_std::task::block_on(async move {
-                        if let Some(file) = rfd::AsyncFileDialog::new().save_file().await {
-                            let opml =
-                                Opml::from(unsafe { (*app).rss_client.get().borrow().to_owned() });
-                            if let Ok(data) = opml.try_dump() {
-                                let _ = file.write(data.as_bytes()).await;
-                            }
+                        destination_focused |= top_level.has_focus();
+                        for (path, folder_id) in destinations {
+                            destination_focused |= ui
+                                .selectable_value(&mut self.destination, Some(folder_id), path)
+                                .has_focus();
                         }
                     });
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
                 }
-                if ui.button("🔁").on_hover_text("拉取全部").clicked() {
-                    let _ = self.app.rss_client.try_start_sync_all();
-                }
-                if ui.button("新建文件夹").clicked() {
-                    self.app
-                        .add_window(NewFolderWindow::new(self.app.rss_client.clone()));
-                }
-                if ui.button("新建订阅").clicked() {
-                    self.app
+                ui.horizontal(|ui| {
+                    let move_button = ui.button("移动");
+                    let cancel_button = ui.button("取消");
+                    let was_focused = std::mem::replace(
+                        &mut self.focused,
+                        destination_focused || move_button.has_focus() || cancel_button.has_focus(),
+                    );
+                    // Enter on a destination selects it and moves there at once.
+                    if move_button.clicked()
+                        || destination_focused
+                            && ui.input_mut(|input| {
+                                input.consume_key(egui::Modifiers::NONE, egui::Key::Enter)
+                            })
+                    {
+                        match self.try_move() {
+                            Ok(()) => self.is_open = false,
+                            Err(error) => self.error = Some(error.to_string()),
+                        }
+                    }
+                    if cancel_button.clicked()
+                        || was_focused
+                            && ui.input_mut(|input| {
+                                input.consume_key(egui::Modifiers::NONE, egui::Key::Escape)
+                            })
+                    {
+                        self.is_open = false;
+                    }
+                });
+            });
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+}
+
+fn describe_condition(condition: &Condition) -> &'static str {
+    match condition {
+        Condition::Unread => "未读",
+        Condition::Flagged(_) => "带有标记",
+        Condition::Tagged(_) => "带有标签",
+        Condition::Category(_) => "属于分类",
+        Condition::InEntry(_) => "来自订阅",
+        Condition::InFolder(_) => "来自文件夹",
+        Condition::WithinDays(_) => "最近几天内",
+        Condition::TitleMatches(_) => "标题匹配",
+    }
+}
+
+/// Creates or edits a smart folder, which collects the articles matching a rule.
+pub struct SmartFolderWindow {
+    client: RssClient,
+    /// The smart folder edited, `None` if it's a new one.
+    folder_id: Option<SmartFolderId>,
+    id: egui::Id,
+    is_open: bool,
+    name: String,
+    rule: Rule,
+    error: Option<String>,
+}
+
+impl SmartFolderWindow {
+    pub fn new(client: RssClient) -> Self {
+        Self {
+            client,
+            folder_id: None,
+            id: egui::Id::new(Uuid::new_v4()),
+            is_open: true,
+            name: String::new(),
+            rule: Rule::default(),
+            error: None,
+        }
+    }
+
+    pub fn edit(client: RssClient, folder_id: SmartFolderId) -> Self {
+        let folder = client.get_smart_folder(&folder_id);
+        Self {
+            folder_id: Some(folder_id),
+            name: folder
+                .as_ref()
+                .map(|folder| folder.title().to_owned())
+                .unwrap_or_default(),
+            rule: folder.map(|folder| folder.rule).unwrap_or_default(),
+            ..Self::new(client)
+        }
+    }
+
+    /// Returns a condition of each kind, which those of folders and entries are missing from
+    /// if there are none.
+    fn condition_kinds(&self) -> Vec<Condition> {
+        let mut kinds = vec![
+            Condition::Unread,
+            Condition::Flagged(Flag::Starred),
+            Condition::Tagged(String::new()),
+            Condition::Category(String::new()),
+        ];
+        if let Some(entry_id) = self.client.list_entry().first() {
+            kinds.push(Condition::InEntry(entry_id.get()));
+        }
+        if let Some(folder_id) = self.client.list_folder().first() {
+            kinds.push(Condition::InFolder(folder_id.get()));
+        }
+        kinds.push(Condition::WithinDays(7));
+        kinds.push(Condition::TitleMatches(String::new()));
+        kinds
+    }
+
+    /// Shows the editor of the value of a condition.
+    fn condition_ui(&self, ui: &mut egui::Ui, index: usize, condition: &mut Condition) {
+        match condition {
+            Condition::Unread => {}
+            Condition::Flagged(flag) => {
+                let describe = |flag: Flag| {
+                    let (icon, description) = article::describe_flag(flag);
+                    format!("{icon} {description}")
+                };
+                egui::ComboBox::from_id_source(self.id.with(("flag", index)))
+                    .selected_text(describe(*flag))
+                    .show_ui(ui, |ui| {
+                        for value in Flag::ALL {
+                            ui.selectable_value(flag, value, describe(value));
+                        }
+                    });
+            }
+            Condition::Tagged(name) => {
+                ui.add(egui::TextEdit::singleline(name).hint_text("标签名称"));
+            }
+            Condition::Category(name) => {
+                ui.add(egui::TextEdit::singleline(name).hint_text("分类名称，如 release"));
+            }
+            Condition::InEntry(entry_id) => {
+                let name_of = |entry_id| {
+                    self.client
+                        .get_entry(&EntryId::from(entry_id))
+                        .map_or("（已删除）".to_owned(), |entry| entry.get_name())
+                };
+                egui::ComboBox::from_id_source(self.id.with(("entry", index)))
+                    .selected_text(name_of(*entry_id))
+                    .show_ui(ui, |ui| {
+                        for id in self.client.list_entry() {
+                            ui.selectable_value(entry_id, id.get(), name_of(id.get()));
+                        }
+                    });
+            }
+            Condition::InFolder(folder_id) => {
+                let path_of = |folder_id| {
+                    self.client
+                        .folder_path(&FolderId::from(folder_id))
+                        .unwrap_or("（已删除）".to_owned())
+                };
+                egui::ComboBox::from_id_source(self.id.with(("folder", index)))
+                    .selected_text(path_of(*folder_id))
+                    .show_ui(ui, |ui| {
+                        for id in self.client.list_folder() {
+                            ui.selectable_value(folder_id, id.get(), path_of(id.get()));
+                        }
+                    });
+            }
+            Condition::WithinDays(days) => {
+                ui.add(
+                    egui::DragValue::new(days)
+                        .clamp_range(1..=365)
+                        .suffix(" 天"),
+                );
+            }
+            Condition::TitleMatches(pattern) => {
+                ui.add(egui::TextEdit::singleline(pattern).hint_text("正则表达式，如 rust|wasm"));
+            }
+        }
+    }
+
+    fn try_save(&self) -> anyhow::Result<()> {
+        // Invalid patterns are caught before saved.
+        self.rule.try_compile(|_| vec![], chrono::Utc::now())?;
+        match self.folder_id {
+            Some(folder_id) => {
+                self.client
+                    .try_update_smart_folder(folder_id, &self.name, self.rule.clone())?
+            }
+            None => {
+                let name = match self.name.trim() {
+                    "" => "智能文件夹",
+                    name => name,
+                };
+                self.client.create_smart_folder(name, self.rule.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Window for SmartFolderWindow {
+    fn show(&mut self, ctx: &egui::Context) {
+        let kinds = self.condition_kinds();
+        egui::Window::new(if self.folder_id.is_some() {
+            "编辑智能文件夹"
+        } else {
+            "新建智能文件夹"
+        })
+        .id(self.id)
+        .movable(true)
+        .collapsible(true)
+        .title_bar(true)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("名称：");
+                ui.text_edit_singleline(&mut self.name);
+            });
+            ui.horizontal(|ui| {
+                ui.label("文章满足");
+                ui.radio_value(&mut self.rule.match_any, false, "全部条件");
+                ui.radio_value(&mut self.rule.match_any, true, "任一条件");
+            });
+            if self.rule.conditions.is_empty() {
+                ui.weak("没有条件时包含所有文章");
+            }
+            let mut conditions = std::mem::take(&mut self.rule.conditions);
+            let mut removed = None;
+            for (index, condition) in conditions.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source(self.id.with(("kind", index)))
+                        .selected_text(describe_condition(condition))
+                        .show_ui(ui, |ui| {
+                            for kind in &kinds {
+                                let same_kind = std::mem::discriminant(kind)
+                                    == std::mem::discriminant(condition);
+                                if ui
+                                    .selectable_label(same_kind, describe_condition(kind))
+                                    .clicked()
+                                    && !same_kind
+                                {
+                                    *condition = kind.clone();
+                                }
+                            }
+                        });
+                    self.condition_ui(ui, index, condition);
+                    if ui.button("🗙").on_hover_text("删除条件").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = removed {
+                conditions.remove(index);
+            }
+            self.rule.conditions = conditions;
+            if ui.button("➕ 添加条件").clicked() {
+                self.rule.conditions.push(Condition::Unread);
+            }
+
+            if let Some(error) = &self.error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("保存").clicked() {
+                    match self.try_save() {
+                        Ok(()) => self.is_open = false,
+                        Err(err) => self.error = Some(format!("{err:#}")),
+                    }
+                }
+                if ui.button("取消").clicked() {
+                    self.is_open = false;
+                }
+            });
+        });
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+}
+
+pub struct SettingsWindow {
+    client: RssClient,
+    scheduler: Scheduler,
+    date_display: Rc<RefCell<DateDisplay>>,
+    id: egui::Id,
+    is_open: bool,
+}
+
+impl SettingsWindow {
+    pub fn new(
+        client: RssClient,
+        scheduler: Scheduler,
+        date_display: Rc<RefCell<DateDisplay>>,
+    ) -> Self {
+        Self {
+            client,
+            scheduler,
+            date_display,
+            id: egui::Id::new(Uuid::new_v4()),
+            is_open: true,
+        }
+    }
+}
+
+impl Window for SettingsWindow {
+    fn show(&mut self, ctx: &egui::Context) {
+        let settings = self.scheduler.settings();
+        egui::Window::new("全局设置")
+            .id(self.id)
+            .open(&mut self.is_open)
+            .movable(true)
+            .collapsible(true)
+            .title_bar(true)
+            .show(ctx, |ui| {
+                let mut settings = settings.borrow_mut();
+                let mut changed = ui.checkbox(&mut settings.enabled, "自动刷新订阅").changed();
+                ui.horizontal(|ui| {
+                    ui.label("默认刷新间隔：");
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut settings.default_interval)
+                                .clamp_range(5..=60 * 24 * 7)
+                                .suffix(" 分钟"),
+                        )
+                        .changed();
+                });
+                if changed {
+                    self.scheduler.reschedule();
+                }
+
+                ui.separator();
+
+                let limits = self.client.sync_limits();
+                let mut limits = limits.borrow_mut();
+                ui.horizontal(|ui| {
+                    ui.label("同时拉取的订阅数：");
+                    ui.add(egui::DragValue::new(&mut limits.max_concurrent).clamp_range(1..=64));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("同一站点同时拉取的订阅数：");
+                    ui.add(egui::DragValue::new(&mut limits.max_per_host).clamp_range(1..=16));
+                });
+
+                ui.separator();
+
+                ui.label("文章保留规则，会在每次拉取后清理旧文章");
+                let mut policy = self.client.retention_policy();
+                let mut changed = retention_ui(ui, &mut policy.retention, "不限");
+                changed |= ui
+                    .checkbox(&mut policy.keep_unread, "从不清理未读文章")
+                    .on_hover_text("标星、稍后读、归档和带标签的文章总是保留")
+                    .changed();
+                if changed {
+                    self.client.set_retention_policy(policy);
+                }
+
+                ui.separator();
+
+                let mut date_display = self.date_display.borrow_mut();
+                ui.horizontal(|ui| {
+                    ui.label("日期显示：");
+                    ui.radio_value(&mut date_display.style, DateStyle::Absolute, "具体时间");
+                    ui.radio_value(&mut date_display.style, DateStyle::Relative, "相对时间");
+                });
+                ui.horizontal(|ui| {
+                    ui.label("时区：");
+                    let timezone = &mut date_display.timezone;
+                    egui::ComboBox::from_id_source(self.id.with("timezone"))
+                        .selected_text(timezone.describe())
+                        .show_ui(ui, |ui| {
+                            for choice in [DisplayTimezone::Local, DisplayTimezone::Utc] {
+                                ui.selectable_value(timezone, choice, choice.describe());
+                            }
+                            let fixed = matches!(timezone, DisplayTimezone::Fixed(_));
+                            if ui.selectable_label(fixed, "固定偏移").clicked() && !fixed {
+                                *timezone = DisplayTimezone::Fixed(0);
+                            }
+                        });
+                    if let DisplayTimezone::Fixed(minutes) = timezone {
+                        let mut hours = *minutes as f64 / 60.0;
+                        let response = ui.add(
+                            egui::DragValue::new(&mut hours)
+                                .clamp_range(-12.0..=14.0)
+                                .speed(0.25)
+                                .max_decimals(2)
+                                .suffix(" 小时"),
+                        );
+                        if response.changed() {
+                            *minutes = (hours * 60.0).round() as i32;
+                        }
+                    }
+                });
+            });
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+}
+
+/// Edits retention rules, where rules not set are inherited from the parent.
+fn retention_ui(ui: &mut egui::Ui, retention: &mut Retention, inherited: &str) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let mut limited = retention.max_articles.is_some();
+        changed |= ui.checkbox(&mut limited, "最多保留文章数").changed();
+        if limited {
+            let mut max = retention.max_articles.unwrap_or(200);
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut max)
+                        .clamp_range(1..=100000)
+                        .suffix(" 篇"),
+                )
+                .changed();
+            retention.max_articles = Some(max);
+        } else {
+            retention.max_articles = None;
+            ui.weak(inherited);
+        }
+    });
+    ui.horizontal(|ui| {
+        let mut limited = retention.max_age_days.is_some();
+        changed |= ui.checkbox(&mut limited, "最长保留时间").changed();
+        if limited {
+            let mut days = retention.max_age_days.unwrap_or(30);
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut days)
+                        .clamp_range(1..=36500)
+                        .suffix(" 天"),
+                )
+                .changed();
+            retention.max_age_days = Some(days);
+        } else {
+            retention.max_age_days = None;
+            ui.weak(inherited);
+        }
+    });
+    changed
+}
+
+pub struct FolderSettingsWindow {
+    client: RssClient,
+    folder_id: FolderId,
+    id: egui::Id,
+    is_open: bool,
+}
+
+impl FolderSettingsWindow {
+    pub fn new(client: RssClient, folder_id: FolderId) -> Self {
+        Self {
+            client,
+            folder_id,
+            id: egui::Id::new(Uuid::new_v4()),
+            is_open: true,
+        }
+    }
+}
+
+impl Window for FolderSettingsWindow {
+    fn show(&mut self, ctx: &egui::Context) {
+        let Some(folder) = self.client.get_folder(&self.folder_id) else {
+            self.is_open = false;
+            return;
+        };
+        egui::Window::new(format!("文件夹设置：{}", folder.name()))
+            .id(self.id)
+            .open(&mut self.is_open)
+            .movable(true)
+            .collapsible(true)
+            .title_bar(true)
+            .show(ctx, |ui| {
+                ui.label("文章保留规则，会在每次拉取后清理旧文章");
+                let mut retention = self
+                    .client
+                    .folder_retention(self.folder_id)
+                    .unwrap_or_default();
+                if retention_ui(ui, &mut retention, "沿用全局设置") {
+                    self.client.set_folder_retention(self.folder_id, retention);
+                }
+            });
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+}
+
+pub struct EntrySettingsWindow {
+    client: RssClient,
+    scheduler: Scheduler,
+    entry_id: EntryId,
+    id: egui::Id,
+    is_open: bool,
+}
+
+impl EntrySettingsWindow {
+    pub fn new(client: RssClient, scheduler: Scheduler, entry_id: EntryId) -> Self {
+        Self {
+            client,
+            scheduler,
+            entry_id,
+            id: egui::Id::new(Uuid::new_v4()),
+            is_open: true,
+        }
+    }
+}
+
+impl Window for EntrySettingsWindow {
+    fn show(&mut self, ctx: &egui::Context) {
+        let Some(entry) = self.client.get_entry(&self.entry_id) else {
+            self.is_open = false;
+            return;
+        };
+        egui::Window::new(format!("订阅设置：{}", entry.get_name()))
+            .id(self.id)
+            .open(&mut self.is_open)
+            .movable(true)
+            .collapsible(true)
+            .title_bar(true)
+            .show(ctx, |ui| {
+                let default_interval = self.scheduler.settings().borrow().default_interval;
+                let mut interval = self.client.entry_refresh_interval(self.entry_id);
+                let mut custom = interval.is_some();
+                let mut changed = ui.checkbox(&mut custom, "自定义刷新间隔").changed();
+                if custom {
+                    let mut minutes = interval.unwrap_or(default_interval);
+                    ui.horizontal(|ui| {
+                        ui.label("刷新间隔：");
+                        changed |= ui
+                            .add(
+                                egui::DragValue::new(&mut minutes)
+                                    .clamp_range(5..=60 * 24 * 7)
+                                    .suffix(" 分钟"),
+                            )
+                            .changed();
+                    });
+                    interval = Some(minutes);
+                } else {
+                    interval = None;
+                }
+                if changed {
+                    self.client
+                        .set_entry_refresh_interval(self.entry_id, interval);
+                    self.scheduler.reschedule();
+                }
+
+                ui.separator();
+
+                ui.label("文章保留规则，会在每次拉取后清理旧文章");
+                let mut retention = self
+                    .client
+                    .entry_retention(self.entry_id)
+                    .unwrap_or_default();
+                if retention_ui(ui, &mut retention, "沿用文件夹或全局设置") {
+                    self.client.set_entry_retention(self.entry_id, retention);
+                }
+
+                ui.separator();
+
+                if let Some(hints) = self.client.entry_refresh_hints(self.entry_id) {
+                    if let Some(ttl) = hints.ttl {
+                        ui.label(format!("订阅建议的缓存时间：{ttl} 分钟"));
+                    }
+                    if let Some(update_interval) = hints.update_interval {
+                        ui.label(format!("订阅声明的更新间隔：{update_interval} 分钟"));
+                    }
+                    if !hints.skip_hours.is_empty() {
+                        ui.label(format!(
+                            "跳过的时段（GMT）：{}",
+                            hints
+                                .skip_hours
+                                .iter()
+                                .map(u32::to_string)
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ));
+                    }
+                    if !hints.skip_days.is_empty() {
+                        ui.label(format!(
+                            "跳过的日期：{}",
+                            hints
+                                .skip_days
+                                .iter()
+                                .map(|day| day.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ));
+                    }
+                }
+            });
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+}
+
+pub struct RestoreWindow {
+    app: RSSucks,
+    backup: Option<Backup>,
+    id: egui::Id,
+    is_open: bool,
+    confirming_replace: bool,
+}
+
+impl RestoreWindow {
+    pub fn new(app: RSSucks, backup: Backup) -> Self {
+        Self {
+            app,
+            backup: Some(backup),
+            id: egui::Id::new(Uuid::new_v4()),
+            is_open: true,
+            confirming_replace: false,
+        }
+    }
+}
+
+impl Window for RestoreWindow {
+    fn show(&mut self, ctx: &egui::Context) {
+        let Some(backup) = &self.backup else {
+            self.is_open = false;
+            return;
+        };
+        let mut mode = None;
+        egui::Window::new("从备份恢复")
+            .id(self.id)
+            .open(&mut self.is_open)
+            .movable(true)
+            .collapsible(true)
+            .title_bar(true)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "备份于 {}，包含 {} 个订阅、{} 篇文章。",
+                    backup
+                        .created
+                        .with_timezone(&chrono::Local)
+                        .format("%Y/%m/%d %H:%M"),
+                    backup.library.get_all_entry_ids().len(),
+                    backup.articles.len()
+                ));
+                ui.separator();
+                if ui
+                    .button("合并到当前文章库")
+                    .on_hover_text(
+                        "按订阅地址合并，添加缺少的订阅和文章，合并已读、标记和标签，保留当前设置",
+                    )
+                    .clicked()
+                {
+                    mode = Some(RestoreMode::Merge);
+                }
+                if self.confirming_replace {
+                    ui.label("当前的订阅、文章和设置都会被覆盖，确定吗？");
+                    ui.horizontal(|ui| {
+                        if ui.button("确定替换").clicked() {
+                            mode = Some(RestoreMode::Replace);
+                        }
+                        if ui.button("取消").clicked() {
+                            self.confirming_replace = false;
+                        }
+                    });
+                } else if ui
+                    .button("替换当前文章库")
+                    .on_hover_text("用备份中的订阅、文章和设置替换当前的")
+                    .clicked()
+                {
+                    self.confirming_replace = true;
+                }
+            });
+        if let Some(mode) = mode {
+            let backup = self.backup.take().expect("Backup is restored only once");
+            let message = match self.app.try_restore(backup, mode) {
+                Ok((entries, articles)) => format!("已恢复 {entries} 个订阅、{articles} 篇文章。"),
+                Err(err) => format!("恢复失败：{err:#}"),
+            };
+            self.app
+                .add_window(InfoWindow::new("从备份恢复".to_owned(), message));
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.is_open
+    }
+}
+
+pub struct LeftSidePanel<'app> {
+    app: &'app RSSucks,
+}
+
+impl<'app> LeftSidePanel<'app> {
+    pub fn new(app: &'app RSSucks) -> Self {
+        Self { app }
+    }
+}
+
+impl<'app> LeftSidePanel<'app> {
+    pub fn show(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("left_panel").show(ctx, move |ui| {
+            let former_visuals = self.app.visuals.borrow().clone();
+            ui.ctx().set_visuals(former_visuals.clone());
+            if let Some(visuals) = former_visuals.light_dark_small_toggle_button(ui) {
+                ui.ctx().set_visuals(visuals.clone());
+                if visuals != former_visuals {
+                    self.app.visuals.replace(visuals);
+                }
+            }
+            ui.heading("Rust SuckS");
+            ui.label("用 Rust 写的 RSS 阅读器");
+            ui.hyperlink_to("RSSucks on Github", "https://github.com/jyi2ya/RSSucks");
+
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                ui.label("订阅列表");
+                let unread = self.app.rss_client.unread_count();
+                if unread > 0 {
+                    ui.label(egui::RichText::new(format!("{unread} 篇未读")).small());
+                }
+                let app = self.app as *const RSSucks as *mut RSSucks;
+                if ui.button("📥").on_hover_text("导入配置").clicked() {
+                    async_std::task::block_on(async move {
+                        if let Some(file) = rfd::AsyncFileDialog::new().pick_file().await {
+                            let data = file.read().await;
+                            if let Ok(opml) = Opml::try_from_str(&String::from_utf8_lossy(&data)) {
+                                if let Ok(feed) = Feed::try_from(opml) {
+                                    unsafe {
+                                        (*app).import_feed(feed);
+                                    }
+                                }
+                            }
+                        }
+                    });
+                }
+                if ui.button("📤").on_hover_text("导出配置").clicked() {
+                    async_std::task::block_on(async move {
+                        if let Some(file) = rfd::AsyncFileDialog::new().save_file().await {
+                            let opml =
+                                Opml::from(unsafe { (*app).rss_client.get().borrow().to_owned() });
+                            if let Ok(data) = opml.try_dump() {
+                                let _ = file.write(data.as_bytes()).await;
+                            }
+                        }
+                    });
+                }
+                if ui.button("💾").on_hover_text("备份文章库").clicked() {
+                    match self.app.try_backup() {
+                        Ok(data) => async_std::task::block_on(async move {
+                            if let Some(file) = rfd::AsyncFileDialog::new()
+                                .set_file_name("rssucks-backup.json")
+                                .save_file()
+                                .await
+                            {
+                                let _ = file.write(&data).await;
+                            }
+                        }),
+                        Err(err) => self
+                            .app
+                            .add_window(InfoWindow::new("备份失败".to_owned(), format!("{err:#}"))),
+                    }
+                }
+                if ui.button("♻").on_hover_text("从备份恢复").clicked() {
+                    let app = self.app.clone();
+                    async_std::task::block_on(async move {
+                        if let Some(file) = rfd::AsyncFileDialog::new().pick_file().await {
+                            match Backup::try_read(&file.read().await) {
+                                Ok(backup) => {
+                                    app.add_window(RestoreWindow::new(app.clone(), backup))
+                                }
+                                Err(err) => app.add_window(InfoWindow::new(
+                                    "无法读取备份".to_owned(),
+                                    format!("{err:#}"),
+                                )),
+                            }
+                        }
+                    });
+                }
+                ui.menu_button("✔", |ui| {
+                    widget::mark_read_menu(ui, |before| {
+                        self.app.rss_client.mark_all_read(before);
+                    });
+                })
+                .response
+                .on_hover_text("全部标为已读");
+                ui.menu_button("⇅", |ui| widget::sort_menu(ui, &self.app.rss_client))
+                    .response
+                    .on_hover_text("排序");
+                if ui.button("🔍").on_hover_text("搜索").clicked() {
+                    self.app.set_view(Rc::new(Box::new(SearchView::default())));
+                }
+                if ui.button("🔁").on_hover_text("拉取全部").clicked() {
+                    let _ = self.app.rss_client.try_start_sync_all();
+                }
+                if ui.button("新建文件夹").clicked() {
+                    self.app
+                        .add_window(NewFolderWindow::new(self.app.rss_client.clone(), None));
+                }
+                if ui.button("新建订阅").clicked() {
+                    self.app
                         .add_window(NewFeedWindow::new(self.app.rss_client.clone(), None));
                 }
+                if ui.button("⚙").on_hover_text("全局设置").clicked() {
+                    self.app.add_window(SettingsWindow::new(
+                        self.app.rss_client.clone(),
+                        self.app.scheduler.clone(),
+                        Rc::clone(&self.app.date_display),
+                    ));
+                }
             });
 
+            if let Some((finished, total)) = self.app.rss_client.sync_progress() {
+                ui.add(
+                    egui::ProgressBar::new(finished as f32 / total as f32)
+                        .text(format!("正在拉取 {finished}/{total} 个订阅"))
+                        .animate(true),
+                );
+            }
+
             ui.separator();
 
             egui::ScrollArea::vertical().show(ui, |ui| {
+                for (label, source) in [
+                    ("📰 全部", FlowSource::All),
+                    ("● 未读", FlowSource::Unread),
+                    ("📅 今天", FlowSource::Today),
+                ] {
+                    if ui.button(label).clicked() {
+                        self.app.set_view(Rc::new(Box::new(FlowView::new(source))));
+                    }
+                }
+                for flag in [Flag::Starred, Flag::ReadLater] {
+                    let (icon, description) = article::describe_flag(flag);
+                    if ui.button(format!("{icon} {description}")).clicked() {
+                        self.app.set_view(Rc::new(Box::new(CollectionView::new(
+                            Collection::Flagged(flag),
+                        ))));
+                    }
+                }
+                let tags = self.app.rss_client.list_tag();
+                if !tags.is_empty() {
+                    egui::CollapsingHeader::new("🏷 标签")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            for (name, count) in tags {
+                                let color =
+                                    article::tag_color32(self.app.rss_client.tag_color(&name));
+                                let label =
+                                    egui::RichText::new(format!("{name} ({count})")).color(color);
+                                if ui.button(label).clicked() {
+                                    self.app.set_view(Rc::new(Box::new(CollectionView::new(
+                                        Collection::Tagged(name),
+                                    ))));
+                                }
+                            }
+                        });
+                }
+                egui::CollapsingHeader::new("🔮 智能文件夹")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for id in self.app.rss_client.list_smart_folder() {
+                            let Some(folder) = self.app.rss_client.get_smart_folder(&id) else {
+                                continue;
+                            };
+                            let label =
+                                match self.app.rss_client.try_count_article_by_smart_folder(id) {
+                                    Ok(count) => format!("{} ({count})", folder.title()),
+                                    Err(_) => format!("{} (⚠)", folder.title()),
+                                };
+                            let button = ui.button(label);
+                            if button.clicked() {
+                                self.app.set_view(Rc::new(Box::new(CollectionView::new(
+                                    Collection::Smart(id),
+                                ))));
+                            }
+                            button.context_menu(|ui| {
+                                if ui.button("编辑规则…").clicked() {
+                                    self.app.add_window(SmartFolderWindow::edit(
+                                        self.app.rss_client.clone(),
+                                        id,
+                                    ));
+                                    ui.close_menu();
+                                }
+                                if ui.button("删除").clicked() {
+                                    self.app.rss_client.delete_smart_folder(id);
+                                    ui.close_menu();
+                                }
+                            });
+                        }
+                        if ui.button("➕ 新建智能文件夹").clicked() {
+                            self.app
+                                .add_window(SmartFolderWindow::new(self.app.rss_client.clone()));
+                        }
+                    });
+
                 ui.separator();
 
-                for folder_id in self.app.rss_client.list_folder() {
+                if widget::dragged_entry(ui.ctx()).is_some() {
+                    let response = egui::Frame::group(ui.style())
+                        .show(ui, |ui| {
+                            ui.set_width(ui.available_width());
+                            ui.weak("拖到这里以移出文件夹");
+                        })
+                        .response;
+                    widget::entry_drop_target(ui, response.rect, self.app, None);
+                }
+
+                for folder_id in self.app.rss_client.list_top_level_folder() {
                     ui.add(CollapsingFolder::new(self.app, folder_id));
                 }
 
                 for feed_id in self.app.rss_client.list_orphan_entry() {
                     ui.add(widget::FeedMinimal::new(self.app, feed_id));
                 }
+
+                widget::finish_entry_drag(ui.ctx());
             });
         });
     }
@@ -408,8 +1959,14 @@ impl<'app> LeftSidePanel<'app> {
 
 pub trait View {
     fn show(&self, app: Rc<RSSucks>, ui: &mut egui::Ui);
+
+    /// Entries shown by the view, which are synchronized first while it's shown.
+    fn sync_focus(&self) -> Option<SyncFocus> {
+        None
+    }
 }
 
+#[allow(unused)]
 #[derive(Default, Clone)]
 pub struct DummyView {}
 
@@ -1,12 +1,209 @@
 use std::rc::Rc;
 
-use egui::{Response, Ui, Widget};
+use egui::{Context, Id, Rect, Response, RichText, Sense, Ui, Widget};
 
 use crate::{
-    utils::rss_client_ng::{EntryId, FolderId},
+    subscription::{feed::SyncRecord, order::SortMode},
+    utils::rss_client_ng::{EntryId, FolderId, RssClient, SyncFocus},
     view, RSSucks,
 };
 
+fn format_sync_time(time: Option<chrono::DateTime<chrono::Utc>>) -> String {
+    time.map_or("从未".to_owned(), |time| {
+        time.with_timezone(&chrono::Local)
+            .format("%Y/%m/%d %H:%M")
+            .to_string()
+    })
+}
+
+// Adds a badge of the number of unread articles, if there are any.
+fn unread_badge(ui: &mut Ui, count: Option<usize>) {
+    if let Some(count) = count.filter(|count| *count > 0) {
+        ui.label(
+            RichText::new(count.to_string())
+                .small()
+                .strong()
+                .color(ui.visuals().hyperlink_color),
+        )
+        .on_hover_text(format!("{count} 篇未读"));
+    }
+}
+
+/// Ages in days, articles older than which can be marked read.
+const MARK_READ_AGES: [i64; 4] = [1, 3, 7, 30];
+
+/// Adds the items of a menu which marks articles read,
+/// calling `mark` with the date before which articles are marked, if any.
+pub fn mark_read_menu(ui: &mut Ui, mark: impl Fn(Option<chrono::DateTime<chrono::Utc>>)) {
+    if ui.button("全部标为已读").clicked() {
+        mark(None);
+        ui.close_menu();
+    }
+    ui.menu_button("标记更早的文章为已读", |ui| {
+        for days in MARK_READ_AGES {
+            if ui.button(format!("{days} 天前")).clicked() {
+                mark(Some(chrono::Utc::now() - chrono::Duration::days(days)));
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+/// Key of the name being typed in the inline editor of a subscription or folder,
+/// which is only in the memory while it's being renamed.
+fn rename_key(id: impl std::hash::Hash) -> Id {
+    Id::new(("renaming", id))
+}
+
+/// Starts renaming inline a subscription or folder of the name.
+fn start_renaming(ui: &Ui, key: Id, name: String) {
+    ui.data_mut(|data| data.insert_temp(key, name));
+}
+
+/// Shows the inline editor of the name if it's being renamed, calling `rename` with the new name
+/// when Enter is pressed. Renaming is canceled when the editor loses focus otherwise.
+/// Returns whether it's being renamed.
+fn inline_rename(ui: &mut Ui, key: Id, rename: impl FnOnce(&str)) -> bool {
+    let Some(mut name) = ui.data(|data| data.get_temp::<String>(key)) else {
+        return false;
+    };
+    let response = ui.add(egui::TextEdit::singleline(&mut name).desired_width(120.0));
+    if response.lost_focus() {
+        if ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+            rename(&name);
+        }
+        ui.data_mut(|data| data.remove::<String>(key));
+    } else {
+        if !response.has_focus() {
+            response.request_focus();
+        }
+        ui.data_mut(|data| data.insert_temp(key, name));
+    }
+    true
+}
+
+fn dragged_entry_key() -> Id {
+    Id::new("dragged_entry")
+}
+
+/// Returns the subscription being dragged onto a folder, if any.
+pub fn dragged_entry(ctx: &Context) -> Option<EntryId> {
+    ctx.data(|data| data.get_temp(dragged_entry_key()))
+}
+
+/// Forgets the subscription being dragged once it's dropped.
+/// Should be called after all drop targets are shown.
+pub fn finish_entry_drag(ctx: &Context) {
+    if !ctx.input(|input| input.pointer.any_down()) {
+        ctx.data_mut(|data| data.remove::<EntryId>(dragged_entry_key()));
+    }
+}
+
+/// Makes the area a target onto which subscriptions are dropped,
+/// moving them into the folder, or out of folders if `to` is `None`.
+pub fn entry_drop_target(ui: &Ui, rect: Rect, app: &RSSucks, to: Option<FolderId>) {
+    let Some(entry_id) = dragged_entry(ui.ctx()) else {
+        return;
+    };
+    if !ui
+        .input(|input| input.pointer.hover_pos())
+        .is_some_and(|pos| rect.contains(pos))
+    {
+        return;
+    }
+    ui.painter()
+        .rect_stroke(rect, 2.0, ui.visuals().selection.stroke);
+    if ui.input(|input| input.pointer.any_released()) {
+        let _ = app.rss_client.try_move_entry(entry_id, to);
+    }
+}
+
+fn describe_sort_mode(mode: SortMode) -> &'static str {
+    match mode {
+        SortMode::Manual => "手动",
+        SortMode::Name => "按名称",
+        SortMode::Unread => "按未读数量",
+        SortMode::Updated => "按最近更新",
+    }
+}
+
+/// Adds the items of a menu which orders folders and subscriptions, either kept until changed,
+/// or once, after which they are in manual order and can be moved by hand.
+pub fn sort_menu(ui: &mut Ui, client: &RssClient) {
+    ui.label("保持排序");
+    for mode in SortMode::ALL {
+        if ui
+            .radio(client.sort_mode() == mode, describe_sort_mode(mode))
+            .clicked()
+        {
+            client.set_sort_mode(mode);
+            ui.close_menu();
+        }
+    }
+    ui.separator();
+    ui.menu_button("排序一次", |ui| {
+        for mode in SortMode::ALL {
+            if mode != SortMode::Manual && ui.button(describe_sort_mode(mode)).clicked() {
+                client.sort_once(mode);
+                client.set_sort_mode(SortMode::Manual);
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+/// Adds the items of a menu which moves a subscription or folder up or down by hand,
+/// calling `shift` with the offset, if they are in manual order.
+fn shift_menu(ui: &mut Ui, client: &RssClient, shift: impl Fn(isize)) {
+    if client.sort_mode() != SortMode::Manual {
+        return;
+    }
+    if ui.button("上移").clicked() {
+        shift(-1);
+        ui.close_menu();
+    }
+    if ui.button("下移").clicked() {
+        shift(1);
+        ui.close_menu();
+    }
+}
+
+/// Returns the offset by which the focused subscription or folder is moved
+/// with Alt+↑ or Alt+↓, if they are in manual order.
+fn shift_key(ui: &Ui, client: &RssClient) -> Option<isize> {
+    if client.sort_mode() != SortMode::Manual {
+        return None;
+    }
+    ui.input_mut(|input| {
+        if input.consume_key(egui::Modifiers::ALT, egui::Key::ArrowUp) {
+            Some(-1)
+        } else if input.consume_key(egui::Modifiers::ALT, egui::Key::ArrowDown) {
+            Some(1)
+        } else {
+            None
+        }
+    })
+}
+
+fn sync_error_tooltip(record: &SyncRecord) -> String {
+    let mut lines = vec![];
+    if let Some(error) = &record.error {
+        lines.push(format!("同步失败（{}）：{}", error.kind, error.message));
+    }
+    if let Some(status) = record.http_status {
+        lines.push(format!("HTTP 状态码：{status}"));
+    }
+    lines.push(format!(
+        "上次尝试：{}",
+        format_sync_time(record.last_attempt)
+    ));
+    lines.push(format!(
+        "上次成功：{}",
+        format_sync_time(record.last_success)
+    ));
+    lines.join("\n")
+}
+
 pub struct FeedMinimal<'a> {
     app: &'a RSSucks,
     id: EntryId,
@@ -20,37 +217,133 @@ impl<'a> FeedMinimal<'a> {
 
 impl<'a> Widget for FeedMinimal<'a> {
     fn ui(self, ui: &mut Ui) -> Response {
-        ui.allocate_ui(ui.available_size(), |ui| {
-            let feed = self.app.rss_client.get_entry(&self.id).unwrap();
-            ui.horizontal(|ui| {
-                let feed_button = ui.button(feed.get_name());
-
-                if feed_button.clicked() {
-                    self.app
-                        .set_view(Rc::new(Box::new(view::FeedFlowView::new(self.id))));
-                }
+        // Identified by the subscription, which keeps the focus when it's reordered.
+        ui.push_id(self.id, |ui| {
+            ui.allocate_ui(ui.available_size(), |ui| {
+                let feed = self.app.rss_client.get_entry(&self.id).unwrap();
+                ui.horizontal(|ui| {
+                    let is_dead = self.app.rss_client.entry_is_dead(self.id).unwrap_or(false);
+                    let next_refresh = if is_dead {
+                        "订阅已失效，不再自动刷新".to_owned()
+                    } else if self.app.scheduler.is_enabled() {
+                        format_sync_time(
+                            self.app
+                                .scheduler
+                                .next_refresh(&self.app.rss_client, self.id),
+                        )
+                    } else {
+                        "自动刷新已关闭".to_owned()
+                    };
+                    let mut hover_text = format!("下次刷新：{next_refresh}");
+                    if let Some(record) = self
+                        .app
+                        .rss_client
+                        .entry_sync_record(self.id)
+                        .filter(|record| record.pruned_articles > 0)
+                    {
+                        hover_text +=
+                            &format!("\n上次拉取后清理了 {} 篇旧文章", record.pruned_articles);
+                    }
+                    let rename_key = rename_key(self.id);
+                    if inline_rename(ui, rename_key, |name| {
+                        self.app.rss_client.rename_entry(self.id, name)
+                    }) {
+                        return;
+                    }
+                    let feed_button = ui
+                        .add(egui::Button::new(feed.get_name()).sense(Sense::click_and_drag()))
+                        .on_hover_text(
+                            hover_text
+                                + "\n拖到文件夹上以移动，F2 重命名，M 移动到…，Alt+↑/↓ 调整顺序",
+                        );
 
-                if self
-                    .app
-                    .rss_client
-                    .entry_is_syncing(self.id)
-                    .unwrap_or(false)
-                {
-                    ui.spinner();
-                }
+                    if feed_button.clicked() {
+                        self.app.set_view(Rc::new(Box::new(view::FlowView::new(
+                            view::FlowSource::Entry(self.id),
+                        ))));
+                    }
+                    if feed_button.drag_started() {
+                        ui.data_mut(|data| data.insert_temp(dragged_entry_key(), self.id));
+                    }
+                    if feed_button.dragged() {
+                        ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+                        egui::show_tooltip_at_pointer(
+                            ui.ctx(),
+                            Id::new("dragged_entry_tooltip"),
+                            |ui| {
+                                ui.label(feed.get_name());
+                            },
+                        );
+                    }
+                    if feed_button.has_focus() {
+                        if let Some(offset) = shift_key(ui, &self.app.rss_client) {
+                            let _ = self.app.rss_client.try_shift_entry(self.id, offset);
+                        } else if ui.input(|input| input.key_pressed(egui::Key::F2)) {
+                            start_renaming(ui, rename_key, feed.get_name());
+                        } else if ui.input(|input| input.key_pressed(egui::Key::M)) {
+                            self.app.add_window(view::MoveWindow::new(
+                                self.app.rss_client.clone(),
+                                view::Movable::Entry(self.id),
+                            ));
+                        }
+                    }
+                    feed_button.context_menu(|ui| {
+                        mark_read_menu(ui, |before| {
+                            let _ = self.app.rss_client.try_mark_entry_read(self.id, before);
+                        });
+                        ui.separator();
+                        if ui.button("重命名").clicked() {
+                            start_renaming(ui, rename_key, feed.get_name());
+                            ui.close_menu();
+                        }
+                        if ui.button("移动到…").clicked() {
+                            self.app.add_window(view::MoveWindow::new(
+                                self.app.rss_client.clone(),
+                                view::Movable::Entry(self.id),
+                            ));
+                            ui.close_menu();
+                        }
+                        shift_menu(ui, &self.app.rss_client, |offset| {
+                            let _ = self.app.rss_client.try_shift_entry(self.id, offset);
+                        });
+                    });
+                    unread_badge(ui, self.app.rss_client.unread_count_of_entry(self.id));
 
-                if ui
-                    .interact(ui.max_rect(), ui.id(), egui::Sense::hover())
-                    .hovered()
-                {
-                    if ui.button("🔁").on_hover_text("拉取文章").clicked() {
-                        self.app.rss_client.try_start_sync_entry(self.id).unwrap();
+                    if let Some(record) = self
+                        .app
+                        .rss_client
+                        .entry_sync_record(self.id)
+                        .filter(|record| record.error.is_some())
+                    {
+                        ui.label(RichText::new("⚠").color(ui.visuals().warn_fg_color))
+                            .on_hover_text(sync_error_tooltip(&record));
                     }
 
-                    if ui.button("🗙").on_hover_text("删除订阅").clicked() {
-                        self.app.rss_client.delete_entry(self.id);
+                    if ui
+                        .interact(ui.max_rect(), ui.id(), egui::Sense::hover())
+                        .hovered()
+                    {
+                        if ui.button("🔁").on_hover_text("拉取文章").clicked() {
+                            self.app.rss_client.try_start_sync_entry(self.id).unwrap();
+                        }
+
+                        if ui.button("✏").on_hover_text("重命名").clicked() {
+                            start_renaming(ui, rename_key, feed.get_name());
+                        }
+
+                        if ui.button("⚙").on_hover_text("订阅设置").clicked() {
+                            self.app.add_window(view::EntrySettingsWindow::new(
+                                self.app.rss_client.clone(),
+                                self.app.scheduler.clone(),
+                                self.id,
+                            ));
+                        }
+
+                        if ui.button("🗙").on_hover_text("删除订阅").clicked() {
+                            self.app.rss_client.delete_entry(self.id);
+                        }
                     }
-                }
+                })
             })
         })
         .response
@@ -71,9 +364,23 @@ impl<'app> CollapsingFolder<'app> {
 impl<'app> Widget for CollapsingFolder<'app> {
     fn ui(self, ui: &mut Ui) -> Response {
         let folder = self.app.rss_client.get_folder(&self.folder_id).unwrap();
-        let response = egui::CollapsingHeader::new(folder.name()).show(ui, |ui| {
+        let name = match self.app.rss_client.unread_count_of_folder(self.folder_id) {
+            Some(count) if count > 0 => format!("{} ({count})", folder.name()),
+            _ => folder.name(),
+        };
+        let rename_key = rename_key(self.folder_id);
+        let move_folder = || {
+            self.app.add_window(view::MoveWindow::new(
+                self.app.rss_client.clone(),
+                view::Movable::Folder(self.folder_id),
+            ));
+        };
+        let body = |ui: &mut Ui| {
             ui.horizontal(|ui| {
                 if ui.button("🔁").on_hover_text("拉取文章").clicked() {
+                    self.app
+                        .rss_client
+                        .set_sync_focus(Some(SyncFocus::Folder(self.folder_id)));
                     self.app
                         .rss_client
                         .try_start_sync_folder(self.folder_id)
@@ -85,20 +392,105 @@ impl<'app> Widget for CollapsingFolder<'app> {
                         Some(self.folder_id),
                     ));
                 }
+                if ui.button("📁").on_hover_text("新建子文件夹").clicked() {
+                    self.app.add_window(view::NewFolderWindow::new(
+                        self.app.rss_client.clone(),
+                        Some(self.folder_id),
+                    ));
+                }
+                ui.menu_button("✔", |ui| {
+                    mark_read_menu(ui, |before| {
+                        let _ = self
+                            .app
+                            .rss_client
+                            .try_mark_folder_read(self.folder_id, before);
+                    });
+                })
+                .response
+                .on_hover_text("标为已读");
                 if ui
                     .interact(ui.max_rect(), ui.id(), egui::Sense::hover())
                     .hovered()
-                    && ui.button("🗙").on_hover_text("删除文件夹").clicked()
                 {
-                    self.app.rss_client.delete_folder(self.folder_id).unwrap();
+                    if ui.button("✏").on_hover_text("重命名").clicked() {
+                        start_renaming(ui, rename_key, folder.name());
+                    }
+                    if ui.button("↪").on_hover_text("移动到…").clicked() {
+                        move_folder();
+                    }
+                    if ui.button("⚙").on_hover_text("文件夹设置").clicked() {
+                        self.app.add_window(view::FolderSettingsWindow::new(
+                            self.app.rss_client.clone(),
+                            self.folder_id,
+                        ));
+                    }
+                    if ui.button("🗙").on_hover_text("删除文件夹").clicked() {
+                        self.app.rss_client.delete_folder(self.folder_id).unwrap();
+                    }
                 }
             });
+            if let Ok(folder_ids) = self.app.rss_client.try_list_subfolder(self.folder_id) {
+                for folder_id in folder_ids {
+                    ui.add(CollapsingFolder::new(self.app, folder_id));
+                }
+            }
             if let Ok(feed_ids) = self.app.rss_client.try_list_entry_by_folder(self.folder_id) {
                 for feed_id in feed_ids {
                     ui.add(FeedMinimal::new(self.app, feed_id));
                 }
             }
+        };
+
+        // Identified by the folder rather than the name, which changes with the count.
+        let id = ui.make_persistent_id(self.folder_id.get());
+        if ui.data(|data| data.get_temp::<String>(rename_key).is_some()) {
+            // The header can't hold the editor, so a custom one with the same state is shown.
+            let (_, header, body) =
+                egui::collapsing_header::CollapsingState::load_with_default_open(
+                    ui.ctx(),
+                    id,
+                    false,
+                )
+                .show_header(ui, |ui| {
+                    inline_rename(ui, rename_key, |name| {
+                        self.app.rss_client.rename_folder(self.folder_id, name)
+                    })
+                })
+                .body(body);
+            return body.map_or(header.response, |body| body.response);
+        }
+        let response = egui::CollapsingHeader::new(name)
+            .id_source(self.folder_id.get())
+            .show(ui, body);
+        let header = &response.header_response;
+        entry_drop_target(ui, header.rect, self.app, Some(self.folder_id));
+        if header.clicked() {
+            self.app.set_view(Rc::new(Box::new(view::FlowView::new(
+                view::FlowSource::Folder(self.folder_id),
+            ))));
+        }
+        if header.has_focus() {
+            if let Some(offset) = shift_key(ui, &self.app.rss_client) {
+                let _ = self.app.rss_client.try_shift_folder(self.folder_id, offset);
+            } else if ui.input(|input| input.key_pressed(egui::Key::F2)) {
+                start_renaming(ui, rename_key, folder.name());
+            } else if ui.input(|input| input.key_pressed(egui::Key::M)) {
+                move_folder();
+            }
+        }
+        let header = response.header_response.context_menu(|ui| {
+            if ui.button("重命名").clicked() {
+                start_renaming(ui, rename_key, folder.name());
+                ui.close_menu();
+            }
+            if ui.button("移动到…").clicked() {
+                move_folder();
+                ui.close_menu();
+            }
+            shift_menu(ui, &self.app.rss_client, |offset| {
+                let _ = self.app.rss_client.try_shift_folder(self.folder_id, offset);
+            });
         });
-        response.body_response.unwrap_or(response.header_response)
+        response.body_response.unwrap_or(header)
     }
 }